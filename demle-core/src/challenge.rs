@@ -0,0 +1,241 @@
+use crate::merkle::{InclusionProof, TILE_SIZE};
+use crate::proof::parse_hash;
+use crate::{DemleError, MLOperation, Result, WorkResult};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// One output tile of one operation of a work unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TileIndex {
+    pub operation: usize,
+    pub tile: usize,
+}
+
+/// Tiles a challenger asks a miner to open after the work was submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub work_id: String,
+    /// Hash of a block mined after the submission, which seeds the sampling
+    pub block_hash: String,
+    pub tiles: Vec<TileIndex>,
+}
+
+/// Number of output tiles of an operation whose tiles can be recomputed on
+/// their own. Only seeded GEMMs can be spot-checked so far; other operations
+/// are rejected as not challengeable.
+pub fn challengeable_tiles(operation: &MLOperation) -> Result<usize> {
+    match operation {
+        MLOperation::MatrixMultiply {
+            dimensions: (m, _, n),
+            ..
+        } => Ok((m * n).div_ceil(TILE_SIZE).max(1)),
+        _ => Err(DemleError::ValidationError(format!(
+            "{} is not challengeable",
+            operation
+        ))),
+    }
+}
+
+impl Challenge {
+    /// Sample `samples` tiles uniformly over all challengeable tiles of the
+    /// work unit's operations. The block hash must not be known when the work
+    /// is submitted, so the miner can't predict which tiles will be checked.
+    /// It is hashed as its 32 bytes, so every spelling of it samples the
+    /// same tiles. Operations that aren't challengeable are never sampled.
+    pub fn derive(
+        block_hash: &str,
+        operations: &[MLOperation],
+        result: &WorkResult,
+        samples: usize,
+    ) -> Result<Self> {
+        let block_hash_bytes = parse_hash(block_hash)?;
+        let tile_counts: Vec<(usize, usize)> = operations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| challengeable_tiles(op).ok().map(|tiles| (i, tiles)))
+            .collect();
+        let total: usize = tile_counts.iter().map(|&(_, tiles)| tiles).sum();
+        if total == 0 {
+            return Err(DemleError::ValidationError(format!(
+                "Work unit {} has no operations that can be spot-checked",
+                result.work_id
+            )));
+        }
+
+        let mut seed = Sha3_256::new();
+        seed.update(b"demle-challenge");
+        seed.update(block_hash_bytes);
+        seed.update(result.work_id.as_bytes());
+        seed.update(result.hash.as_bytes());
+        let seed = seed.finalize();
+
+        let tiles = (0..samples as u64)
+            .map(|i| {
+                let mut hasher = Sha3_256::new();
+                hasher.update(seed);
+                hasher.update(i.to_le_bytes());
+                let digest = hasher.finalize();
+                let draw = u64::from_le_bytes(digest[..8].try_into().unwrap());
+                let mut position = (draw % total as u64) as usize;
+
+                let mut sampled = TileIndex {
+                    operation: 0,
+                    tile: 0,
+                };
+                for &(operation, tiles) in &tile_counts {
+                    if position < tiles {
+                        sampled = TileIndex {
+                            operation,
+                            tile: position,
+                        };
+                        break;
+                    }
+                    position -= tiles;
+                }
+                sampled
+            })
+            .collect();
+
+        Ok(Self {
+            work_id: result.work_id.clone(),
+            block_hash: block_hash.to_string(),
+            tiles,
+        })
+    }
+}
+
+/// Where a tile's inputs come from, so the verifier can regenerate only what
+/// the tile depends on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputCoordinates {
+    /// Tile elements `elements.0..elements.1` of a seeded GEMM output; they
+    /// read rows `rows.0..rows.1` of A and all of B
+    Gemm {
+        dimensions: (usize, usize, usize),
+        seed: u64,
+        rows: (usize, usize),
+        elements: (usize, usize),
+    },
+}
+
+/// A challenged tile with its bytes and the path to the operation's root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileOpening {
+    pub index: TileIndex,
+    pub data: Vec<u8>,
+    pub proof: InclusionProof,
+    pub inputs: InputCoordinates,
+}
+
+/// Miner's answer to a challenge, one opening per challenged tile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChallengeResponse {
+    pub work_id: String,
+    pub openings: Vec<TileOpening>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn work_result() -> WorkResult {
+        WorkResult {
+            work_id: "work-1".to_string(),
+            nonce: 0,
+            hash: "ab".repeat(32),
+            execution_time_ms: 0,
+            total_flops: 0,
+            operation_results: Vec::new(),
+            node_hashes: Vec::new(),
+            beneficiary: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_challenge_derivation() {
+        let operations = vec![
+            MLOperation::Softmax {
+                rows: 4,
+                cols: 4,
+                log: false,
+                seed: 1,
+            },
+            MLOperation::MatrixMultiply {
+                dimensions: (64, 8, 64),
+                seed: 2,
+            },
+            MLOperation::MatrixMultiply {
+                dimensions: (2, 2, 2),
+                seed: 3,
+            },
+        ];
+        let result = work_result();
+        let block_a = format!("0x{}", "a1".repeat(32));
+        let block_b = format!("0x{}", "b2".repeat(32));
+
+        let challenge = Challenge::derive(&block_a, &operations, &result, 32).unwrap();
+        assert_eq!(challenge.tiles.len(), 32);
+        assert!(challenge.tiles.iter().all(|t| match t.operation {
+            1 => t.tile < 4,
+            2 => t.tile == 0,
+            _ => false,
+        }));
+
+        // Deterministic in the block hash, and different for another block
+        assert_eq!(
+            Challenge::derive(&block_a, &operations, &result, 32).unwrap(),
+            challenge
+        );
+        assert_ne!(
+            Challenge::derive(&block_b, &operations, &result, 32)
+                .unwrap()
+                .tiles,
+            challenge.tiles
+        );
+
+        assert!(Challenge::derive(&block_a, &operations[..1], &result, 4).is_err());
+    }
+
+    #[test]
+    fn test_challenge_block_hash_spellings() {
+        let operations = vec![MLOperation::MatrixMultiply {
+            dimensions: (64, 8, 64),
+            seed: 2,
+        }];
+        let result = work_result();
+        let lower = "ab".repeat(32);
+
+        // Case and the 0x prefix don't change the sampled tiles
+        let challenge = Challenge::derive(&lower, &operations, &result, 8).unwrap();
+        for spelling in [format!("0x{}", lower), lower.to_uppercase()] {
+            assert_eq!(
+                Challenge::derive(&spelling, &operations, &result, 8)
+                    .unwrap()
+                    .tiles,
+                challenge.tiles
+            );
+        }
+
+        for malformed in ["block-a", "0xabcd", ""] {
+            assert!(Challenge::derive(malformed, &operations, &result, 8).is_err());
+        }
+    }
+
+    #[test]
+    fn test_challengeable_tiles() {
+        let gemm = MLOperation::MatrixMultiply {
+            dimensions: (64, 8, 64),
+            seed: 2,
+        };
+        assert_eq!(challengeable_tiles(&gemm).unwrap(), 4);
+
+        let softmax = MLOperation::Softmax {
+            rows: 4,
+            cols: 4,
+            log: false,
+            seed: 1,
+        };
+        assert!(challengeable_tiles(&softmax).is_err());
+    }
+}
@@ -29,10 +29,71 @@ pub fn difficulty_to_teraflops(difficulty: u64) -> f64 {
     difficulty as f64 / 1e6
 }
 
+/// 256-bit big-endian proof-of-work target for a difficulty: the largest hash
+/// value accepted, ⌊(2²⁵⁶ − 1) / difficulty⌋. Difficulty 0 is treated as 1.
+pub fn difficulty_to_target(difficulty: u64) -> [u8; 32] {
+    let divisor = difficulty.max(1) as u128;
+    let mut target = [0u8; 32];
+    let mut remainder: u128 = 0;
+    // Long division of 0xff…ff, one byte at a time
+    for byte in target.iter_mut() {
+        let value = (remainder << 8) | 0xff;
+        *byte = (value / divisor) as u8;
+        remainder = value % divisor;
+    }
+    target
+}
+
+/// Whether a 256-bit big-endian hash is at or below the target
+pub fn meets_target(hash: &[u8; 32], target: &[u8; 32]) -> bool {
+    hash <= target
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_difficulty_to_target_vectors() {
+        let vectors: [(u64, &str); 6] = [
+            (
+                1,
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            ),
+            (
+                2,
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            ),
+            (
+                16,
+                "0fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            ),
+            (
+                1000,
+                "004189374bc6a7ef9db22d0e5604189374bc6a7ef9db22d0e5604189374bc6a7",
+            ),
+            (
+                0x1_0000_0000,
+                "00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            ),
+            (
+                u64::MAX,
+                "0000000000000001000000000000000100000000000000010000000000000001",
+            ),
+        ];
+        for (difficulty, target) in vectors {
+            assert_eq!(hex::encode(difficulty_to_target(difficulty)), target);
+        }
+        assert_eq!(difficulty_to_target(0), difficulty_to_target(1));
+
+        // The target itself is accepted, the next hash value up is not
+        let target = difficulty_to_target(3);
+        let mut hash = target;
+        assert!(meets_target(&hash, &target));
+        hash[31] = hash[31].wrapping_add(1);
+        assert!(!meets_target(&hash, &target));
+    }
+
     #[test]
     fn test_difficulty_adjustment() {
         let current = 1000000;
@@ -0,0 +1,1168 @@
+use crate::proof::{parse_hash, BlockHeader, Proof, HEADER_LEN};
+use crate::seed::parse_address;
+use crate::signing::SIGNATURE_LEN;
+use crate::{
+    DemleError, MLOperation, OperationResult, Optimizer, Result, TensorInput, TensorOp, WorkResult,
+    WorkUnit,
+};
+
+/// Leading bytes of every encoded message
+pub const MAGIC: [u8; 4] = *b"DMLE";
+
+/// Version of the binary encoding. A message is `MAGIC ‖ version (u8) ‖ kind
+/// (u8) ‖ body ‖ extensions`, with integers little-endian, hashes as raw 32
+/// bytes, names as `length (u16) ‖ UTF-8` and lists as `count (u32) ‖ items`.
+/// Changing the body layout needs a new version, which older decoders reject.
+pub const ENCODING_VERSION: u8 = 1;
+
+/// Largest message accepted, in bytes; about 2M gas as calldata
+pub const MAX_ENCODED_LEN: usize = 128 * 1024;
+
+/// Longest work id or hash name, in bytes
+pub const MAX_NAME_LEN: usize = 256;
+
+/// Extensions are `tag (u16) ‖ length (u32) ‖ bytes` records after the body,
+/// in strictly increasing tag order. Decoders skip tags they don't know below
+/// this one and reject unknown tags from it upwards, so fields can be added
+/// without a new version as long as older decoders may ignore them.
+pub const REQUIRED_EXTENSION: u16 = 0x8000;
+
+/// What an encoded message holds, the byte after the version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageKind {
+    Proof = 1,
+    WorkResult = 2,
+    WorkUnit = 3,
+}
+
+fn encoding_error(message: impl Into<String>) -> DemleError {
+    DemleError::SerializationError(message.into())
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn new(kind: MessageKind) -> Self {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[ENCODING_VERSION, kind as u8]);
+        Self { bytes }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn shape(&mut self, shape: &[usize]) -> Result<()> {
+        self.count(shape.len())?;
+        for &dim in shape {
+            self.usize(dim);
+        }
+        Ok(())
+    }
+
+    fn count(&mut self, count: usize) -> Result<()> {
+        let count = u32::try_from(count)
+            .map_err(|_| encoding_error(format!("{} items don't fit a list", count)))?;
+        self.bytes.extend_from_slice(&count.to_le_bytes());
+        Ok(())
+    }
+
+    fn hash(&mut self, hash: &str) -> Result<()> {
+        self.bytes.extend_from_slice(&parse_hash(hash)?);
+        Ok(())
+    }
+
+    fn name(&mut self, name: &str) -> Result<()> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(encoding_error(format!(
+                "Name of {} bytes exceeds {}",
+                name.len(),
+                MAX_NAME_LEN
+            )));
+        }
+        self.bytes
+            .extend_from_slice(&(name.len() as u16).to_le_bytes());
+        self.bytes.extend_from_slice(name.as_bytes());
+        Ok(())
+    }
+
+    /// `0` for an unsigned message, or `1 ‖ beneficiary ‖ signature`
+    fn signature(&mut self, beneficiary: &str, signature: &str) -> Result<()> {
+        if beneficiary.is_empty() && signature.is_empty() {
+            self.u8(0);
+            return Ok(());
+        }
+        let signature = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+            .ok()
+            .filter(|bytes| bytes.len() == SIGNATURE_LEN)
+            .ok_or_else(|| {
+                encoding_error(format!("Expected a {}-byte signature", SIGNATURE_LEN))
+            })?;
+        self.u8(1);
+        self.bytes.extend_from_slice(&parse_address(beneficiary)?);
+        self.bytes.extend_from_slice(&signature);
+        Ok(())
+    }
+
+    /// `tag (u8) ‖ fields`, see [`WorkUnit::to_binary`]
+    fn operation(&mut self, operation: &MLOperation) -> Result<()> {
+        match operation {
+            MLOperation::MatrixMultiply { dimensions, seed } => {
+                self.u8(0);
+                self.dimensions(*dimensions);
+                self.u64(*seed);
+            }
+            MLOperation::Convolution2D {
+                input_shape,
+                kernel_shape,
+                stride,
+                padding,
+                seed,
+            } => {
+                self.u8(1);
+                self.nchw(*input_shape);
+                self.nchw(*kernel_shape);
+                self.pair(*stride);
+                self.pair(*padding);
+                self.u64(*seed);
+            }
+            MLOperation::MultiHeadAttention {
+                batch_size,
+                seq_length,
+                d_model,
+                num_heads,
+                seed,
+            } => {
+                self.u8(2);
+                self.attention(*batch_size, *seq_length, *d_model, *num_heads);
+                self.u64(*seed);
+            }
+            MLOperation::BatchNormalization {
+                shape,
+                epsilon,
+                seed,
+            } => {
+                self.u8(3);
+                self.nchw(*shape);
+                self.f32(*epsilon);
+                self.u64(*seed);
+            }
+            MLOperation::TransformerBlock {
+                batch_size,
+                seq_length,
+                d_model,
+                num_heads,
+                d_ff,
+                feed_forward,
+                seed,
+            } => {
+                self.u8(4);
+                self.attention(*batch_size, *seq_length, *d_model, *num_heads);
+                self.usize(*d_ff);
+                self.u8(*feed_forward as u8);
+                self.u64(*seed);
+            }
+            MLOperation::GatedFeedForward {
+                tokens,
+                d_model,
+                d_ff,
+                activation,
+                seed,
+            } => {
+                self.u8(5);
+                self.dimensions((*tokens, *d_model, *d_ff));
+                self.u8(*activation as u8);
+                self.u64(*seed);
+            }
+            MLOperation::Pooling2D {
+                input_shape,
+                pool_type,
+                kernel_size,
+                stride,
+                padding,
+                seed,
+            } => {
+                self.u8(6);
+                self.nchw(*input_shape);
+                self.u8(*pool_type as u8);
+                self.pair(*kernel_size);
+                self.pair(*stride);
+                self.pair(*padding);
+                self.u64(*seed);
+            }
+            MLOperation::EmbeddingLookup {
+                vocab_size,
+                embedding_dim,
+                num_tokens,
+                seed,
+            } => {
+                self.u8(7);
+                self.dimensions((*vocab_size, *embedding_dim, *num_tokens));
+                self.u64(*seed);
+            }
+            MLOperation::Softmax {
+                rows,
+                cols,
+                log,
+                seed,
+            } => {
+                self.u8(8);
+                self.pair((*rows, *cols));
+                self.bool(*log);
+                self.u64(*seed);
+            }
+            MLOperation::MatrixMultiplyBackward { dimensions, seed } => {
+                self.u8(9);
+                self.dimensions(*dimensions);
+                self.u64(*seed);
+            }
+            MLOperation::Convolution2DBackward {
+                input_shape,
+                kernel_shape,
+                stride,
+                padding,
+                seed,
+            } => {
+                self.u8(10);
+                self.nchw(*input_shape);
+                self.nchw(*kernel_shape);
+                self.pair(*stride);
+                self.pair(*padding);
+                self.u64(*seed);
+            }
+            MLOperation::MultiHeadAttentionBackward {
+                batch_size,
+                seq_length,
+                d_model,
+                num_heads,
+                seed,
+            } => {
+                self.u8(11);
+                self.attention(*batch_size, *seq_length, *d_model, *num_heads);
+                self.u64(*seed);
+            }
+            MLOperation::OptimizerStep {
+                num_params,
+                optimizer,
+                master_weights,
+                seed,
+            } => {
+                self.u8(12);
+                self.usize(*num_params);
+                self.optimizer(optimizer);
+                self.u8(*master_weights as u8);
+                self.u64(*seed);
+            }
+            MLOperation::TrainingStep {
+                batch_size,
+                input_dim,
+                hidden_dim,
+                num_classes,
+                optimizer,
+                master_weights,
+                seed,
+            } => {
+                self.u8(13);
+                self.nchw((*batch_size, *input_dim, *hidden_dim, *num_classes));
+                self.optimizer(optimizer);
+                self.u8(*master_weights as u8);
+                self.u64(*seed);
+            }
+            MLOperation::BatchedMatrixMultiply {
+                batch_size,
+                dimensions,
+                a_operand,
+                b_operand,
+                transpose_a,
+                transpose_b,
+                seed,
+            } => {
+                self.u8(14);
+                self.usize(*batch_size);
+                self.dimensions(*dimensions);
+                self.u8(*a_operand as u8);
+                self.u8(*b_operand as u8);
+                self.bool(*transpose_a);
+                self.bool(*transpose_b);
+                self.u64(*seed);
+            }
+            MLOperation::SparseMatrixMultiply { dimensions, seed } => {
+                self.u8(15);
+                self.dimensions(*dimensions);
+                self.u64(*seed);
+            }
+            MLOperation::MixtureOfExperts {
+                tokens,
+                d_model,
+                d_ff,
+                num_experts,
+                top_k,
+                capacity_factor,
+                seed,
+            } => {
+                self.u8(16);
+                self.dimensions((*tokens, *d_model, *d_ff));
+                self.pair((*num_experts, *top_k));
+                self.f32(*capacity_factor);
+                self.u64(*seed);
+            }
+            MLOperation::Quantize {
+                num_elements,
+                source,
+                target,
+                seed,
+            } => {
+                self.u8(17);
+                self.usize(*num_elements);
+                self.u8(*source as u8);
+                self.u8(*target as u8);
+                self.u64(*seed);
+            }
+            MLOperation::TensorOperation { op, inputs, output } => {
+                self.u8(18);
+                self.tensor_op(op)?;
+                self.count(inputs.len())?;
+                for input in inputs {
+                    match input {
+                        TensorInput::Stored(tensor) => {
+                            self.u8(0);
+                            self.hash(&tensor.hash)?;
+                            self.shape(&tensor.shape)?;
+                        }
+                        TensorInput::Edge { name, shape } => {
+                            self.u8(1);
+                            self.name(name)?;
+                            self.shape(shape)?;
+                        }
+                    }
+                }
+                match output {
+                    None => self.u8(0),
+                    Some(name) => {
+                        self.u8(1);
+                        self.name(name)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn pair(&mut self, (a, b): (usize, usize)) {
+        self.usize(a);
+        self.usize(b);
+    }
+
+    fn dimensions(&mut self, (a, b, c): (usize, usize, usize)) {
+        self.usize(a);
+        self.pair((b, c));
+    }
+
+    fn nchw(&mut self, (a, b, c, d): (usize, usize, usize, usize)) {
+        self.pair((a, b));
+        self.pair((c, d));
+    }
+
+    fn attention(&mut self, batch_size: usize, seq_length: usize, d_model: usize, heads: usize) {
+        self.nchw((batch_size, seq_length, d_model, heads));
+    }
+
+    fn optimizer(&mut self, optimizer: &Optimizer) {
+        match *optimizer {
+            Optimizer::SgdMomentum {
+                learning_rate,
+                momentum,
+                weight_decay,
+            } => {
+                self.u8(0);
+                self.f32(learning_rate);
+                self.f32(momentum);
+                self.f32(weight_decay);
+            }
+            Optimizer::AdamW {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+                step,
+            } => {
+                self.u8(1);
+                self.f32(learning_rate);
+                self.f32(beta1);
+                self.f32(beta2);
+                self.f32(epsilon);
+                self.f32(weight_decay);
+                self.u64(step);
+            }
+        }
+    }
+
+    fn tensor_op(&mut self, op: &TensorOp) -> Result<()> {
+        match op {
+            TensorOp::MatMul => self.u8(0),
+            TensorOp::Conv2D { stride, padding } => {
+                self.u8(1);
+                self.pair(*stride);
+                self.pair(*padding);
+            }
+            TensorOp::MultiHeadAttention { num_heads } => {
+                self.u8(2);
+                self.usize(*num_heads);
+            }
+            TensorOp::Softmax { log } => {
+                self.u8(3);
+                self.bool(*log);
+            }
+            TensorOp::LayerNorm { epsilon } => {
+                self.u8(4);
+                self.f32(*epsilon);
+            }
+            TensorOp::Activation(activation) => {
+                self.u8(5);
+                self.u8(*activation as u8);
+            }
+            TensorOp::Add => self.u8(6),
+            TensorOp::Pooling2D {
+                pool_type,
+                kernel_size,
+                stride,
+                padding,
+            } => {
+                self.u8(7);
+                self.u8(*pool_type as u8);
+                self.pair(*kernel_size);
+                self.pair(*stride);
+                self.pair(*padding);
+            }
+            TensorOp::Reshape { shape } => {
+                self.u8(8);
+                self.shape(shape)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        if self.bytes.len() > MAX_ENCODED_LEN {
+            return Err(encoding_error(format!(
+                "Encoding of {} bytes exceeds {}",
+                self.bytes.len(),
+                MAX_ENCODED_LEN
+            )));
+        }
+        Ok(self.bytes)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Check the size, magic, version and kind of a message
+    fn open(bytes: &'a [u8], kind: MessageKind) -> Result<Self> {
+        if bytes.len() > MAX_ENCODED_LEN {
+            return Err(encoding_error(format!(
+                "Message of {} bytes exceeds {}",
+                bytes.len(),
+                MAX_ENCODED_LEN
+            )));
+        }
+        let mut reader = Self { bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(encoding_error("Not a DEMLE message"));
+        }
+        let version = reader.u8()?;
+        if version != ENCODING_VERSION {
+            return Err(encoding_error(format!(
+                "Unsupported encoding version {}",
+                version
+            )));
+        }
+        let found = reader.u8()?;
+        if found != kind as u8 {
+            return Err(encoding_error(format!(
+                "Expected a {:?} message, found kind {}",
+                kind, found
+            )));
+        }
+        Ok(reader)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(encoding_error(format!(
+                "Truncated message: {} bytes needed at offset {}",
+                len, self.position
+            )));
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    /// List length, rejected before allocating if the items can't fit the
+    /// rest of the message
+    fn count(&mut self, min_item_len: usize) -> Result<usize> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(min_item_len) > self.remaining() {
+            return Err(encoding_error(format!(
+                "List of {} items overruns the message",
+                count
+            )));
+        }
+        Ok(count)
+    }
+
+    fn hash(&mut self) -> Result<String> {
+        Ok(hex::encode(self.take(32)?))
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        if len > MAX_NAME_LEN {
+            return Err(encoding_error(format!(
+                "Name of {} bytes exceeds {}",
+                len, MAX_NAME_LEN
+            )));
+        }
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| encoding_error("Name is not valid UTF-8"))
+    }
+
+    /// Beneficiary and signature, both empty for an unsigned message
+    fn signature(&mut self) -> Result<(String, String)> {
+        match self.u8()? {
+            0 => Ok((String::new(), String::new())),
+            1 => {
+                let beneficiary = format!("0x{}", hex::encode(self.take(20)?));
+                Ok((beneficiary, hex::encode(self.take(SIGNATURE_LEN)?)))
+            }
+            flag => Err(encoding_error(format!("Invalid signature flag {}", flag))),
+        }
+    }
+
+    /// Walk the extensions after the body. Optional extensions are checked
+    /// and then dropped, so they don't survive re-encoding.
+    fn finish(mut self) -> Result<()> {
+        let mut previous: Option<u16> = None;
+        while self.remaining() > 0 {
+            let tag = self.u16()?;
+            let len = self.u32()? as usize;
+            self.take(len)?;
+            if previous.is_some_and(|previous| tag <= previous) {
+                return Err(encoding_error(format!(
+                    "Extension {:#06x} is out of order",
+                    tag
+                )));
+            }
+            if tag >= REQUIRED_EXTENSION {
+                return Err(encoding_error(format!(
+                    "Unknown required extension {:#06x}",
+                    tag
+                )));
+            }
+            previous = Some(tag);
+        }
+        Ok(())
+    }
+}
+
+impl Proof {
+    /// Deterministic binary encoding: `header ‖ operation hashes ‖ total FLOPs ‖
+    /// timestamp ‖ signature`. The work hash is recomputed from the header on
+    /// decoding rather than encoded.
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut writer = Writer::new(MessageKind::Proof);
+        writer.bytes.extend_from_slice(&self.header.to_bytes());
+        writer.count(self.operation_hashes.len())?;
+        for hash in &self.operation_hashes {
+            writer.hash(hash)?;
+        }
+        writer.u64(self.total_flops);
+        writer.u64(self.timestamp);
+        writer.signature(&self.beneficiary, &self.signature)?;
+        writer.finish()
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::open(bytes, MessageKind::Proof)?;
+        let header = BlockHeader::from_bytes(&reader.array::<HEADER_LEN>()?);
+        let operation_hashes = (0..reader.count(32)?)
+            .map(|_| reader.hash())
+            .collect::<Result<_>>()?;
+        let total_flops = reader.u64()?;
+        let timestamp = reader.u64()?;
+        let (beneficiary, signature) = reader.signature()?;
+        reader.finish()?;
+
+        Ok(Self {
+            work_hash: hex::encode(header.hash()),
+            header,
+            operation_hashes,
+            total_flops,
+            timestamp,
+            beneficiary,
+            signature,
+        })
+    }
+}
+
+/// Smallest encoding of one operation result: hash, FLOPs, time and two
+/// empty lists
+const MIN_OPERATION_RESULT_LEN: usize = 32 + 8 + 8 + 4 + 4;
+
+impl WorkResult {
+    /// Deterministic binary encoding of every field, in declaration order
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut writer = Writer::new(MessageKind::WorkResult);
+        writer.name(&self.work_id)?;
+        writer.u64(self.nonce);
+        writer.hash(&self.hash)?;
+        writer.u64(self.execution_time_ms);
+        writer.u64(self.total_flops);
+
+        writer.count(self.operation_results.len())?;
+        for result in &self.operation_results {
+            writer.hash(&result.result_hash)?;
+            writer.u64(result.flops);
+            writer.u64(result.execution_time_ms);
+            writer.count(result.intermediate_hashes.len())?;
+            for (name, hash) in &result.intermediate_hashes {
+                writer.name(name)?;
+                writer.hash(hash)?;
+            }
+            writer.count(result.metrics.len())?;
+            for (name, value) in &result.metrics {
+                writer.name(name)?;
+                writer.f64(*value);
+            }
+        }
+
+        writer.count(self.node_hashes.len())?;
+        for (name, hash) in &self.node_hashes {
+            writer.name(name)?;
+            writer.hash(hash)?;
+        }
+        writer.signature(&self.beneficiary, &self.signature)?;
+        writer.finish()
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::open(bytes, MessageKind::WorkResult)?;
+        let work_id = reader.name()?;
+        let nonce = reader.u64()?;
+        let hash = reader.hash()?;
+        let execution_time_ms = reader.u64()?;
+        let total_flops = reader.u64()?;
+
+        let mut operation_results = Vec::new();
+        for _ in 0..reader.count(MIN_OPERATION_RESULT_LEN)? {
+            let result_hash = reader.hash()?;
+            let flops = reader.u64()?;
+            let execution_time_ms = reader.u64()?;
+            let intermediate_hashes = (0..reader.count(2 + 32)?)
+                .map(|_| Ok((reader.name()?, reader.hash()?)))
+                .collect::<Result<_>>()?;
+            let metrics = (0..reader.count(2 + 8)?)
+                .map(|_| Ok((reader.name()?, reader.f64()?)))
+                .collect::<Result<_>>()?;
+            operation_results.push(OperationResult {
+                result_hash,
+                flops,
+                execution_time_ms,
+                intermediate_hashes,
+                metrics,
+            });
+        }
+
+        let node_hashes = (0..reader.count(2 + 32)?)
+            .map(|_| Ok((reader.name()?, reader.hash()?)))
+            .collect::<Result<_>>()?;
+        let (beneficiary, signature) = reader.signature()?;
+        reader.finish()?;
+
+        Ok(Self {
+            work_id,
+            nonce,
+            hash,
+            execution_time_ms,
+            total_flops,
+            operation_results,
+            node_hashes,
+            beneficiary,
+            signature,
+        })
+    }
+}
+
+impl WorkUnit {
+    /// Deterministic binary encoding that [`WorkUnit::commitment`] hashes:
+    /// `id ‖ previous hash ‖ timestamp ‖ difficulty ‖ operations ‖ nonce range
+    /// ‖ miner ‖ sequence`, the miner being `0` when unset or `1 ‖ address`.
+    ///
+    /// An operation is its variant index in [`MLOperation`] (u8) followed by
+    /// its fields in declaration order: sizes as u64, `f32` as its IEEE bits
+    /// (u32), bools as u8, tuples element by element, fieldless enums as their
+    /// variant index (u8), and [`Optimizer`], [`TensorOp`] and [`TensorInput`]
+    /// as their variant index followed by their fields. Shapes are lists of
+    /// u64 and the output of a tensor operation is `0` or `1 ‖ name`.
+    /// Work units are exchanged as JSON, so there is no decoder, and the
+    /// encoding is only hashed, so it is not capped at [`MAX_ENCODED_LEN`].
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut writer = Writer::new(MessageKind::WorkUnit);
+        writer.name(&self.id)?;
+        writer.hash(&self.previous_hash)?;
+        writer.u64(self.timestamp);
+        writer.u64(self.difficulty);
+        writer.count(self.operations.len())?;
+        for operation in &self.operations {
+            writer.operation(operation)?;
+        }
+        writer.u64(self.nonce_range.0);
+        writer.u64(self.nonce_range.1);
+        if self.miner_address.is_empty() {
+            writer.u8(0);
+        } else {
+            writer.u8(1);
+            writer
+                .bytes
+                .extend_from_slice(&parse_address(&self.miner_address)?);
+        }
+        writer.u64(self.sequence);
+        Ok(writer.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::MinerKey;
+    use crate::{Activation, FeedForwardType, MasterWeights, TensorRef};
+    use proptest::prelude::*;
+
+    fn hash() -> impl Strategy<Value = String> {
+        any::<[u8; 32]>().prop_map(hex::encode)
+    }
+
+    fn named_hashes() -> impl Strategy<Value = Vec<(String, String)>> {
+        prop::collection::vec(("[a-z0-9_.]{0,16}", hash()), 0..4)
+    }
+
+    fn operation_result() -> impl Strategy<Value = OperationResult> {
+        (
+            hash(),
+            any::<u64>(),
+            any::<u64>(),
+            named_hashes(),
+            prop::collection::vec(("[a-z_]{0,8}", any::<f64>()), 0..3),
+        )
+            .prop_map(
+                |(result_hash, flops, execution_time_ms, intermediate_hashes, metrics)| {
+                    OperationResult {
+                        result_hash,
+                        flops,
+                        execution_time_ms,
+                        intermediate_hashes,
+                        metrics,
+                    }
+                },
+            )
+    }
+
+    fn work_result() -> impl Strategy<Value = WorkResult> {
+        (
+            "\\PC{0,24}",
+            any::<u64>(),
+            hash(),
+            any::<(u64, u64)>(),
+            prop::collection::vec(operation_result(), 0..4),
+            named_hashes(),
+            any::<bool>(),
+        )
+            .prop_map(
+                |(
+                    work_id,
+                    nonce,
+                    hash,
+                    (execution_time_ms, total_flops),
+                    operation_results,
+                    node_hashes,
+                    signed,
+                )| {
+                    let (beneficiary, signature) = if signed {
+                        (format!("0x{}", "5a".repeat(20)), "c3".repeat(SIGNATURE_LEN))
+                    } else {
+                        (String::new(), String::new())
+                    };
+                    WorkResult {
+                        work_id,
+                        nonce,
+                        hash,
+                        execution_time_ms,
+                        total_flops,
+                        operation_results,
+                        node_hashes,
+                        beneficiary,
+                        signature,
+                    }
+                },
+            )
+    }
+
+    fn proof() -> impl Strategy<Value = Proof> {
+        (
+            hash(),
+            hash(),
+            any::<u64>(),
+            prop::collection::vec(hash(), 0..8),
+            any::<(u64, u64)>(),
+        )
+            .prop_map(
+                |(previous, commitment, nonce, hashes, (flops, timestamp))| {
+                    Proof::new(&previous, &commitment, nonce, hashes, flops, timestamp).unwrap()
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn test_work_result_round_trip(result in work_result()) {
+            let bytes = result.to_binary().unwrap();
+            let decoded = WorkResult::from_binary(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_binary().unwrap(), bytes);
+            prop_assert_eq!(&decoded.work_id, &result.work_id);
+            prop_assert_eq!(decoded.operation_results.len(), result.operation_results.len());
+            prop_assert_eq!(&decoded.node_hashes, &result.node_hashes);
+            prop_assert_eq!(&decoded.signature, &result.signature);
+        }
+
+        #[test]
+        fn test_proof_round_trip(proof in proof()) {
+            let bytes = proof.to_binary().unwrap();
+            let decoded = Proof::from_binary(&bytes).unwrap();
+            prop_assert_eq!(decoded.to_binary().unwrap(), bytes);
+            prop_assert_eq!(decoded.header, proof.header);
+            prop_assert_eq!(&decoded.work_hash, &proof.work_hash);
+            prop_assert_eq!(&decoded.operation_hashes, &proof.operation_hashes);
+        }
+
+        #[test]
+        fn test_decode_arbitrary_bytes(
+            kind in 1u8..=2,
+            body in prop::collection::vec(any::<u8>(), 0..512),
+        ) {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend_from_slice(&[ENCODING_VERSION, kind]);
+            bytes.extend_from_slice(&body);
+            let _ = Proof::from_binary(&bytes);
+            let _ = WorkResult::from_binary(&bytes);
+            let _ = WorkResult::from_binary(&body);
+        }
+
+        #[test]
+        fn test_decode_corrupted_messages(
+            result in work_result(),
+            flips in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+            truncate in any::<prop::sample::Index>(),
+        ) {
+            let mut bytes = result.to_binary().unwrap();
+            for (index, value) in flips {
+                let index = index.index(bytes.len());
+                bytes[index] ^= value;
+            }
+            if let Ok(decoded) = WorkResult::from_binary(&bytes) {
+                let encoded = decoded.to_binary().unwrap();
+                prop_assert!(WorkResult::from_binary(&encoded).is_ok());
+            }
+            let cut = truncate.index(bytes.len());
+            let _ = WorkResult::from_binary(&bytes[..cut]);
+        }
+    }
+
+    fn signed_proof() -> Proof {
+        Proof::new(
+            &"ab".repeat(32),
+            &"cd".repeat(32),
+            3,
+            vec!["ef".repeat(32)],
+            1000,
+            1_700_000_000,
+        )
+        .unwrap()
+        .sign(&MinerKey::from_hex(&"42".repeat(32)).unwrap())
+        .unwrap()
+    }
+
+    #[test]
+    fn test_signed_proof_layout() {
+        let proof = signed_proof();
+        let bytes = proof.to_binary().unwrap();
+        assert_eq!(&bytes[..6], b"DMLE\x01\x01");
+        assert_eq!(
+            bytes.len(),
+            6 + HEADER_LEN + 4 + 32 + 8 + 8 + 1 + 20 + SIGNATURE_LEN
+        );
+
+        let decoded = Proof::from_binary(&bytes).unwrap();
+        assert_eq!(decoded.beneficiary, proof.beneficiary);
+        assert!(decoded.verify_signature());
+
+        // The wrong kind or an unknown version is rejected
+        assert!(WorkResult::from_binary(&bytes).is_err());
+        let mut future = bytes.clone();
+        future[4] = ENCODING_VERSION + 1;
+        assert!(Proof::from_binary(&future).is_err());
+        assert!(Proof::from_binary(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_hex_normalization() {
+        // Hex fields are stored as bytes, so case and `0x` prefixes don't change
+        // the encoding, and decoding gives the normalized form
+        let proof = signed_proof();
+        let mut mixed = proof.clone();
+        mixed.operation_hashes[0] = format!("0x{}", "EF".repeat(32));
+        mixed.beneficiary = proof.beneficiary.to_uppercase().replacen("0X", "0x", 1);
+        mixed.signature = format!("0x{}", proof.signature.to_uppercase());
+        let bytes = mixed.to_binary().unwrap();
+        assert_eq!(bytes, proof.to_binary().unwrap());
+
+        let decoded = Proof::from_binary(&bytes).unwrap();
+        assert_eq!(decoded.operation_hashes, proof.operation_hashes);
+        assert_eq!(decoded.beneficiary, proof.beneficiary);
+        assert_eq!(decoded.signature, proof.signature);
+    }
+
+    #[test]
+    fn test_extensions() {
+        let bytes = signed_proof().to_binary().unwrap();
+        let extension = |tag: u16, payload: &[u8]| {
+            let mut record = tag.to_le_bytes().to_vec();
+            record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            record.extend_from_slice(payload);
+            record
+        };
+
+        // Unknown optional extensions are skipped
+        let optional = [bytes.clone(), extension(1, b"new"), extension(7, b"")].concat();
+        assert_eq!(
+            Proof::from_binary(&optional).unwrap().to_binary().unwrap(),
+            bytes
+        );
+
+        let required = [bytes.clone(), extension(REQUIRED_EXTENSION, b"x")].concat();
+        assert!(Proof::from_binary(&required).is_err());
+        let unordered = [bytes.clone(), extension(7, b""), extension(1, b"")].concat();
+        assert!(Proof::from_binary(&unordered).is_err());
+        let overrun = [bytes.clone(), extension(1, b"abc")].concat();
+        assert!(Proof::from_binary(&overrun[..overrun.len() - 1]).is_err());
+    }
+
+    fn work_unit() -> WorkUnit {
+        WorkUnit {
+            id: "unit-7".to_string(),
+            previous_hash: "ab".repeat(32),
+            timestamp: 1_700_000_000,
+            difficulty: 4,
+            operations: vec![MLOperation::MatrixMultiply {
+                dimensions: (2, 3, 4),
+                seed: 9,
+            }],
+            nonce_range: (0, 1000),
+            miner_address: String::new(),
+            sequence: 5,
+        }
+    }
+
+    #[test]
+    fn test_work_unit_layout() {
+        let unit = work_unit();
+        let mut expected = b"DMLE\x01\x03".to_vec();
+        expected.extend_from_slice(&6u16.to_le_bytes());
+        expected.extend_from_slice(b"unit-7");
+        expected.extend_from_slice(&[0xab; 32]);
+        expected.extend_from_slice(&1_700_000_000u64.to_le_bytes());
+        expected.extend_from_slice(&4u64.to_le_bytes());
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.push(0);
+        for value in [2u64, 3, 4, 9, 0, 1000] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+        expected.push(0);
+        expected.extend_from_slice(&5u64.to_le_bytes());
+        assert_eq!(unit.to_binary().unwrap(), expected);
+
+        // Setting the miner adds its raw address
+        let mut mined = unit.clone();
+        mined.miner_address = format!("0x{}", "5A".repeat(20));
+        let bytes = mined.to_binary().unwrap();
+        assert_eq!(bytes.len(), expected.len() + 20);
+        assert_eq!(
+            &bytes[bytes.len() - 29..bytes.len() - 8],
+            &[[1u8].as_slice(), &[0x5a; 20]].concat()
+        );
+    }
+
+    #[test]
+    fn test_work_unit_commitment_vector() {
+        let mut unit = work_unit();
+        unit.miner_address = format!("0x{}", "5a".repeat(20));
+        unit.operations.extend([
+            MLOperation::TransformerBlock {
+                batch_size: 1,
+                seq_length: 8,
+                d_model: 16,
+                num_heads: 2,
+                d_ff: 32,
+                feed_forward: FeedForwardType::SwiGLU,
+                seed: 1,
+            },
+            MLOperation::OptimizerStep {
+                num_params: 64,
+                optimizer: Optimizer::AdamW {
+                    learning_rate: 1e-3,
+                    beta1: 0.9,
+                    beta2: 0.999,
+                    epsilon: 1e-8,
+                    weight_decay: 0.01,
+                    step: 3,
+                },
+                master_weights: MasterWeights::BF16,
+                seed: 2,
+            },
+            MLOperation::MixtureOfExperts {
+                tokens: 8,
+                d_model: 16,
+                d_ff: 32,
+                num_experts: 4,
+                top_k: 2,
+                capacity_factor: 1.25,
+                seed: 3,
+            },
+            MLOperation::TensorOperation {
+                op: TensorOp::Activation(Activation::GELU),
+                inputs: vec![
+                    TensorInput::Stored(TensorRef {
+                        hash: "cd".repeat(32),
+                        shape: vec![2, 4],
+                    }),
+                    TensorInput::Edge {
+                        name: "hidden".to_string(),
+                        shape: vec![2, 4],
+                    },
+                ],
+                output: Some("out".to_string()),
+            },
+        ]);
+        assert_eq!(
+            unit.commitment().unwrap(),
+            "1cee8622e6ad11e0befda3d50a9feadcc40944a1e4d6b35af481bcc61f2fdbca"
+        );
+
+        // Any changed field changes the commitment
+        let mut changed = unit.clone();
+        changed.sequence += 1;
+        assert_ne!(changed.commitment().unwrap(), unit.commitment().unwrap());
+    }
+
+    #[test]
+    fn test_large_work_unit_commitment() {
+        let mut unit = work_unit();
+        unit.operations = (0..5000)
+            .map(|seed| MLOperation::MatrixMultiply {
+                dimensions: (64, 64, 64),
+                seed,
+            })
+            .collect();
+
+        assert!(unit.to_binary().unwrap().len() > MAX_ENCODED_LEN);
+        assert_ne!(
+            unit.commitment().unwrap(),
+            work_unit().commitment().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let mut result = WorkResult {
+            work_id: "w".repeat(MAX_NAME_LEN + 1),
+            nonce: 0,
+            hash: "00".repeat(32),
+            execution_time_ms: 0,
+            total_flops: 0,
+            operation_results: Vec::new(),
+            node_hashes: Vec::new(),
+            beneficiary: String::new(),
+            signature: String::new(),
+        };
+        assert!(result.to_binary().is_err());
+
+        result.work_id = "w".to_string();
+        result.node_hashes = vec![("n".to_string(), "00".repeat(32)); MAX_ENCODED_LEN / 35];
+        assert!(result.to_binary().is_err());
+        result.node_hashes.truncate(1);
+        assert!(result.to_binary().is_ok());
+
+        assert!(WorkResult::from_binary(&vec![0; MAX_ENCODED_LEN + 1]).is_err());
+
+        // A list count beyond the message is rejected before allocating
+        let mut bytes = result.to_binary().unwrap();
+        let offset = 6 + 2 + 1 + 8 + 32 + 8 + 8;
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(WorkResult::from_binary(&bytes).is_err());
+    }
+}
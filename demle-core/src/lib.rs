@@ -1,8 +1,16 @@
+pub mod challenge;
 pub mod difficulty;
+pub mod encoding;
+pub mod merkle;
 pub mod proof;
+pub mod seed;
+pub mod signing;
 pub mod types;
 
+use proof::Proof;
+use seed::{derive_seed, parse_address};
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
 use std::fmt;
 use thiserror::Error;
 
@@ -56,6 +64,308 @@ pub enum MLOperation {
         epsilon: f32,
         seed: u64,
     },
+    TransformerBlock {
+        batch_size: usize,
+        seq_length: usize,
+        d_model: usize,
+        num_heads: usize,
+        d_ff: usize,
+        feed_forward: FeedForwardType,
+        seed: u64,
+    },
+    GatedFeedForward {
+        tokens: usize,
+        d_model: usize,
+        d_ff: usize,
+        activation: Activation,
+        seed: u64,
+    },
+    Pooling2D {
+        input_shape: (usize, usize, usize, usize),
+        pool_type: PoolType,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        seed: u64,
+    },
+    EmbeddingLookup {
+        vocab_size: usize,
+        embedding_dim: usize,
+        num_tokens: usize,
+        seed: u64,
+    },
+    Softmax {
+        rows: usize,
+        cols: usize,
+        log: bool,
+        seed: u64,
+    },
+    MatrixMultiplyBackward {
+        dimensions: (usize, usize, usize),
+        seed: u64,
+    },
+    Convolution2DBackward {
+        input_shape: (usize, usize, usize, usize),
+        kernel_shape: (usize, usize, usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        seed: u64,
+    },
+    MultiHeadAttentionBackward {
+        batch_size: usize,
+        seq_length: usize,
+        d_model: usize,
+        num_heads: usize,
+        seed: u64,
+    },
+    OptimizerStep {
+        num_params: usize,
+        optimizer: Optimizer,
+        master_weights: MasterWeights,
+        seed: u64,
+    },
+    TrainingStep {
+        batch_size: usize,
+        input_dim: usize,
+        hidden_dim: usize,
+        num_classes: usize,
+        optimizer: Optimizer,
+        master_weights: MasterWeights,
+        seed: u64,
+    },
+    BatchedMatrixMultiply {
+        batch_size: usize,
+        dimensions: (usize, usize, usize), // (m, k, n)
+        a_operand: BatchOperand,
+        b_operand: BatchOperand,
+        /// A is stored as (k, m) and transposed
+        transpose_a: bool,
+        /// B is stored as (n, k) and transposed
+        transpose_b: bool,
+        seed: u64,
+    },
+    /// GEMM with A pruned to 2:4 structured sparsity along k
+    SparseMatrixMultiply {
+        dimensions: (usize, usize, usize), // (m, k, n)
+        seed: u64,
+    },
+    /// Top-k routed mixture of expert FFNs with a per-expert token capacity
+    MixtureOfExperts {
+        tokens: usize,
+        d_model: usize,
+        d_ff: usize,
+        num_experts: usize,
+        top_k: usize,
+        capacity_factor: f32,
+        seed: u64,
+    },
+    /// Quantize seeded f32 values to one format and requantize them to another
+    Quantize {
+        num_elements: usize,
+        source: NumericFormat,
+        target: NumericFormat,
+        seed: u64,
+    },
+    /// Apply a kernel to input tensors loaded from the local tensor store
+    TensorOperation {
+        op: TensorOp,
+        inputs: Vec<TensorInput>,
+        /// Edge name later operations in the same work unit read the output by
+        #[serde(default)]
+        output: Option<String>,
+    },
+}
+
+/// Reference to a tensor in a local tensor store, addressed by the hash of its
+/// shape and contents
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TensorRef {
+    pub hash: String,
+    pub shape: Vec<usize>,
+}
+
+/// Input of a [`MLOperation::TensorOperation`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TensorInput {
+    /// Tensor already in the store
+    Stored(TensorRef),
+    /// Output of the operation in the same work unit that names it `name`
+    Edge { name: String, shape: Vec<usize> },
+}
+
+impl TensorInput {
+    pub fn shape(&self) -> &[usize] {
+        match self {
+            TensorInput::Stored(tensor) => &tensor.shape,
+            TensorInput::Edge { shape, .. } => shape,
+        }
+    }
+}
+
+/// Kernel applied by a [`MLOperation::TensorOperation`]; the inputs it expects
+/// are listed per variant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TensorOp {
+    /// (..., k) x (k, n) -> (..., n)
+    MatMul,
+    /// NCHW input and (out_channels, in_channels, kh, kw) kernel
+    Conv2D {
+        stride: (usize, usize),
+        padding: (usize, usize),
+    },
+    /// (batch, seq, d_model) input and (d_model, d_model) Q, K and V projections
+    MultiHeadAttention { num_heads: usize },
+    /// Row-wise over the last dimension of one input
+    Softmax { log: bool },
+    /// Over the last dimension of (..., d) input with (d) gamma and beta
+    LayerNorm { epsilon: f32 },
+    /// Element-wise over one input
+    Activation(Activation),
+    /// Element-wise sum, the second input broadcast over leading dimensions
+    Add,
+    /// NCHW input
+    Pooling2D {
+        pool_type: PoolType,
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    },
+    /// Same elements viewed with a new shape of equal size
+    Reshape { shape: Vec<usize> },
+}
+
+/// Element format of a tensor; FP8 and INT8 carry a per-tensor scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumericFormat {
+    F32,
+    BF16,
+    F16,
+    FP8E4M3,
+    FP8E5M2,
+    INT8,
+}
+
+/// Whether a batched GEMM operand has one matrix per batch entry or a single
+/// matrix shared by every entry (a zero batch stride)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchOperand {
+    PerBatch,
+    Shared,
+}
+
+/// Optimizer update rule and its hyperparameters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Optimizer {
+    SgdMomentum {
+        learning_rate: f32,
+        momentum: f32,
+        weight_decay: f32,
+    },
+    AdamW {
+        learning_rate: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        weight_decay: f32,
+        /// 1-based step number, used for bias correction
+        step: u64,
+    },
+}
+
+impl Optimizer {
+    /// Short name used when displaying operations
+    pub fn name(&self) -> &'static str {
+        match self {
+            Optimizer::SgdMomentum { .. } => "SGD",
+            Optimizer::AdamW { .. } => "AdamW",
+        }
+    }
+}
+
+/// Precision of the master copy of the weights that optimizer updates are applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MasterWeights {
+    F32,
+    BF16,
+}
+
+/// 2D pooling variant; global variants reduce the whole spatial extent and
+/// ignore kernel size, stride and padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoolType {
+    Max,
+    Average,
+    GlobalMax,
+    GlobalAverage,
+}
+
+/// Element-wise activation function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activation {
+    ReLU,
+    GELU,
+    Swish,
+}
+
+/// Feed-forward network variant used inside a transformer block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeedForwardType {
+    /// Two projections with a GELU in between
+    GELU,
+    /// Gated feed-forward: swish(x * W_gate) * (x * W_up), then W_down
+    SwiGLU,
+}
+
+impl MLOperation {
+    /// Seed the operation generates its inputs from; tensor operations read
+    /// stored inputs and have none
+    pub fn seed_mut(&mut self) -> Option<&mut u64> {
+        match self {
+            MLOperation::MatrixMultiply { seed, .. }
+            | MLOperation::Convolution2D { seed, .. }
+            | MLOperation::MultiHeadAttention { seed, .. }
+            | MLOperation::BatchNormalization { seed, .. }
+            | MLOperation::TransformerBlock { seed, .. }
+            | MLOperation::GatedFeedForward { seed, .. }
+            | MLOperation::Pooling2D { seed, .. }
+            | MLOperation::EmbeddingLookup { seed, .. }
+            | MLOperation::Softmax { seed, .. }
+            | MLOperation::MatrixMultiplyBackward { seed, .. }
+            | MLOperation::Convolution2DBackward { seed, .. }
+            | MLOperation::MultiHeadAttentionBackward { seed, .. }
+            | MLOperation::OptimizerStep { seed, .. }
+            | MLOperation::TrainingStep { seed, .. }
+            | MLOperation::BatchedMatrixMultiply { seed, .. }
+            | MLOperation::SparseMatrixMultiply { seed, .. }
+            | MLOperation::MixtureOfExperts { seed, .. }
+            | MLOperation::Quantize { seed, .. } => Some(seed),
+            MLOperation::TensorOperation { .. } => None,
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            MLOperation::MatrixMultiply { seed, .. }
+            | MLOperation::Convolution2D { seed, .. }
+            | MLOperation::MultiHeadAttention { seed, .. }
+            | MLOperation::BatchNormalization { seed, .. }
+            | MLOperation::TransformerBlock { seed, .. }
+            | MLOperation::GatedFeedForward { seed, .. }
+            | MLOperation::Pooling2D { seed, .. }
+            | MLOperation::EmbeddingLookup { seed, .. }
+            | MLOperation::Softmax { seed, .. }
+            | MLOperation::MatrixMultiplyBackward { seed, .. }
+            | MLOperation::Convolution2DBackward { seed, .. }
+            | MLOperation::MultiHeadAttentionBackward { seed, .. }
+            | MLOperation::OptimizerStep { seed, .. }
+            | MLOperation::TrainingStep { seed, .. }
+            | MLOperation::BatchedMatrixMultiply { seed, .. }
+            | MLOperation::SparseMatrixMultiply { seed, .. }
+            | MLOperation::MixtureOfExperts { seed, .. }
+            | MLOperation::Quantize { seed, .. } => Some(*seed),
+            MLOperation::TensorOperation { .. } => None,
+        }
+    }
 }
 
 impl fmt::Display for MLOperation {
@@ -83,6 +393,151 @@ impl fmt::Display for MLOperation {
                     shape.0, shape.1, shape.2, shape.3
                 )
             }
+            MLOperation::TransformerBlock {
+                d_model,
+                num_heads,
+                d_ff,
+                feed_forward,
+                ..
+            } => {
+                write!(
+                    f,
+                    "TransformerBlock {}heads x {} ({:?} FFN {})",
+                    num_heads, d_model, feed_forward, d_ff
+                )
+            }
+            MLOperation::GatedFeedForward {
+                tokens,
+                d_model,
+                d_ff,
+                activation,
+                ..
+            } => {
+                write!(
+                    f,
+                    "GatedFFN {}x{}x{} ({:?})",
+                    tokens, d_model, d_ff, activation
+                )
+            }
+            MLOperation::Pooling2D {
+                input_shape,
+                pool_type,
+                kernel_size,
+                ..
+            } => {
+                write!(
+                    f,
+                    "{:?}Pool {}x{}x{}x{} ({}x{})",
+                    pool_type,
+                    input_shape.0,
+                    input_shape.1,
+                    input_shape.2,
+                    input_shape.3,
+                    kernel_size.0,
+                    kernel_size.1
+                )
+            }
+            MLOperation::EmbeddingLookup {
+                vocab_size,
+                embedding_dim,
+                num_tokens,
+                ..
+            } => {
+                write!(
+                    f,
+                    "Embedding {} tokens from {}x{}",
+                    num_tokens, vocab_size, embedding_dim
+                )
+            }
+            MLOperation::Softmax {
+                rows, cols, log, ..
+            } => {
+                let name = if *log { "LogSoftmax" } else { "Softmax" };
+                write!(f, "{} {}x{}", name, rows, cols)
+            }
+            MLOperation::MatrixMultiplyBackward { dimensions, .. } => {
+                write!(
+                    f,
+                    "GEMM backward {}x{}x{}",
+                    dimensions.0, dimensions.1, dimensions.2
+                )
+            }
+            MLOperation::Convolution2DBackward { kernel_shape, .. } => {
+                write!(
+                    f,
+                    "Conv2D backward {}x{}x{}x{}",
+                    kernel_shape.0, kernel_shape.1, kernel_shape.2, kernel_shape.3
+                )
+            }
+            MLOperation::MultiHeadAttentionBackward {
+                num_heads, d_model, ..
+            } => {
+                write!(f, "Attention backward {}heads x {}", num_heads, d_model)
+            }
+            MLOperation::OptimizerStep {
+                num_params,
+                optimizer,
+                master_weights,
+                ..
+            } => write!(
+                f,
+                "{} step {} params ({:?} master)",
+                optimizer.name(),
+                num_params,
+                master_weights
+            ),
+            MLOperation::TrainingStep {
+                batch_size,
+                input_dim,
+                hidden_dim,
+                num_classes,
+                optimizer,
+                ..
+            } => write!(
+                f,
+                "Training step MLP {}->{}->{} x{} ({})",
+                input_dim,
+                hidden_dim,
+                num_classes,
+                batch_size,
+                optimizer.name()
+            ),
+            MLOperation::BatchedMatrixMultiply {
+                batch_size,
+                dimensions,
+                ..
+            } => {
+                let (m, k, n) = dimensions;
+                write!(f, "Batched GEMM {}x {}x{}x{}", batch_size, m, k, n)
+            }
+            MLOperation::SparseMatrixMultiply { dimensions, .. } => {
+                let (m, k, n) = dimensions;
+                write!(f, "Sparse 2:4 GEMM {}x{}x{}", m, k, n)
+            }
+            MLOperation::MixtureOfExperts {
+                tokens,
+                d_model,
+                num_experts,
+                top_k,
+                ..
+            } => write!(
+                f,
+                "MoE {} experts top-{} {}x{}",
+                num_experts, top_k, tokens, d_model
+            ),
+            MLOperation::Quantize {
+                num_elements,
+                source,
+                target,
+                ..
+            } => write!(f, "Quantize {} {:?} -> {:?}", num_elements, source, target),
+            MLOperation::TensorOperation { op, inputs, output } => {
+                write!(f, "Tensor {:?} on {} inputs", op, inputs.len())?;
+                match output {
+                    Some(name) => write!(f, " -> {}", name),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
@@ -93,6 +548,12 @@ pub struct OperationResult {
     pub result_hash: String,
     pub flops: u64,
     pub execution_time_ms: u64,
+    /// Hashes of named intermediate activations, in execution order, for spot checks
+    #[serde(default)]
+    pub intermediate_hashes: Vec<(String, String)>,
+    /// Named scalar values reported by the operation, such as training loss
+    #[serde(default)]
+    pub metrics: Vec<(String, f64)>,
 }
 
 /// Work unit for mining
@@ -102,8 +563,58 @@ pub struct WorkUnit {
     pub previous_hash: String,
     pub timestamp: u64,
     pub difficulty: u64,
+    /// Nodes of the work unit's dependency graph; tensor operations are
+    /// connected by named edges and need not be listed in execution order
     pub operations: Vec<MLOperation>,
     pub nonce_range: (u64, u64),
+    /// Address of the miner the work is for, which the operation seeds are derived from
+    #[serde(default)]
+    pub miner_address: String,
+    /// Miner's work-unit counter, mixed into the operation seeds
+    #[serde(default)]
+    pub sequence: u64,
+}
+
+impl WorkUnit {
+    /// Hex-encoded SHA3-256 of the work unit's binary encoding, which block
+    /// headers commit to
+    pub fn commitment(&self) -> Result<String> {
+        Ok(hex::encode(Sha3_256::digest(self.to_binary()?)))
+    }
+
+    /// Set every operation seed from the previous hash, miner address and
+    /// sequence number
+    pub fn derive_seeds(&mut self) -> Result<()> {
+        for (index, operation) in self.operations.iter_mut().enumerate() {
+            if let Some(seed) = operation.seed_mut() {
+                *seed = derive_seed(
+                    &self.previous_hash,
+                    &self.miner_address,
+                    self.sequence,
+                    index,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every operation seed is the one derived from the chain state
+    pub fn seeds_match(&self) -> Result<bool> {
+        for (index, operation) in self.operations.iter().enumerate() {
+            if let Some(seed) = operation.seed() {
+                let derived = derive_seed(
+                    &self.previous_hash,
+                    &self.miner_address,
+                    self.sequence,
+                    index,
+                )?;
+                if seed != derived {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
 }
 
 /// Result of mining a work unit
@@ -115,6 +626,51 @@ pub struct WorkResult {
     pub execution_time_ms: u64,
     pub total_flops: u64,
     pub operation_results: Vec<OperationResult>,
+    /// Result hash of each node of the work unit's dependency graph, by node name
+    #[serde(default)]
+    pub node_hashes: Vec<(String, String)>,
+    /// Address the rewards go to
+    #[serde(default)]
+    pub beneficiary: String,
+    /// Beneficiary's signature over the proof commitment
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl WorkResult {
+    /// Rebuild the signed proof of this result from the work unit it claims
+    pub fn proof(&self, work_unit: &WorkUnit) -> Result<Proof> {
+        let mut proof = Proof::new(
+            &work_unit.previous_hash,
+            &work_unit.commitment()?,
+            self.nonce,
+            self.operation_results
+                .iter()
+                .map(|r| r.result_hash.clone())
+                .collect(),
+            self.total_flops,
+            0,
+        )?;
+        proof.beneficiary = self.beneficiary.clone();
+        proof.signature = self.signature.clone();
+        Ok(proof)
+    }
+
+    /// Whether the result is signed by the miner the work unit's seeds were
+    /// derived for, so it can't be resubmitted for another beneficiary
+    pub fn signed_by_miner(&self, work_unit: &WorkUnit) -> bool {
+        let same_miner = match (
+            parse_address(&self.beneficiary),
+            parse_address(&work_unit.miner_address),
+        ) {
+            (Ok(beneficiary), Ok(miner)) => beneficiary == miner,
+            _ => false,
+        };
+        same_miner
+            && self
+                .proof(work_unit)
+                .is_ok_and(|proof| proof.verify_signature())
+    }
 }
 
 /// DEMLE-specific error types
@@ -0,0 +1,213 @@
+use crate::{DemleError, Result};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// Bytes per output tile; 1024 FP8 elements
+pub const TILE_SIZE: usize = 1024;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+type Digest32 = [u8; 32];
+
+fn hash_leaf(tile: &[u8]) -> Digest32 {
+    let mut hasher = Sha3_256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(tile);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Digest32, right: &Digest32) -> Digest32 {
+    let mut hasher = Sha3_256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Merkle tree over fixed-size tiles of an operation output. Leaves and inner
+/// nodes are hashed with distinct prefixes, and a node without a sibling is
+/// carried up unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleTree {
+    tile_size: usize,
+    /// Level 0 holds the leaf hashes, the last level the root
+    levels: Vec<Vec<Digest32>>,
+}
+
+impl MerkleTree {
+    /// Split `data` into tiles of `tile_size` bytes, the last one possibly
+    /// shorter, and build the tree. Empty data forms a single empty tile.
+    pub fn build(data: &[u8], tile_size: usize) -> Result<Self> {
+        if tile_size == 0 {
+            return Err(DemleError::ValidationError(
+                "Tile size must be positive".to_string(),
+            ));
+        }
+
+        let leaves: Vec<Digest32> = if data.is_empty() {
+            vec![hash_leaf(&[])]
+        } else {
+            data.chunks(tile_size).map(hash_leaf).collect()
+        };
+
+        let mut levels = vec![leaves];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self { tile_size, levels })
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn num_tiles(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Hex-encoded leaf hash of every tile, in order
+    pub fn tile_hashes(&self) -> Vec<String> {
+        self.levels[0].iter().map(hex::encode).collect()
+    }
+
+    /// Hex-encoded root hash
+    pub fn root(&self) -> String {
+        hex::encode(self.levels[self.levels.len() - 1][0])
+    }
+
+    /// Byte range of a tile within the output
+    pub fn tile_range(&self, tile_index: usize, data_len: usize) -> std::ops::Range<usize> {
+        let start = (tile_index * self.tile_size).min(data_len);
+        start..(start + self.tile_size).min(data_len)
+    }
+
+    /// Inclusion proof of one tile
+    pub fn prove(&self, tile_index: usize) -> Result<InclusionProof> {
+        if tile_index >= self.num_tiles() {
+            return Err(DemleError::ValidationError(format!(
+                "Tile {} is out of range for {} tiles",
+                tile_index,
+                self.num_tiles()
+            )));
+        }
+
+        let mut siblings = Vec::new();
+        let mut index = tile_index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                siblings.push(hex::encode(sibling));
+            }
+            index /= 2;
+        }
+
+        Ok(InclusionProof {
+            tile_index,
+            num_tiles: self.num_tiles(),
+            siblings,
+        })
+    }
+}
+
+/// Sibling hashes linking one output tile to the Merkle root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub tile_index: usize,
+    pub num_tiles: usize,
+    /// Hex-encoded sibling hashes from the leaf level upwards, skipping levels
+    /// where the node has no sibling
+    pub siblings: Vec<String>,
+}
+
+impl InclusionProof {
+    /// Check that `tile` is the tile at `tile_index` of the output with this root
+    pub fn verify(&self, root: &str, tile: &[u8]) -> bool {
+        if self.tile_index >= self.num_tiles {
+            return false;
+        }
+
+        let mut hash = hash_leaf(tile);
+        let mut siblings = self.siblings.iter();
+        let mut index = self.tile_index;
+        let mut width = self.num_tiles;
+        while width > 1 {
+            let sibling = index ^ 1;
+            if sibling < width {
+                let Some(bytes) = siblings.next().and_then(|s| hex::decode(s).ok()) else {
+                    return false;
+                };
+                let Ok(sibling_hash) = Digest32::try_from(bytes.as_slice()) else {
+                    return false;
+                };
+                hash = if index.is_multiple_of(2) {
+                    hash_node(&hash, &sibling_hash)
+                } else {
+                    hash_node(&sibling_hash, &hash)
+                };
+            }
+            index /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none() && hex::encode(hash) == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+        for tile_size in [1, 64, 1000, 4096, 8192] {
+            let tree = MerkleTree::build(&data, tile_size).unwrap();
+            assert_eq!(tree.num_tiles(), data.len().div_ceil(tile_size));
+            let root = tree.root();
+
+            for tile_index in [0, tree.num_tiles() / 2, tree.num_tiles() - 1] {
+                let proof = tree.prove(tile_index).unwrap();
+                let range = tree.tile_range(tile_index, data.len());
+                assert!(proof.verify(&root, &data[range.clone()]));
+
+                // A modified tile or a proof for another position fails
+                let mut tile = data[range].to_vec();
+                tile[0] ^= 1;
+                assert!(!proof.verify(&root, &tile));
+                if tree.num_tiles() > 1 {
+                    let moved = InclusionProof {
+                        tile_index: (tile_index + 1) % tree.num_tiles(),
+                        ..proof.clone()
+                    };
+                    assert!(!moved.verify(&root, &data[tree.tile_range(tile_index, data.len())]));
+                }
+            }
+            assert!(tree.prove(tree.num_tiles()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_root_changes_with_any_tile() {
+        let data = vec![0u8; 3 * TILE_SIZE + 17];
+        let root = MerkleTree::build(&data, TILE_SIZE).unwrap().root();
+        for position in [0, TILE_SIZE, 3 * TILE_SIZE + 16] {
+            let mut changed = data.clone();
+            changed[position] = 1;
+            assert_ne!(MerkleTree::build(&changed, TILE_SIZE).unwrap().root(), root);
+        }
+        // Appending a tile is not the same output
+        let mut longer = data.clone();
+        longer.extend_from_slice(&[0u8; TILE_SIZE]);
+        assert_ne!(MerkleTree::build(&longer, TILE_SIZE).unwrap().root(), root);
+        assert!(MerkleTree::build(&data, 0).is_err());
+    }
+}
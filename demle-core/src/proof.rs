@@ -1,50 +1,572 @@
-use crate::Result;
+use crate::difficulty::{difficulty_to_target, meets_target};
+use crate::merkle::{MerkleTree, TILE_SIZE};
+use crate::seed::parse_address;
+use crate::signing::{self, MinerKey};
+use crate::{DemleError, Result};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Sha3_256};
+use sha3::{Digest, Keccak256, Sha3_256};
+
+/// Version of the block header encoding
+pub const HEADER_VERSION: u32 = 1;
+
+/// Length of an encoded block header
+pub const HEADER_LEN: usize = 4 + 32 * 3 + 8;
+
+/// Domain separator for the commitment a miner signs
+const PROOF_DOMAIN: &[u8] = b"demle/proof/v1";
+
+/// Decode a 32-byte hash from hex, with or without a `0x` prefix
+pub fn parse_hash(hash: &str) -> Result<[u8; 32]> {
+    let digits = hash.strip_prefix("0x").unwrap_or(hash);
+    hex::decode(digits)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| {
+            DemleError::ValidationError(format!("Expected a 32-byte hex hash, got '{}'", hash))
+        })
+}
+
+/// Canonical block header that the proof-of-work hash is computed over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub previous_hash: [u8; 32],
+    /// Commitment to the work unit the operations come from
+    pub work_commitment: [u8; 32],
+    /// Merkle root over the result hashes of the work unit's operations
+    pub operations_root: [u8; 32],
+    pub nonce: u64,
+}
+
+impl BlockHeader {
+    /// Encode as `version (u32 LE) ‖ previous hash ‖ work commitment ‖
+    /// operations root ‖ nonce (u64 LE)`
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(&self.previous_hash);
+        bytes[36..68].copy_from_slice(&self.work_commitment);
+        bytes[68..100].copy_from_slice(&self.operations_root);
+        bytes[100..].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    /// Decode a header encoded by [`BlockHeader::to_bytes`]
+    pub fn from_bytes(bytes: &[u8; HEADER_LEN]) -> Self {
+        Self {
+            version: u32::from_le_bytes(bytes[..4].try_into().unwrap()),
+            previous_hash: bytes[4..36].try_into().unwrap(),
+            work_commitment: bytes[36..68].try_into().unwrap(),
+            operations_root: bytes[68..100].try_into().unwrap(),
+            nonce: u64::from_le_bytes(bytes[100..].try_into().unwrap()),
+        }
+    }
+
+    /// SHA3-256 of the encoded header, compared against the target as a
+    /// big-endian number
+    pub fn hash(&self) -> [u8; 32] {
+        Sha3_256::digest(self.to_bytes()).into()
+    }
+}
+
+/// Outcome of searching a nonce range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceSearch {
+    /// First nonce whose header hash meets the target, if any
+    pub nonce: Option<u64>,
+    /// Header hashes computed
+    pub hashes: u64,
+}
+
+impl BlockHeader {
+    /// Try the nonces of `start..end` in order, stopping at the first whose
+    /// header hash is at or below the target. Only the nonce bytes of the
+    /// encoded header change between attempts.
+    pub fn search_nonce(&self, nonce_range: (u64, u64), target: &[u8; 32]) -> NonceSearch {
+        let mut bytes = self.to_bytes();
+        let mut hashes = 0;
+        for nonce in nonce_range.0..nonce_range.1 {
+            bytes[HEADER_LEN - 8..].copy_from_slice(&nonce.to_le_bytes());
+            hashes += 1;
+            let hash: [u8; 32] = Sha3_256::digest(bytes).into();
+            if meets_target(&hash, target) {
+                return NonceSearch {
+                    nonce: Some(nonce),
+                    hashes,
+                };
+            }
+        }
+        NonceSearch {
+            nonce: None,
+            hashes,
+        }
+    }
+}
+
+/// Merkle root over operation result hashes, one 32-byte leaf per operation
+pub fn operations_root(operation_hashes: &[String]) -> Result<[u8; 32]> {
+    let leaves = operation_hashes
+        .iter()
+        .map(|hash| parse_hash(hash))
+        .collect::<Result<Vec<_>>>()?
+        .concat();
+    parse_hash(&MerkleTree::build(&leaves, 32)?.root())
+}
 
 /// Proof of work for ML computation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proof {
-    pub nonce: u64,
+    pub header: BlockHeader,
+    /// Hex-encoded header hash
     pub work_hash: String,
     pub operation_hashes: Vec<String>,
     pub total_flops: u64,
     pub timestamp: u64,
+    /// Address the rewards go to, set when the proof is signed
+    #[serde(default)]
+    pub beneficiary: String,
+    /// Hex-encoded signature of the beneficiary over the commitment
+    #[serde(default)]
+    pub signature: String,
 }
 
 impl Proof {
     /// Create a new proof from computation results
     pub fn new(
+        previous_hash: &str,
+        work_commitment: &str,
         nonce: u64,
         operation_hashes: Vec<String>,
         total_flops: u64,
         timestamp: u64,
-    ) -> Self {
-        let combined = format!("{}:{}:{}", nonce, operation_hashes.join(","), total_flops);
-        let work_hash = hex::encode(Sha3_256::digest(combined.as_bytes()));
-
-        Self {
+    ) -> Result<Self> {
+        let header = BlockHeader {
+            version: HEADER_VERSION,
+            previous_hash: parse_hash(previous_hash)?,
+            work_commitment: parse_hash(work_commitment)?,
+            operations_root: operations_root(&operation_hashes)?,
             nonce,
-            work_hash,
+        };
+
+        Ok(Self {
+            work_hash: hex::encode(header.hash()),
+            header,
             operation_hashes,
             total_flops,
             timestamp,
-        }
+            beneficiary: String::new(),
+            signature: String::new(),
+        })
     }
 
-    /// Verify that this proof meets the difficulty requirement
-    pub fn verify(&self, difficulty: u64) -> Result<bool> {
-        // Simple difficulty check: hash must start with certain number of zeros
-        let leading_zeros = self.work_hash.chars().take_while(|&c| c == '0').count();
+    /// The same proof with another nonce in its header. Any signature is
+    /// dropped, since it no longer covers the header.
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.header.nonce = nonce;
+        self.work_hash = hex::encode(self.header.hash());
+        self.signature.clear();
+        self
+    }
+
+    /// Digest the beneficiary signs: the EIP-191 hash of `keccak256(domain ‖
+    /// encoded header ‖ beneficiary ‖ total FLOPs (u64 BE))`, which is
+    /// `abi.encodePacked` of those fields, so a contract can recover the signer
+    /// with `ecrecover`. The header binds the chain state, the work unit and the
+    /// results, so the signature can't be replayed onto other work.
+    pub fn commitment(&self) -> Result<[u8; 32]> {
+        let mut hasher = Keccak256::new();
+        hasher.update(PROOF_DOMAIN);
+        hasher.update(self.header.to_bytes());
+        hasher.update(parse_address(&self.beneficiary)?);
+        hasher.update(self.total_flops.to_be_bytes());
+        Ok(signing::eip191_hash(&hasher.finalize()))
+    }
+
+    /// Make the key's address the beneficiary and sign the commitment
+    pub fn sign(mut self, key: &MinerKey) -> Result<Self> {
+        self.beneficiary = key.address();
+        self.signature = key.sign(&self.commitment()?)?;
+        Ok(self)
+    }
+
+    /// Whether the signature over the commitment was made by the beneficiary
+    pub fn verify_signature(&self) -> bool {
+        self.commitment().is_ok_and(|commitment| {
+            signing::verify_signature(&commitment, &self.signature, &self.beneficiary)
+        })
+    }
 
-        // Convert difficulty to required leading zeros (simplified)
-        let required_zeros = (difficulty as f64).log10() as usize;
+    /// Verify that the header binds the operation hashes and that its hash is
+    /// at or below the target for `difficulty`
+    pub fn verify(&self, difficulty: u64) -> Result<bool> {
+        if self.header.version != HEADER_VERSION
+            || self.header.operations_root != operations_root(&self.operation_hashes)?
+        {
+            return Ok(false);
+        }
 
-        Ok(leading_zeros >= required_zeros)
+        let hash = self.header.hash();
+        Ok(hex::encode(hash) == self.work_hash
+            && meets_target(&hash, &difficulty_to_target(difficulty)))
     }
 
-    /// Calculate the hash of intermediate computation results
+    /// Calculate the hash of intermediate computation results: the Merkle root
+    /// over `TILE_SIZE`-byte tiles, so single tiles can be spot-checked
     pub fn hash_operation_result(data: &[u8]) -> String {
-        hex::encode(Sha3_256::digest(data))
+        Self::output_tree(data).root()
+    }
+
+    /// Merkle tree whose root is the hash of an operation output
+    pub fn output_tree(data: &[u8]) -> MerkleTree {
+        MerkleTree::build(data, TILE_SIZE).expect("tile size is positive")
+    }
+}
+
+/// Claimed output of a GEMM C(m×k · k×n) with its FP32 accumulators, for
+/// standalone Freivalds checks. A claim is not bound to any work result: mined
+/// GEMM hashes cover the FP8-accumulated output, so verifying a claim proves
+/// nothing about submitted work
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GemmClaim {
+    /// (m, k, n)
+    pub dimensions: (usize, usize, usize),
+    /// Row-major m×n output
+    pub output: Vec<f32>,
+}
+
+impl GemmClaim {
+    /// Hash binding the dimensions and the exact output bits
+    pub fn commitment(&self) -> String {
+        let (m, k, n) = self.dimensions;
+        let mut hasher = Sha3_256::new();
+        for dim in [m, k, n] {
+            hasher.update((dim as u64).to_le_bytes());
+        }
+        for value in &self.output {
+            hasher.update(value.to_le_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Randomized Freivalds check of claimed GEMM outputs in O(mk + kn + mn) per
+/// round instead of the O(mkn) of recomputing them. Each round compares
+/// A(Br) with Cr for a random ±1 vector r; a wrong output survives a round with
+/// probability at most 1/2.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreivaldsVerifier {
+    /// Upper bound on the probability of accepting a wrong output
+    pub error_probability: f64,
+}
+
+impl Default for FreivaldsVerifier {
+    fn default() -> Self {
+        Self {
+            error_probability: 1e-9,
+        }
+    }
+}
+
+impl FreivaldsVerifier {
+    pub fn new(error_probability: f64) -> Result<Self> {
+        if !(error_probability > 0.0 && error_probability < 1.0) {
+            return Err(DemleError::ValidationError(format!(
+                "Error probability must be in (0, 1), got {}",
+                error_probability
+            )));
+        }
+        Ok(Self { error_probability })
+    }
+
+    /// Rounds needed to reach the error probability
+    pub fn rounds(&self) -> usize {
+        (1.0 / self.error_probability).log2().ceil().max(1.0) as usize
+    }
+
+    /// Check a claim against its commitment and the inputs A(m×k) and B(k×n).
+    /// Outputs are accepted within the worst-case rounding error of FP32
+    /// accumulation, so `rng` must be private to the verifier.
+    pub fn verify<R: Rng>(
+        &self,
+        a: &[f32],
+        b: &[f32],
+        claim: &GemmClaim,
+        commitment: &str,
+        rng: &mut R,
+    ) -> Result<bool> {
+        let (m, k, n) = claim.dimensions;
+        let lengths = m.checked_mul(k).zip(k.checked_mul(n)).zip(m.checked_mul(n));
+        if lengths != Some(((a.len(), b.len()), claim.output.len())) {
+            return Err(DemleError::ValidationError(format!(
+                "GEMM operands of {}, {} and {} elements do not match dimensions {:?}",
+                a.len(),
+                b.len(),
+                claim.output.len(),
+                claim.dimensions
+            )));
+        }
+        if claim.commitment() != commitment {
+            return Ok(false);
+        }
+
+        // Per-row error bound: k·ε·(|A||B|·1)_i, since |r| = 1 everywhere
+        let abs_b_rows: Vec<f64> = b
+            .chunks(n.max(1))
+            .map(|row| row.iter().map(|&x| (x as f64).abs()).sum())
+            .collect();
+        let tolerance: Vec<f64> = a
+            .chunks(k.max(1))
+            .map(|row| {
+                let bound: f64 = row
+                    .iter()
+                    .zip(&abs_b_rows)
+                    .map(|(&x, &b_sum)| (x as f64).abs() * b_sum)
+                    .sum();
+                (k as f64 + 1.0) * f32::EPSILON as f64 * bound + f32::MIN_POSITIVE as f64
+            })
+            .collect();
+
+        for _ in 0..self.rounds() {
+            let r: Vec<f64> = (0..n)
+                .map(|_| if rng.random::<bool>() { 1.0 } else { -1.0 })
+                .collect();
+            let br: Vec<f64> = b.chunks(n.max(1)).map(|row| dot(row, &r)).collect();
+            let abr = a.chunks(k.max(1)).map(|row| dot(row, &br));
+            let cr = claim.output.chunks(n.max(1)).map(|row| dot(row, &r));
+
+            if abr
+                .zip(cr)
+                .zip(&tolerance)
+                .take(m)
+                .any(|((expected, actual), &tol)| (expected - actual).abs() > tol)
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn dot(row: &[f32], vector: &[f64]) -> f64 {
+    row.iter().zip(vector).map(|(&x, &v)| x as f64 * v).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn gemm(a: &[f32], b: &[f32], (m, k, n): (usize, usize, usize)) -> Vec<f32> {
+        let mut c = vec![0.0f32; m * n];
+        for i in 0..m {
+            for j in 0..n {
+                c[i * n + j] = (0..k).map(|l| a[i * k + l] * b[l * n + j]).sum();
+            }
+        }
+        c
+    }
+
+    fn random_matrix(len: usize, rng: &mut rand::rngs::StdRng) -> Vec<f32> {
+        (0..len).map(|_| rng.random_range(-4.0..4.0)).collect()
+    }
+
+    #[test]
+    fn test_header_vectors() {
+        let operation_hashes = vec!["33".repeat(32), "44".repeat(32)];
+        let root = operations_root(&operation_hashes).unwrap();
+        assert_eq!(
+            hex::encode(root),
+            "f85044c9fa56158f02ec065ca2edb6450553f719a1e41dae81216a8aedd55164"
+        );
+
+        let proof = Proof::new(
+            &format!("0x{}", "11".repeat(32)),
+            &"22".repeat(32),
+            0x0102030405060708,
+            operation_hashes,
+            0,
+            0,
+        )
+        .unwrap();
+        let bytes = proof.header.to_bytes();
+        assert_eq!(&bytes[..4], &[1, 0, 0, 0]);
+        assert_eq!(&bytes[4..36], &[0x11; 32]);
+        assert_eq!(&bytes[36..68], &[0x22; 32]);
+        assert_eq!(&bytes[68..100], &root);
+        assert_eq!(&bytes[100..], &[8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(BlockHeader::from_bytes(&bytes), proof.header);
+        assert_eq!(
+            proof.work_hash,
+            "2cb435ab9dce655a4a0003ccff1f337e687be00757c9df8b74c5e3197c3d74f2"
+        );
+
+        assert!(Proof::new("0x00", &"22".repeat(32), 0, Vec::new(), 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_proof_verify_against_target() {
+        let operation_hashes = vec![Proof::hash_operation_result(b"output")];
+        let previous = "ab".repeat(32);
+        let commitment = "cd".repeat(32);
+
+        // About one nonce in 16 meets difficulty 16
+        let proof = (0..1000)
+            .map(|nonce| {
+                Proof::new(
+                    &previous,
+                    &commitment,
+                    nonce,
+                    operation_hashes.clone(),
+                    1,
+                    0,
+                )
+                .unwrap()
+            })
+            .find(|proof| proof.verify(16).unwrap())
+            .unwrap();
+        assert!(proof.work_hash.starts_with('0'));
+        assert!(proof.verify(1).unwrap());
+        assert!(!proof.verify(u64::MAX).unwrap());
+
+        // The header binds the operation hashes and the nonce
+        let mut swapped = proof.clone();
+        swapped.operation_hashes = vec![Proof::hash_operation_result(b"other")];
+        assert!(!swapped.verify(1).unwrap());
+        let mut renonced = proof.clone();
+        renonced.header.nonce += 1;
+        assert!(!renonced.verify(1).unwrap());
+    }
+
+    #[test]
+    fn test_nonce_search() {
+        let proof = Proof::new(
+            &"ab".repeat(32),
+            &"cd".repeat(32),
+            0,
+            vec![Proof::hash_operation_result(b"output")],
+            1,
+            0,
+        )
+        .unwrap();
+        let target = difficulty_to_target(64);
+
+        let search = proof.header.search_nonce((100, 10_000), &target);
+        let nonce = search.nonce.unwrap();
+        assert_eq!(search.hashes, nonce - 100 + 1);
+        let solved = proof.clone().with_nonce(nonce);
+        assert!(solved.verify(64).unwrap());
+        // Every nonce before the solution misses the target
+        assert!((100..nonce).all(|n| !proof.clone().with_nonce(n).verify(64).unwrap()));
+
+        let exhausted = proof.header.search_nonce((100, nonce), &target);
+        assert_eq!(
+            exhausted,
+            NonceSearch {
+                nonce: None,
+                hashes: nonce - 100
+            }
+        );
+    }
+
+    #[test]
+    fn test_signed_proof_tampering_and_replay() {
+        let key = MinerKey::from_hex(&"42".repeat(32)).unwrap();
+        let proof = Proof::new(
+            &"ab".repeat(32),
+            &"cd".repeat(32),
+            9,
+            vec![Proof::hash_operation_result(b"output")],
+            100,
+            0,
+        )
+        .unwrap();
+        assert!(!proof.verify_signature());
+
+        let signed = proof.clone().sign(&key).unwrap();
+        assert_eq!(signed.beneficiary, key.address());
+        assert!(signed.verify_signature());
+
+        // Changing the nonce, results, FLOPs or beneficiary breaks the signature
+        assert!(!signed.clone().with_nonce(10).verify_signature());
+        let mut tampered = signed.clone();
+        tampered.header.nonce = 10;
+        assert!(!tampered.verify_signature());
+        let mut tampered = signed.clone();
+        tampered.header.operations_root = [0; 32];
+        assert!(!tampered.verify_signature());
+        let mut tampered = signed.clone();
+        tampered.total_flops += 1;
+        assert!(!tampered.verify_signature());
+        let thief = MinerKey::from_hex(&"43".repeat(32)).unwrap();
+        let mut tampered = signed.clone();
+        tampered.beneficiary = thief.address();
+        assert!(!tampered.verify_signature());
+
+        // The signature can't be replayed onto a proof of other work
+        let mut replayed = Proof::new(
+            &"ac".repeat(32),
+            &"cd".repeat(32),
+            9,
+            signed.operation_hashes.clone(),
+            100,
+            0,
+        )
+        .unwrap();
+        replayed.beneficiary = signed.beneficiary.clone();
+        replayed.signature = signed.signature.clone();
+        assert!(!replayed.verify_signature());
+
+        // A malformed beneficiary has no commitment to sign
+        tampered.beneficiary = "0x1234".to_string();
+        assert!(tampered.commitment().is_err());
+        assert!(!tampered.verify_signature());
+    }
+
+    #[test]
+    fn test_freivalds_honest_and_tampered() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(41);
+        let dimensions = (24, 96, 40);
+        let a = random_matrix(24 * 96, &mut rng);
+        let b = random_matrix(96 * 40, &mut rng);
+        let claim = GemmClaim {
+            dimensions,
+            output: gemm(&a, &b, dimensions),
+        };
+        let commitment = claim.commitment();
+        let verifier = FreivaldsVerifier::new(1e-6).unwrap();
+        assert_eq!(verifier.rounds(), 20);
+        assert!(verifier
+            .verify(&a, &b, &claim, &commitment, &mut rng)
+            .unwrap());
+
+        // A single wrong element is caught, whether or not it is committed to
+        let mut tampered = claim.clone();
+        tampered.output[7 * 40 + 13] += 0.5;
+        assert!(!verifier
+            .verify(&a, &b, &tampered, &tampered.commitment(), &mut rng)
+            .unwrap());
+        assert!(!verifier
+            .verify(&a, &b, &tampered, &commitment, &mut rng)
+            .unwrap());
+
+        // So is a skipped row
+        let mut lazy = claim.clone();
+        lazy.output[..40].fill(0.0);
+        assert!(!verifier
+            .verify(&a, &b, &lazy, &lazy.commitment(), &mut rng)
+            .unwrap());
+
+        assert!(verifier
+            .verify(&a[1..], &b, &claim, &commitment, &mut rng)
+            .is_err());
+        let overflowing = GemmClaim {
+            dimensions: (usize::MAX, 2, 1),
+            output: claim.output.clone(),
+        };
+        assert!(verifier
+            .verify(&a, &b, &overflowing, &overflowing.commitment(), &mut rng)
+            .is_err());
+        assert!(FreivaldsVerifier::new(0.0).is_err());
     }
 }
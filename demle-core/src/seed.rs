@@ -0,0 +1,108 @@
+use crate::proof::parse_hash;
+use crate::{DemleError, Result};
+use sha3::{Digest, Sha3_256};
+
+/// Domain separator for operation seeds, so they never collide with other
+/// hashes over the same chain state
+const SEED_DOMAIN: &[u8] = b"demle/operation-seed/v1";
+
+/// Decode a 20-byte account address from hex, with or without a `0x` prefix
+pub fn parse_address(address: &str) -> Result<[u8; 20]> {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    hex::decode(digits)
+        .ok()
+        .and_then(|bytes| <[u8; 20]>::try_from(bytes).ok())
+        .ok_or_else(|| {
+            DemleError::ValidationError(format!(
+                "Expected a 20-byte hex address, got '{}'",
+                address
+            ))
+        })
+}
+
+/// Seed of one operation: the first 8 bytes (little-endian) of
+/// `SHA3-256(domain ‖ previous hash ‖ miner address ‖ sequence ‖ operation index)`,
+/// with the integers encoded as u64 LE. It can't be known before the previous
+/// block, differs between miners, and anyone can re-derive it.
+///
+/// The work unit's sequence number is used rather than the proof nonce: seeds
+/// fix the operations before the nonce search starts, so each nonce candidate
+/// costs one hash instead of re-executing the whole work unit.
+pub fn derive_seed(
+    previous_hash: &str,
+    miner_address: &str,
+    sequence: u64,
+    operation_index: usize,
+) -> Result<u64> {
+    let mut hasher = Sha3_256::new();
+    hasher.update(SEED_DOMAIN);
+    hasher.update(parse_hash(previous_hash)?);
+    hasher.update(parse_address(miner_address)?);
+    hasher.update(sequence.to_le_bytes());
+    hasher.update((operation_index as u64).to_le_bytes());
+    let digest = hasher.finalize();
+    Ok(u64::from_le_bytes(digest[..8].try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MLOperation, WorkUnit};
+
+    #[test]
+    fn test_seed_derivation() {
+        let previous = format!("0x{}", "11".repeat(32));
+        let miner = format!("0x{}", "22".repeat(20));
+        let seed = derive_seed(&previous, &miner, 7, 0).unwrap();
+        assert_eq!(seed, 0x039e_ed00_0de4_965e);
+
+        // Every input changes the seed
+        assert_ne!(derive_seed(&"12".repeat(32), &miner, 7, 0).unwrap(), seed);
+        assert_ne!(
+            derive_seed(&previous, &"23".repeat(20), 7, 0).unwrap(),
+            seed
+        );
+        assert_ne!(derive_seed(&previous, &miner, 8, 0).unwrap(), seed);
+        assert_ne!(derive_seed(&previous, &miner, 7, 1).unwrap(), seed);
+
+        assert!(derive_seed(&previous, "0x1234", 7, 0).is_err());
+        assert!(derive_seed("0x00", &miner, 7, 0).is_err());
+    }
+
+    #[test]
+    fn test_work_unit_seeds() {
+        let mut unit = WorkUnit {
+            id: "unit".to_string(),
+            previous_hash: "ab".repeat(32),
+            timestamp: 0,
+            difficulty: 1,
+            operations: vec![
+                MLOperation::MatrixMultiply {
+                    dimensions: (2, 2, 2),
+                    seed: 0,
+                },
+                MLOperation::Softmax {
+                    rows: 2,
+                    cols: 2,
+                    log: false,
+                    seed: 0,
+                },
+            ],
+            nonce_range: (0, 1),
+            miner_address: "cd".repeat(20),
+            sequence: 3,
+        };
+        assert!(!unit.seeds_match().unwrap());
+
+        unit.derive_seeds().unwrap();
+        assert!(unit.seeds_match().unwrap());
+        assert_eq!(
+            unit.operations[1].seed(),
+            Some(derive_seed(&unit.previous_hash, &unit.miner_address, 3, 1).unwrap())
+        );
+
+        // Reusing the work for another block or miner breaks the seeds
+        unit.previous_hash = "ac".repeat(32);
+        assert!(!unit.seeds_match().unwrap());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::seed::parse_address;
+use crate::{DemleError, Result};
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use rand::Rng;
+use sha3::{Digest, Keccak256};
+use std::fmt;
+
+/// Length of an encoded recoverable signature: `r ‖ s ‖ v`
+pub const SIGNATURE_LEN: usize = 65;
+
+/// EIP-191 personal-message hash, `keccak256("\x19Ethereum Signed Message:\n"
+/// ‖ decimal length ‖ message)`, which contracts check with `ecrecover`
+pub fn eip191_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n");
+    hasher.update(message.len().to_string());
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Ethereum-style address of a public key: the last 20 bytes of the
+/// Keccak-256 of its uncompressed encoding without the `0x04` tag
+fn key_address(key: &VerifyingKey) -> [u8; 20] {
+    let point = key.to_encoded_point(false);
+    let digest = Keccak256::digest(&point.as_bytes()[1..]);
+    digest[12..].try_into().unwrap()
+}
+
+/// secp256k1 key a miner signs its proofs with; its address is the beneficiary
+/// of the rewards
+#[derive(Clone)]
+pub struct MinerKey {
+    key: SigningKey,
+}
+
+impl MinerKey {
+    /// Decode a 32-byte secret key from hex, with or without a `0x` prefix
+    pub fn from_hex(secret: &str) -> Result<Self> {
+        let digits = secret.trim().strip_prefix("0x").unwrap_or(secret.trim());
+        hex::decode(digits)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .and_then(|bytes| SigningKey::from_slice(&bytes).ok())
+            .map(|key| Self { key })
+            .ok_or_else(|| {
+                DemleError::ValidationError("Expected a 32-byte hex secp256k1 key".to_string())
+            })
+    }
+
+    /// Fresh random key
+    pub fn generate() -> Self {
+        let mut rng = rand::rng();
+        loop {
+            let bytes: [u8; 32] = rng.random();
+            if let Ok(key) = SigningKey::from_slice(&bytes) {
+                return Self { key };
+            }
+        }
+    }
+
+    /// Hex-encoded secret key
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.key.to_bytes())
+    }
+
+    /// `0x`-prefixed address of the key
+    pub fn address(&self) -> String {
+        format!("0x{}", hex::encode(key_address(self.key.verifying_key())))
+    }
+
+    /// Sign a 32-byte digest, returning the hex-encoded `r ‖ s ‖ v` with the
+    /// low-s form and `v` being 27 or 28
+    pub fn sign(&self, digest: &[u8; 32]) -> Result<String> {
+        let (signature, recovery_id) = self
+            .key
+            .sign_prehash_recoverable(digest)
+            .map_err(|e| DemleError::ComputationError(format!("Failed to sign: {}", e)))?;
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        Ok(hex::encode(bytes))
+    }
+}
+
+impl fmt::Debug for MinerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MinerKey")
+            .field("address", &self.address())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Address of the key that signed `digest`. High-s signatures are rejected, so
+/// a signature can't be altered into another valid one.
+pub fn recover_address(digest: &[u8; 32], signature: &str) -> Result<[u8; 20]> {
+    let invalid = || {
+        DemleError::ValidationError(format!(
+            "Expected a {}-byte hex signature, got '{}'",
+            SIGNATURE_LEN, signature
+        ))
+    };
+    let bytes = hex::decode(signature.strip_prefix("0x").unwrap_or(signature))
+        .ok()
+        .filter(|bytes| bytes.len() == SIGNATURE_LEN)
+        .ok_or_else(invalid)?;
+
+    let recovery_id = bytes[64]
+        .checked_sub(27)
+        .and_then(RecoveryId::from_byte)
+        .ok_or_else(invalid)?;
+    let signature = Signature::from_slice(&bytes[..64]).map_err(|_| invalid())?;
+    if signature.normalize_s().is_some() {
+        return Err(DemleError::ValidationError(
+            "Signature is not in low-s form".to_string(),
+        ));
+    }
+
+    let key = VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+        .map_err(|e| DemleError::ValidationError(format!("Failed to recover signer: {}", e)))?;
+    Ok(key_address(&key))
+}
+
+/// Whether `signature` over `digest` was made by the key of `address`
+pub fn verify_signature(digest: &[u8; 32], signature: &str, address: &str) -> bool {
+    match (recover_address(digest, signature), parse_address(address)) {
+        (Ok(signer), Ok(address)) => signer == address,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_key_address() {
+        // Secret key 1 is the generator point
+        let key = MinerKey::from_hex(&format!("0x{}1", "0".repeat(63))).unwrap();
+        assert_eq!(key.address(), "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf");
+        assert_eq!(
+            MinerKey::from_hex(&key.to_hex()).unwrap().address(),
+            key.address()
+        );
+
+        assert!(MinerKey::from_hex("0x1234").is_err());
+        assert!(MinerKey::from_hex(&"0".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_eip191_hash() {
+        assert_eq!(
+            hex::encode(Keccak256::digest(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(eip191_hash(b"hello world")),
+            "d9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68"
+        );
+    }
+
+    #[test]
+    fn test_sign_and_recover() {
+        let key = MinerKey::generate();
+        let digest = [7u8; 32];
+        let signature = key.sign(&digest).unwrap();
+        assert_eq!(signature.len(), 2 * SIGNATURE_LEN);
+        assert!(verify_signature(&digest, &signature, &key.address()));
+
+        // Another digest, signer or byte of the signature fails
+        assert!(!verify_signature(&[8u8; 32], &signature, &key.address()));
+        assert!(!verify_signature(
+            &digest,
+            &signature,
+            &MinerKey::generate().address()
+        ));
+        let mut bytes = hex::decode(&signature).unwrap();
+        bytes[10] ^= 1;
+        assert!(!verify_signature(
+            &digest,
+            &hex::encode(&bytes),
+            &key.address()
+        ));
+        assert!(recover_address(&digest, "0x1234").is_err());
+    }
+}
@@ -16,6 +16,7 @@ pub type Difficulty = u64;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MiningStats {
     pub hashrate: f64,         // Hashes per second
+    pub total_hashes: u64,     // Header hashes computed while searching nonces
     pub teraflops: f64,        // Teraflops per second
     pub blocks_found: u64,     // Total blocks found
     pub total_operations: u64, // Total ML operations performed
@@ -27,6 +28,7 @@ impl Default for MiningStats {
     fn default() -> Self {
         Self {
             hashrate: 0.0,
+            total_hashes: 0,
             teraflops: 0.0,
             blocks_found: 0,
             total_operations: 0,
@@ -1,9 +1,17 @@
 use crate::fp8::FP8;
-use crate::operations::{generate_random_tensor, softmax};
-use demle_core::{proof::Proof, Result};
+use crate::operations::{
+    flops_product, flops_sum, generate_random_tensor, softmax, OperationTensors,
+};
+use demle_core::{DemleError, Result};
 
 #[cfg(feature = "cuda")]
-use candle_core::{Device, Tensor, DType};
+use demle_core::proof::Proof;
+#[cfg(feature = "cuda")]
+use crate::quantize::{dequantize, from_candle, quantize, to_candle};
+#[cfg(feature = "cuda")]
+use candle_core::Device;
+#[cfg(feature = "cuda")]
+use demle_core::NumericFormat;
 
 /// Execute multi-head attention operation with H100 optimization
 pub fn execute_attention(
@@ -13,6 +21,8 @@ pub fn execute_attention(
     num_heads: usize,
     seed: u64,
 ) -> Result<(String, u64)> {
+    validate_attention_heads(d_model, num_heads)?;
+
     #[cfg(feature = "cuda")]
     {
         execute_attention_gpu(batch_size, seq_length, d_model, num_heads, seed)
@@ -58,12 +68,10 @@ fn execute_attention_gpu(
         .map(|_| normal.sample(&mut rng) as f32)
         .collect();
 
-    let q = Tensor::from_vec(q_data, (batch_size, seq_length, d_model), &device)?
-        .to_dtype(DType::BF16)?;
-    let k = Tensor::from_vec(k_data, (batch_size, seq_length, d_model), &device)?
-        .to_dtype(DType::BF16)?;
-    let v = Tensor::from_vec(v_data, (batch_size, seq_length, d_model), &device)?
-        .to_dtype(DType::BF16)?;
+    let shape = [batch_size, seq_length, d_model];
+    let q = to_candle(&quantize(&q_data, NumericFormat::BF16), &shape, &device)?;
+    let k = to_candle(&quantize(&k_data, NumericFormat::BF16), &shape, &device)?;
+    let v = to_candle(&quantize(&v_data, NumericFormat::BF16), &shape, &device)?;
 
     // Reshape for multi-head attention with H100 optimization
     let q_heads = q.reshape((batch_size, seq_length, num_heads, d_k))?
@@ -97,10 +105,7 @@ fn execute_attention_gpu(
     }
 
     // Get result back to CPU for hashing
-    let output_data: Vec<f32> = final_output.unwrap()
-        .to_dtype(DType::F32)?
-        .flatten_all()?
-        .to_vec1()?;
+    let output_data = dequantize(&from_candle(&final_output.unwrap(), NumericFormat::F32)?);
 
     // Convert to FP8 for consistent hashing
     let fp8_data: Vec<FP8> = output_data.iter().map(|&f| FP8::from_f32(f)).collect();
@@ -117,7 +122,22 @@ fn execute_attention_cpu(
     num_heads: usize,
     seed: u64,
 ) -> Result<(String, u64)> {
-    let d_k = d_model / num_heads;
+    // Calculate FLOPS (simplified estimation)
+    let total_flops = attention_flops(batch_size, seq_length, d_model, num_heads)?;
+    let tensors = attention_tensors(batch_size, seq_length, d_model, num_heads, seed)?;
+
+    Ok((tensors.result_hash(), total_flops))
+}
+
+/// Seeded input and Q, K and V projections and output of multi-head attention
+pub fn attention_tensors(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    seed: u64,
+) -> Result<OperationTensors> {
+    validate_attention_heads(d_model, num_heads)?;
 
     // Generate random input (batch_size, seq_length, d_model)
     let input_data = generate_random_tensor(&[batch_size, seq_length, d_model], seed)?;
@@ -126,6 +146,72 @@ fn execute_attention_cpu(
     let wq = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(1))?;
     let wk = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(2))?;
     let wv = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(3))?;
+    let mut tensors = OperationTensors::default();
+    for operand in [&input_data, &wq, &wk, &wv] {
+        tensors.add_fp8(operand);
+    }
+
+    let output = multi_head_attention(
+        &input_data,
+        &wq,
+        &wk,
+        &wv,
+        batch_size,
+        seq_length,
+        d_model,
+        num_heads,
+    );
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// Check that `d_model` is non-zero and splits evenly into `num_heads` heads
+pub fn validate_attention_heads(d_model: usize, num_heads: usize) -> Result<()> {
+    if d_model == 0 {
+        return Err(DemleError::ValidationError(
+            "d_model must be non-zero".to_string(),
+        ));
+    }
+    if num_heads == 0 || !d_model.is_multiple_of(num_heads) {
+        return Err(DemleError::ValidationError(format!(
+            "d_model {} is not divisible by num_heads {}",
+            d_model, num_heads
+        )));
+    }
+    Ok(())
+}
+
+/// FLOP count of multi-head attention (simplified estimation)
+pub fn attention_flops(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+) -> Result<u64> {
+    let d_k = d_model / num_heads;
+
+    let qkv_flops = flops_product(&[3, batch_size, seq_length, d_model, d_model])?;
+    let attention_flops = flops_product(&[2, batch_size, num_heads, seq_length, seq_length, d_k])?;
+    let output_flops = flops_product(&[batch_size, seq_length, d_model, d_model])?;
+    flops_sum(&[qkv_flops, attention_flops, output_flops])
+}
+
+/// Multi-head self-attention over a (batch_size, seq_length, d_model) input
+/// with (d_model, d_model) Q, K and V projections.
+///
+/// Returns the concatenated head outputs in the input layout.
+#[allow(clippy::too_many_arguments)]
+pub fn multi_head_attention(
+    input_data: &[FP8],
+    wq: &[FP8],
+    wk: &[FP8],
+    wv: &[FP8],
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+) -> Vec<FP8> {
+    let d_k = d_model / num_heads;
 
     let mut output = vec![FP8::zero(); batch_size * seq_length * d_model];
 
@@ -202,24 +288,7 @@ fn execute_attention_cpu(
         }
     }
 
-    // Calculate FLOPS (simplified estimation)
-    let qkv_flops =
-        3 * (batch_size as u64) * (seq_length as u64) * (d_model as u64) * (d_model as u64);
-    let attention_flops = (batch_size as u64)
-        * (num_heads as u64)
-        * (seq_length as u64)
-        * (seq_length as u64)
-        * (d_k as u64);
-    let output_flops =
-        (batch_size as u64) * (seq_length as u64) * (d_model as u64) * (d_model as u64);
-    let total_flops = qkv_flops + 2 * attention_flops + output_flops;
-
-    // Hash the result
-    let result_bytes: Vec<u8> = output.iter().flat_map(|fp8| vec![fp8.to_bits()]).collect();
-
-    let result_hash = Proof::hash_operation_result(&result_bytes);
-
-    Ok((result_hash, total_flops))
+    output
 }
 
 #[cfg(test)]
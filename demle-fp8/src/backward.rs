@@ -0,0 +1,775 @@
+use crate::attention::{attention_flops, validate_attention_heads};
+use crate::convolution::{conv2d_flops, conv2d_output_size, validate_conv2d_window};
+use crate::fp8::{FP8, FP8E5M2};
+use crate::gemm::gemm_flops;
+use crate::operations::{
+    flops_product, flops_sum, generate_random_gradient, generate_random_tensor, OperationOutput,
+    OperationTensors,
+};
+use demle_core::{proof::Proof, DemleError, Result};
+use rayon::prelude::*;
+
+/// Execute the GEMM backward pass for C = A * B on seeded E4M3 operands and a
+/// seeded E5M2 upstream gradient: dA = dC * B^T and dB = A^T * dC
+pub fn execute_gemm_backward(
+    dimensions: (usize, usize, usize),
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_gemm_backward(dimensions, seed)?.0)
+}
+
+/// Seeded operands and upstream gradient and the E5M2 gradients of a GEMM
+/// backward pass
+pub fn gemm_backward_tensors(
+    dimensions: (usize, usize, usize),
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_gemm_backward(dimensions, seed)?.1)
+}
+
+fn run_gemm_backward(
+    dimensions: (usize, usize, usize),
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    let (m, k, n) = dimensions;
+
+    let mut tensors = OperationTensors::default();
+    let a = tensors.decode(generate_random_tensor(&[m, k], seed)?);
+    let b = tensors.decode(generate_random_tensor(&[k, n], seed.wrapping_add(1))?);
+    let grad_c = tensors.decode_gradient(generate_random_gradient(&[m, n], seed.wrapping_add(2))?);
+
+    let (grad_a, grad_b) = gemm_backward(&a, &b, &grad_c, m, k, n);
+
+    Ok(gradient_output(
+        tensors,
+        &[("grad_a", &grad_a), ("grad_b", &grad_b)],
+        gemm_backward_flops(dimensions)?,
+    ))
+}
+
+/// Execute the 2D convolution backward pass, producing input and kernel gradients
+pub fn execute_conv2d_backward(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_conv2d_backward(input_shape, kernel_shape, stride, padding, seed)?.0)
+}
+
+/// Seeded input, kernel and upstream gradient and the E5M2 gradients of a
+/// convolution backward pass
+pub fn conv2d_backward_tensors(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_conv2d_backward(input_shape, kernel_shape, stride, padding, seed)?.1)
+}
+
+fn run_conv2d_backward(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    validate_conv2d_backward(input_shape, kernel_shape, stride, padding)?;
+    let (batch, in_ch, ih, iw) = input_shape;
+    let (out_ch, _, kh, kw) = kernel_shape;
+    let (oh, ow) = conv2d_output_size(input_shape, kernel_shape, stride, padding)?;
+
+    let mut tensors = OperationTensors::default();
+    let input = tensors.decode(generate_random_tensor(&[batch, in_ch, ih, iw], seed)?);
+    let kernel = tensors.decode(generate_random_tensor(
+        &[out_ch, in_ch, kh, kw],
+        seed.wrapping_add(1),
+    )?);
+    let grad_output = tensors.decode_gradient(generate_random_gradient(
+        &[batch, out_ch, oh, ow],
+        seed.wrapping_add(2),
+    )?);
+
+    let (grad_input, grad_kernel) = conv2d_backward(
+        &input,
+        &kernel,
+        &grad_output,
+        input_shape,
+        kernel_shape,
+        stride,
+        padding,
+    );
+
+    Ok(gradient_output(
+        tensors,
+        &[("grad_input", &grad_input), ("grad_kernel", &grad_kernel)],
+        conv2d_backward_flops(input_shape, kernel_shape, stride, padding)?,
+    ))
+}
+
+/// Check a convolution backward pass: the kernel must match the input
+/// channels and fit the padded input
+pub fn validate_conv2d_backward(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<()> {
+    let (_, in_ch, _, _) = input_shape;
+    let (_, kernel_in_ch, _, _) = kernel_shape;
+    if kernel_in_ch != in_ch {
+        return Err(DemleError::ValidationError(format!(
+            "Kernel expects {} input channels, input has {}",
+            kernel_in_ch, in_ch
+        )));
+    }
+    validate_conv2d_window(input_shape, kernel_shape, stride, padding)
+}
+
+/// Execute the multi-head attention backward pass, producing gradients for the
+/// input and the Q, K and V projections
+pub fn execute_attention_backward(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_attention_backward(batch_size, seq_length, d_model, num_heads, seed)?.0)
+}
+
+/// Seeded input, projections and upstream gradient and the E5M2 gradients of
+/// an attention backward pass
+pub fn attention_backward_tensors(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_attention_backward(batch_size, seq_length, d_model, num_heads, seed)?.1)
+}
+
+fn run_attention_backward(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    validate_attention_heads(d_model, num_heads)?;
+
+    // Same input and projections as the forward attention operation
+    let shape = [batch_size, seq_length, d_model];
+    let mut tensors = OperationTensors::default();
+    let input = tensors.decode(generate_random_tensor(&shape, seed)?);
+    let wq = tensors.decode(generate_random_tensor(
+        &[d_model, d_model],
+        seed.wrapping_add(1),
+    )?);
+    let wk = tensors.decode(generate_random_tensor(
+        &[d_model, d_model],
+        seed.wrapping_add(2),
+    )?);
+    let wv = tensors.decode(generate_random_tensor(
+        &[d_model, d_model],
+        seed.wrapping_add(3),
+    )?);
+    let grad_output =
+        tensors.decode_gradient(generate_random_gradient(&shape, seed.wrapping_add(4))?);
+
+    let grads = attention_backward(
+        &input,
+        &wq,
+        &wk,
+        &wv,
+        &grad_output,
+        batch_size,
+        seq_length,
+        d_model,
+        num_heads,
+    );
+
+    Ok(gradient_output(
+        tensors,
+        &[
+            ("grad_input", &grads.input),
+            ("grad_wq", &grads.wq),
+            ("grad_wk", &grads.wk),
+            ("grad_wv", &grads.wv),
+        ],
+        attention_backward_flops(batch_size, seq_length, d_model, num_heads)?,
+    ))
+}
+
+/// Quantize named gradients (accumulated in f32) to E5M2, hash each one and
+/// hash their concatenation
+fn gradient_output(
+    tensors: OperationTensors,
+    gradients: &[(&str, &[f32])],
+    flops: u64,
+) -> (OperationOutput, OperationTensors) {
+    let mut all_bytes = Vec::new();
+    let mut intermediate_hashes = Vec::new();
+
+    for (name, gradient) in gradients {
+        let bytes: Vec<u8> = gradient
+            .iter()
+            .map(|&g| FP8E5M2::from_f32(g).to_bits())
+            .collect();
+        intermediate_hashes.push((name.to_string(), Proof::hash_operation_result(&bytes)));
+        all_bytes.extend(bytes);
+    }
+
+    let output = OperationOutput {
+        result_hash: Proof::hash_operation_result(&all_bytes),
+        flops,
+        intermediate_hashes,
+        metrics: Vec::new(),
+    };
+    (
+        output,
+        OperationTensors {
+            output: all_bytes,
+            ..tensors
+        },
+    )
+}
+
+fn decode(data: &[FP8]) -> Vec<f32> {
+    data.iter().map(|x| x.to_f32()).collect()
+}
+
+impl OperationTensors {
+    /// Record a seeded FP8 operand and widen it to f32
+    fn decode(&mut self, data: Vec<FP8>) -> Vec<f32> {
+        self.add_fp8(&data);
+        decode(&data)
+    }
+
+    /// Record a seeded E5M2 gradient and widen it to f32
+    fn decode_gradient(&mut self, data: Vec<FP8E5M2>) -> Vec<f32> {
+        self.add_gradient(&data);
+        data.iter().map(|x| x.to_f32()).collect()
+    }
+}
+
+/// f32 GEMM: C(m×n) = op(A) * op(B), where op transposes a row-major operand
+/// stored as (k×m) or (n×k) when the corresponding flag is set
+pub(crate) fn matmul(
+    a: &[f32],
+    transpose_a: bool,
+    b: &[f32],
+    transpose_b: bool,
+    m: usize,
+    k: usize,
+    n: usize,
+) -> Vec<f32> {
+    let mut c = vec![0.0f32; m * n];
+    c.par_chunks_mut(n.max(1)).enumerate().for_each(|(i, row)| {
+        for l in 0..k {
+            let a_val = if transpose_a {
+                a[l * m + i]
+            } else {
+                a[i * k + l]
+            };
+            for (j, out) in row.iter_mut().enumerate() {
+                let b_val = if transpose_b {
+                    b[j * k + l]
+                } else {
+                    b[l * n + j]
+                };
+                *out += a_val * b_val;
+            }
+        }
+    });
+    c
+}
+
+/// Gradients of C = A * B with respect to A (m×k) and B (k×n)
+pub fn gemm_backward(
+    a: &[f32],
+    b: &[f32],
+    grad_c: &[f32],
+    m: usize,
+    k: usize,
+    n: usize,
+) -> (Vec<f32>, Vec<f32>) {
+    let grad_a = matmul(grad_c, false, b, true, m, n, k);
+    let grad_b = matmul(a, true, grad_c, false, k, m, n);
+    (grad_a, grad_b)
+}
+
+/// Visit every (output element, kernel tap) pair of a convolution that reads
+/// a non-padding input element, passing (output_idx, input_idx, kernel_idx)
+fn for_each_conv_tap(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    mut visit: impl FnMut(usize, usize, usize),
+) {
+    let (batch, in_ch, ih, iw) = input_shape;
+    let (out_ch, _, kh, kw) = kernel_shape;
+    let (sh, sw) = stride;
+    let (ph, pw) = padding;
+    let (oh, ow) =
+        conv2d_output_size(input_shape, kernel_shape, stride, padding).unwrap_or_default();
+
+    for b in 0..batch {
+        for oc in 0..out_ch {
+            for y in 0..oh {
+                for x in 0..ow {
+                    let output_idx = ((b * out_ch + oc) * oh + y) * ow + x;
+                    for ic in 0..in_ch {
+                        for ky in 0..kh {
+                            for kx in 0..kw {
+                                let input_y = y * sh + ky;
+                                let input_x = x * sw + kx;
+                                if input_y < ph || input_x < pw {
+                                    continue;
+                                }
+                                let (input_y, input_x) = (input_y - ph, input_x - pw);
+                                if input_y >= ih || input_x >= iw {
+                                    continue;
+                                }
+
+                                let input_idx = ((b * in_ch + ic) * ih + input_y) * iw + input_x;
+                                let kernel_idx = ((oc * in_ch + ic) * kh + ky) * kw + kx;
+                                visit(output_idx, input_idx, kernel_idx);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// f32 reference 2D convolution (NCHW input, OIHW kernel)
+pub fn conv2d_forward(
+    input: &[f32],
+    kernel: &[f32],
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Vec<f32> {
+    let (batch, _, _, _) = input_shape;
+    let (out_ch, _, _, _) = kernel_shape;
+    let (oh, ow) =
+        conv2d_output_size(input_shape, kernel_shape, stride, padding).unwrap_or_default();
+
+    let mut output = vec![0.0f32; batch * out_ch * oh * ow];
+    for_each_conv_tap(input_shape, kernel_shape, stride, padding, |o, i, k| {
+        output[o] += input[i] * kernel[k];
+    });
+    output
+}
+
+/// Gradients of a 2D convolution with respect to its input and kernel
+pub fn conv2d_backward(
+    input: &[f32],
+    kernel: &[f32],
+    grad_output: &[f32],
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> (Vec<f32>, Vec<f32>) {
+    let mut grad_input = vec![0.0f32; input.len()];
+    let mut grad_kernel = vec![0.0f32; kernel.len()];
+
+    for_each_conv_tap(input_shape, kernel_shape, stride, padding, |o, i, k| {
+        grad_input[i] += grad_output[o] * kernel[k];
+        grad_kernel[k] += grad_output[o] * input[i];
+    });
+
+    (grad_input, grad_kernel)
+}
+
+/// Gradients of multi-head attention
+pub struct AttentionGradients {
+    pub input: Vec<f32>,
+    pub wq: Vec<f32>,
+    pub wk: Vec<f32>,
+    pub wv: Vec<f32>,
+}
+
+/// Per-head forward activations kept for the backward pass
+struct HeadActivations {
+    q: Vec<f32>,
+    k: Vec<f32>,
+    v: Vec<f32>,
+    /// Attention probabilities (seq_length × seq_length)
+    p: Vec<f32>,
+}
+
+/// Extract the columns of head `h` from a (d_model × d_model) projection
+fn head_columns(w: &[f32], d_model: usize, h: usize, d_k: usize) -> Vec<f32> {
+    (0..d_model)
+        .flat_map(|row| w[row * d_model + h * d_k..row * d_model + (h + 1) * d_k].to_vec())
+        .collect()
+}
+
+fn head_forward(
+    x: &[f32],
+    wq: &[f32],
+    wk: &[f32],
+    wv: &[f32],
+    s: usize,
+    d: usize,
+    d_k: usize,
+) -> HeadActivations {
+    let q = matmul(x, false, wq, false, s, d, d_k);
+    let k = matmul(x, false, wk, false, s, d, d_k);
+    let v = matmul(x, false, wv, false, s, d, d_k);
+
+    let scale = 1.0 / (d_k as f32).sqrt();
+    let mut p = matmul(&q, false, &k, true, s, d_k, s);
+    for row in p.chunks_mut(s.max(1)) {
+        let max = row
+            .iter()
+            .map(|&x| x * scale)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let mut sum = 0.0;
+        for value in row.iter_mut() {
+            *value = (*value * scale - max).exp();
+            sum += *value;
+        }
+        for value in row.iter_mut() {
+            *value /= sum;
+        }
+    }
+
+    HeadActivations { q, k, v, p }
+}
+
+/// f32 reference multi-head attention with the same layout as
+/// [`crate::attention::multi_head_attention`]
+#[allow(clippy::too_many_arguments)]
+pub fn attention_forward(
+    input: &[f32],
+    wq: &[f32],
+    wk: &[f32],
+    wv: &[f32],
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+) -> Vec<f32> {
+    let d_k = d_model / num_heads;
+    let mut output = vec![0.0f32; input.len()];
+
+    for b in 0..batch_size {
+        let x = &input[b * seq_length * d_model..(b + 1) * seq_length * d_model];
+        for h in 0..num_heads {
+            let act = head_forward(
+                x,
+                &head_columns(wq, d_model, h, d_k),
+                &head_columns(wk, d_model, h, d_k),
+                &head_columns(wv, d_model, h, d_k),
+                seq_length,
+                d_model,
+                d_k,
+            );
+            let o = matmul(&act.p, false, &act.v, false, seq_length, seq_length, d_k);
+            for i in 0..seq_length {
+                let out_row = (b * seq_length + i) * d_model + h * d_k;
+                output[out_row..out_row + d_k].copy_from_slice(&o[i * d_k..(i + 1) * d_k]);
+            }
+        }
+    }
+
+    output
+}
+
+/// Gradients of multi-head attention with respect to its input and projections
+#[allow(clippy::too_many_arguments)]
+pub fn attention_backward(
+    input: &[f32],
+    wq: &[f32],
+    wk: &[f32],
+    wv: &[f32],
+    grad_output: &[f32],
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+) -> AttentionGradients {
+    let (s, d) = (seq_length, d_model);
+    let d_k = d / num_heads;
+    let scale = 1.0 / (d_k as f32).sqrt();
+
+    let mut grads = AttentionGradients {
+        input: vec![0.0; input.len()],
+        wq: vec![0.0; d * d],
+        wk: vec![0.0; d * d],
+        wv: vec![0.0; d * d],
+    };
+
+    for b in 0..batch_size {
+        let x = &input[b * s * d..(b + 1) * s * d];
+        for h in 0..num_heads {
+            let (wq_h, wk_h, wv_h) = (
+                head_columns(wq, d, h, d_k),
+                head_columns(wk, d, h, d_k),
+                head_columns(wv, d, h, d_k),
+            );
+            let act = head_forward(x, &wq_h, &wk_h, &wv_h, s, d, d_k);
+
+            // Upstream gradient of this head's output (s × d_k)
+            let grad_o: Vec<f32> = (0..s)
+                .flat_map(|i| {
+                    let row = (b * s + i) * d + h * d_k;
+                    grad_output[row..row + d_k].to_vec()
+                })
+                .collect();
+
+            // O = P * V
+            let grad_v = matmul(&act.p, true, &grad_o, false, s, s, d_k);
+            let grad_p = matmul(&grad_o, false, &act.v, true, s, d_k, s);
+
+            // Softmax: dS = P * (dP - rowsum(dP * P)), then the 1/sqrt(d_k) scale
+            let mut grad_s = vec![0.0f32; s * s];
+            for i in 0..s {
+                let row = i * s..(i + 1) * s;
+                let dot: f32 = act.p[row.clone()]
+                    .iter()
+                    .zip(&grad_p[row.clone()])
+                    .map(|(p, g)| p * g)
+                    .sum();
+                for j in row {
+                    grad_s[j] = act.p[j] * (grad_p[j] - dot) * scale;
+                }
+            }
+
+            // S = Q * K^T
+            let grad_q = matmul(&grad_s, false, &act.k, false, s, s, d_k);
+            let grad_k = matmul(&grad_s, true, &act.q, false, s, s, d_k);
+
+            // Projections Q = X * Wq_h etc.
+            for (grad_w, grad_proj, w_h) in [
+                (&mut grads.wq, &grad_q, &wq_h),
+                (&mut grads.wk, &grad_k, &wk_h),
+                (&mut grads.wv, &grad_v, &wv_h),
+            ] {
+                let grad_w_h = matmul(x, true, grad_proj, false, d, s, d_k);
+                for row in 0..d {
+                    for j in 0..d_k {
+                        grad_w[row * d + h * d_k + j] += grad_w_h[row * d_k + j];
+                    }
+                }
+
+                let grad_x = matmul(grad_proj, false, w_h, true, s, d_k, d);
+                for (acc, g) in grads.input[b * s * d..(b + 1) * s * d]
+                    .iter_mut()
+                    .zip(&grad_x)
+                {
+                    *acc += g;
+                }
+            }
+        }
+    }
+
+    grads
+}
+
+/// FLOP count of the GEMM backward pass: two GEMMs of the forward size
+pub fn gemm_backward_flops(dimensions: (usize, usize, usize)) -> Result<u64> {
+    let forward = gemm_flops(dimensions)?;
+    flops_sum(&[forward, forward])
+}
+
+/// FLOP count of the convolution backward pass: input and kernel gradients
+/// each cost one forward convolution
+pub fn conv2d_backward_flops(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<u64> {
+    let forward = conv2d_flops(input_shape, kernel_shape, stride, padding)?;
+    flops_sum(&[forward, forward])
+}
+
+/// FLOP count of the attention backward pass: the forward recomputation plus
+/// gradients of the score, value and projection GEMMs and the softmax
+pub fn attention_backward_flops(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+) -> Result<u64> {
+    let (batch, seq) = (batch_size, seq_length);
+    let d_k = d_model / num_heads;
+
+    // dV, dP, dQ and dK
+    let score_flops = flops_product(&[4 * 2, batch, num_heads, seq, seq, d_k])?;
+    // Softmax backward
+    let softmax_flops = flops_product(&[4, batch, num_heads, seq, seq])?;
+    // Projection weight and input gradients for Q, K and V
+    let projection_flops = flops_product(&[2 * 3 * 2, batch, seq, d_model, d_model])?;
+
+    flops_sum(&[
+        attention_flops(batch_size, seq_length, d_model, num_heads)?,
+        score_flops,
+        softmax_flops,
+        projection_flops,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central finite difference of L = sum(grad_output * f(x)) with respect to each
+    /// element of x, compared against the analytical gradient
+    fn check_gradient(
+        x: &[f32],
+        grad_output: &[f32],
+        analytical: &[f32],
+        forward: impl Fn(&[f32]) -> Vec<f32>,
+    ) {
+        let epsilon = 1e-2;
+        let loss = |x: &[f32]| -> f64 {
+            forward(x)
+                .iter()
+                .zip(grad_output)
+                .map(|(&y, &g)| y as f64 * g as f64)
+                .sum()
+        };
+
+        for i in 0..x.len() {
+            let mut plus = x.to_vec();
+            let mut minus = x.to_vec();
+            plus[i] += epsilon;
+            minus[i] -= epsilon;
+            let numerical = ((loss(&plus) - loss(&minus)) / (2.0 * epsilon as f64)) as f32;
+
+            let tolerance = 2e-2 * (1.0 + numerical.abs());
+            assert!(
+                (numerical - analytical[i]).abs() < tolerance,
+                "gradient mismatch at {}: numerical {} vs analytical {}",
+                i,
+                numerical,
+                analytical[i]
+            );
+        }
+    }
+
+    fn seeded(shape: &[usize], seed: u64) -> Vec<f32> {
+        decode(&generate_random_tensor(shape, seed).unwrap())
+    }
+
+    #[test]
+    fn test_gemm_backward_finite_difference() {
+        let (m, k, n) = (3, 4, 5);
+        let a = seeded(&[m, k], 1);
+        let b = seeded(&[k, n], 2);
+        let grad_c = seeded(&[m, n], 3);
+
+        let (grad_a, grad_b) = gemm_backward(&a, &b, &grad_c, m, k, n);
+
+        check_gradient(&a, &grad_c, &grad_a, |a| {
+            matmul(a, false, &b, false, m, k, n)
+        });
+        check_gradient(&b, &grad_c, &grad_b, |b| {
+            matmul(&a, false, b, false, m, k, n)
+        });
+    }
+
+    #[test]
+    fn test_conv2d_backward_finite_difference() {
+        let input_shape = (2, 2, 5, 5);
+        let kernel_shape = (3, 2, 3, 3);
+        let stride = (2, 1);
+        let padding = (1, 0);
+        let (oh, ow) = conv2d_output_size(input_shape, kernel_shape, stride, padding).unwrap();
+
+        let input = seeded(&[2, 2, 5, 5], 1);
+        let kernel = seeded(&[3, 2, 3, 3], 2);
+        let grad_output = seeded(&[2, 3, oh, ow], 3);
+
+        let (grad_input, grad_kernel) = conv2d_backward(
+            &input,
+            &kernel,
+            &grad_output,
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+        );
+
+        check_gradient(&input, &grad_output, &grad_input, |x| {
+            conv2d_forward(x, &kernel, input_shape, kernel_shape, stride, padding)
+        });
+        check_gradient(&kernel, &grad_output, &grad_kernel, |k| {
+            conv2d_forward(&input, k, input_shape, kernel_shape, stride, padding)
+        });
+    }
+
+    #[test]
+    fn test_attention_backward_finite_difference() {
+        let (batch, seq, d_model, heads) = (2, 3, 4, 2);
+        // Small weights keep the softmax away from saturation
+        let scaled = |shape: &[usize], seed| -> Vec<f32> {
+            seeded(shape, seed).iter().map(|x| x * 0.5).collect()
+        };
+        let input = scaled(&[batch, seq, d_model], 1);
+        let wq = scaled(&[d_model, d_model], 2);
+        let wk = scaled(&[d_model, d_model], 3);
+        let wv = scaled(&[d_model, d_model], 4);
+        let grad_output = seeded(&[batch, seq, d_model], 5);
+
+        let grads = attention_backward(
+            &input,
+            &wq,
+            &wk,
+            &wv,
+            &grad_output,
+            batch,
+            seq,
+            d_model,
+            heads,
+        );
+
+        check_gradient(&input, &grad_output, &grads.input, |x| {
+            attention_forward(x, &wq, &wk, &wv, batch, seq, d_model, heads)
+        });
+        check_gradient(&wq, &grad_output, &grads.wq, |w| {
+            attention_forward(&input, w, &wk, &wv, batch, seq, d_model, heads)
+        });
+        check_gradient(&wk, &grad_output, &grads.wk, |w| {
+            attention_forward(&input, &wq, w, &wv, batch, seq, d_model, heads)
+        });
+        check_gradient(&wv, &grad_output, &grads.wv, |w| {
+            attention_forward(&input, &wq, &wk, w, batch, seq, d_model, heads)
+        });
+    }
+
+    #[test]
+    fn test_backward_execution_deterministic() {
+        let gemm1 = execute_gemm_backward((8, 16, 4), 42).unwrap();
+        let gemm2 = execute_gemm_backward((8, 16, 4), 42).unwrap();
+        assert_eq!(gemm1.result_hash, gemm2.result_hash);
+        assert_eq!(gemm1.flops, 4 * 8 * 16 * 4);
+        assert_eq!(gemm1.intermediate_hashes.len(), 2);
+
+        let conv = execute_conv2d_backward((1, 2, 6, 6), (4, 2, 3, 3), (1, 1), (1, 1), 7).unwrap();
+        assert_eq!(conv.intermediate_hashes[0].0, "grad_input");
+        assert!(conv.flops > 0);
+
+        let attention = execute_attention_backward(1, 4, 8, 2, 9).unwrap();
+        assert_eq!(attention.intermediate_hashes.len(), 4);
+        assert!(attention.flops > attention_flops(1, 4, 8, 2).unwrap());
+
+        assert!(execute_attention_backward(1, 4, 6, 4, 9).is_err());
+        assert!(execute_conv2d_backward((1, 2, 6, 6), (4, 3, 3, 3), (1, 1), (1, 1), 7).is_err());
+        assert!(execute_conv2d_backward((1, 2, 2, 2), (4, 2, 5, 5), (1, 1), (1, 1), 7).is_err());
+        assert!(execute_conv2d_backward((1, 2, 6, 6), (4, 2, 3, 3), (0, 1), (1, 1), 7).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::fp8::FP8;
-use crate::operations::generate_random_tensor;
-use demle_core::{proof::Proof, Result};
+use crate::operations::{flops_product, generate_random_tensor, OperationTensors};
+use demle_core::Result;
 
 /// Execute batch normalization operation
 pub fn execute_batch_norm(
@@ -8,6 +8,19 @@ pub fn execute_batch_norm(
     epsilon: f32,
     seed: u64,
 ) -> Result<(String, u64)> {
+    // Calculate FLOPS
+    let total_flops = batch_norm_flops(shape)?;
+    let tensors = batch_norm_tensors(shape, epsilon, seed)?;
+
+    Ok((tensors.result_hash(), total_flops))
+}
+
+/// Seeded input, gamma and beta and output of batch normalization
+pub fn batch_norm_tensors(
+    shape: (usize, usize, usize, usize),
+    epsilon: f32,
+    seed: u64,
+) -> Result<OperationTensors> {
     let (batch, channels, height, width) = shape;
     let total_size = batch * channels * height * width;
 
@@ -17,6 +30,10 @@ pub fn execute_batch_norm(
     // Generate random gamma and beta parameters
     let gamma = generate_random_tensor(&[channels], seed.wrapping_add(1))?;
     let beta = generate_random_tensor(&[channels], seed.wrapping_add(2))?;
+    let mut tensors = OperationTensors::default();
+    for operand in [&input_data, &gamma, &beta] {
+        tensors.add_fp8(operand);
+    }
 
     let mut output = vec![FP8::zero(); total_size];
 
@@ -68,17 +85,15 @@ pub fn execute_batch_norm(
         }
     }
 
-    // Calculate FLOPS
-    // Mean: N operations, Variance: 2N operations, Normalize: 3N operations per channel
-    let flops_per_channel = 6 * (batch * height * width) as u64;
-    let total_flops = (channels as u64) * flops_per_channel;
-
-    // Hash the result
-    let result_bytes: Vec<u8> = output.iter().flat_map(|fp8| vec![fp8.to_bits()]).collect();
+    Ok(tensors.with_fp8_output(&output))
+}
 
-    let result_hash = Proof::hash_operation_result(&result_bytes);
+/// FLOP count of batch normalization
+pub fn batch_norm_flops(shape: (usize, usize, usize, usize)) -> Result<u64> {
+    let (batch, channels, height, width) = shape;
 
-    Ok((result_hash, total_flops))
+    // Mean: N operations, Variance: 2N operations, Normalize: 3N operations per channel
+    flops_product(&[6, batch, height, width, channels])
 }
 
 #[cfg(test)]
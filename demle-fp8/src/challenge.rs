@@ -0,0 +1,256 @@
+use crate::fp8::FP8;
+use crate::gemm::{gemm_fp8, generate_gemm_inputs};
+use demle_core::challenge::{
+    challengeable_tiles, Challenge, ChallengeResponse, InputCoordinates, TileOpening,
+};
+use demle_core::merkle::TILE_SIZE;
+use demle_core::proof::Proof;
+use demle_core::{DemleError, MLOperation, Result, WorkResult};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Input coordinates of one output tile of a seeded GEMM
+fn gemm_tile_inputs(dimensions: (usize, usize, usize), seed: u64, tile: usize) -> InputCoordinates {
+    let (m, _, n) = dimensions;
+    let start = (tile * TILE_SIZE).min(m * n);
+    let end = (start + TILE_SIZE).min(m * n);
+    let rows = if start == end {
+        (start / n.max(1), start / n.max(1))
+    } else {
+        (start / n, (end - 1) / n + 1)
+    };
+    InputCoordinates::Gemm {
+        dimensions,
+        seed,
+        rows,
+        elements: (start, end),
+    }
+}
+
+fn fp8_bytes(data: &[FP8]) -> Vec<u8> {
+    data.iter().map(|x| x.to_bits()).collect()
+}
+
+/// Full output bytes of a challengeable operation
+fn operation_output(operation: &MLOperation) -> Result<Vec<u8>> {
+    match operation {
+        MLOperation::MatrixMultiply { dimensions, seed } => {
+            let (m, k, n) = *dimensions;
+            let (a, b) = generate_gemm_inputs(*dimensions, *seed)?;
+            Ok(fp8_bytes(&gemm_fp8(&a, &b, m, k, n)))
+        }
+        other => Err(DemleError::ValidationError(format!(
+            "{} can't be spot-checked",
+            other
+        ))),
+    }
+}
+
+/// Miner side: open every challenged tile with its bytes, its Merkle path and
+/// the coordinates of the inputs it was computed from
+pub fn respond_to_challenge(
+    operations: &[MLOperation],
+    challenge: &Challenge,
+) -> Result<ChallengeResponse> {
+    let mut outputs: HashMap<usize, Vec<u8>> = HashMap::new();
+    let mut openings = Vec::with_capacity(challenge.tiles.len());
+
+    for &index in &challenge.tiles {
+        let operation = operations.get(index.operation).ok_or_else(|| {
+            DemleError::ValidationError(format!("No operation {}", index.operation))
+        })?;
+        let MLOperation::MatrixMultiply { dimensions, seed } = operation else {
+            return Err(DemleError::ValidationError(format!(
+                "{} can't be spot-checked",
+                operation
+            )));
+        };
+
+        let output = match outputs.entry(index.operation) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(operation_output(operation)?),
+        };
+        let tree = Proof::output_tree(output);
+        openings.push(TileOpening {
+            index,
+            data: output[tree.tile_range(index.tile, output.len())].to_vec(),
+            proof: tree.prove(index.tile)?,
+            inputs: gemm_tile_inputs(*dimensions, *seed, index.tile),
+        });
+    }
+
+    Ok(ChallengeResponse {
+        work_id: challenge.work_id.clone(),
+        openings,
+    })
+}
+
+/// Challenger side: check that every opening answers its challenged tile, is
+/// included under the operation's submitted result hash, and matches the tile
+/// recomputed from regenerated inputs. Only the rows of the output that the
+/// tile covers are recomputed.
+pub fn verify_challenge(
+    operations: &[MLOperation],
+    result: &WorkResult,
+    challenge: &Challenge,
+    response: &ChallengeResponse,
+) -> Result<bool> {
+    if response.work_id != challenge.work_id
+        || challenge.work_id != result.work_id
+        || response.openings.len() != challenge.tiles.len()
+    {
+        return Ok(false);
+    }
+
+    let mut inputs: HashMap<usize, (Vec<FP8>, Vec<FP8>)> = HashMap::new();
+    for (opening, &index) in response.openings.iter().zip(&challenge.tiles) {
+        let (Some(operation), Some(operation_result)) = (
+            operations.get(index.operation),
+            result.operation_results.get(index.operation),
+        ) else {
+            return Ok(false);
+        };
+        let MLOperation::MatrixMultiply { dimensions, seed } = operation else {
+            return Ok(false);
+        };
+
+        let expected_inputs = gemm_tile_inputs(*dimensions, *seed, index.tile);
+        if opening.index != index
+            || opening.inputs != expected_inputs
+            || opening.proof.tile_index != index.tile
+            || opening.proof.num_tiles != challengeable_tiles(operation)?
+            || !opening
+                .proof
+                .verify(&operation_result.result_hash, &opening.data)
+        {
+            return Ok(false);
+        }
+
+        let InputCoordinates::Gemm {
+            rows: (first_row, last_row),
+            elements: (start, end),
+            ..
+        } = expected_inputs;
+        if opening.data.len() != end - start {
+            return Ok(false);
+        }
+
+        let (a, b) = match inputs.entry(index.operation) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(generate_gemm_inputs(*dimensions, *seed)?),
+        };
+        let (_, k, n) = *dimensions;
+        let recomputed = gemm_fp8(
+            &a[first_row * k..last_row * k],
+            b,
+            last_row - first_row,
+            k,
+            n,
+        );
+        let offset = start - first_row * n;
+        if fp8_bytes(&recomputed[offset..offset + (end - start)]) != opening.data {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_work_unit;
+    use demle_core::challenge::TileIndex;
+
+    /// Hash of a block mined after the submission
+    const LATER_BLOCK: &str = "0x5151515151515151515151515151515151515151515151515151515151515151";
+
+    /// Simulated miner: execute the work unit and submit its result
+    fn mine(operations: &[MLOperation]) -> WorkResult {
+        let operation_results = execute_work_unit(operations).unwrap();
+        WorkResult {
+            work_id: "work-7".to_string(),
+            nonce: 3,
+            hash: "cd".repeat(32),
+            execution_time_ms: 0,
+            total_flops: operation_results.iter().map(|r| r.flops).sum(),
+            operation_results,
+            node_hashes: Vec::new(),
+            beneficiary: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_challenge_round_trip() {
+        let operations = vec![
+            MLOperation::MatrixMultiply {
+                dimensions: (48, 16, 40),
+                seed: 11,
+            },
+            MLOperation::Softmax {
+                rows: 4,
+                cols: 8,
+                log: false,
+                seed: 2,
+            },
+            MLOperation::MatrixMultiply {
+                dimensions: (3, 5, 7),
+                seed: 12,
+            },
+        ];
+        let result = mine(&operations);
+
+        // The challenger samples tiles once a later block is known
+        let challenge = Challenge::derive(LATER_BLOCK, &operations, &result, 6).unwrap();
+        let response = respond_to_challenge(&operations, &challenge).unwrap();
+        assert!(verify_challenge(&operations, &result, &challenge, &response).unwrap());
+
+        // A tampered tile breaks its Merkle path
+        let mut tampered = response.clone();
+        tampered.openings[0].data[0] ^= 1;
+        assert!(!verify_challenge(&operations, &result, &challenge, &tampered).unwrap());
+
+        // Opening another tile than the one challenged fails
+        let tile = |tile| TileIndex { operation: 0, tile };
+        let first = Challenge {
+            tiles: vec![tile(0)],
+            ..challenge.clone()
+        };
+        let second = Challenge {
+            tiles: vec![tile(1)],
+            ..challenge
+        };
+        let response = respond_to_challenge(&operations, &second).unwrap();
+        assert!(verify_challenge(&operations, &result, &second, &response).unwrap());
+        assert!(!verify_challenge(&operations, &result, &first, &response).unwrap());
+    }
+
+    #[test]
+    fn test_challenge_catches_wrong_output() {
+        // A miner that commits to a consistent tree over a wrong output passes
+        // the Merkle check but not the recomputation
+        let operations = vec![MLOperation::MatrixMultiply {
+            dimensions: (64, 8, 32),
+            seed: 5,
+        }];
+        let mut result = mine(&operations);
+        let mut wrong = operation_output(&operations[0]).unwrap();
+        wrong.iter_mut().for_each(|byte| *byte = 0);
+        result.operation_results[0].result_hash = Proof::hash_operation_result(&wrong);
+
+        let challenge = Challenge::derive(LATER_BLOCK, &operations, &result, 1).unwrap();
+        let tree = Proof::output_tree(&wrong);
+        let tile = challenge.tiles[0].tile;
+        let response = ChallengeResponse {
+            work_id: result.work_id.clone(),
+            openings: vec![TileOpening {
+                index: challenge.tiles[0],
+                data: wrong[tree.tile_range(tile, wrong.len())].to_vec(),
+                proof: tree.prove(tile).unwrap(),
+                inputs: gemm_tile_inputs((64, 8, 32), 5, tile),
+            }],
+        };
+        assert!(!verify_challenge(&operations, &result, &challenge, &response).unwrap());
+    }
+}
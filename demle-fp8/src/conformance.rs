@@ -0,0 +1,716 @@
+use crate::fp8::{FP8, FP8E5M2};
+use crate::store::{StoredTensor, TensorStore};
+use crate::{execute_ml_operation_with_store, operation_tensors_with_store, OperationTensors};
+use demle_core::merkle::{MerkleTree, TILE_SIZE};
+use demle_core::seed::derive_seed;
+use demle_core::{
+    Activation, BatchOperand, DemleError, FeedForwardType, MLOperation, MasterWeights,
+    NumericFormat, OperationResult, Optimizer, PoolType, Result, TensorInput, TensorOp,
+};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Version of the conformance vector format
+pub const VECTOR_FORMAT_VERSION: u32 = 2;
+
+/// Vectors generated by the reference kernels and checked in
+const REFERENCE_VECTORS: &str = include_str!("../vectors/conformance.json");
+
+/// Chain state the vector seeds are derived from
+const VECTOR_PREVIOUS_HASH: &str =
+    "1111111111111111111111111111111111111111111111111111111111111111";
+const VECTOR_MINER_ADDRESS: &str = "0x2222222222222222222222222222222222222222";
+
+/// Kernels a miner runs, which must reproduce the reference results bit for bit
+pub trait ComputeBackend {
+    fn name(&self) -> &str;
+
+    /// f32 value of FP8 bits in `format`
+    fn decode_fp8(&self, format: NumericFormat, bits: u8) -> Result<f32>;
+
+    /// FP8 bits in `format` that `value` converts to
+    fn encode_fp8(&self, format: NumericFormat, value: f32) -> Result<u8>;
+
+    /// Operands of an operation and the output bytes its result hash commits
+    /// to. Tensor operations read their inputs from `store`.
+    fn operation_tensors(
+        &self,
+        operation: &MLOperation,
+        store: &TensorStore,
+    ) -> Result<OperationTensors>;
+
+    fn execute(&self, operation: &MLOperation, store: &TensorStore) -> Result<OperationResult>;
+}
+
+/// This build's kernels
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeBackend;
+
+fn unsupported_format(format: NumericFormat) -> DemleError {
+    DemleError::ValidationError(format!("{:?} is not an FP8 format", format))
+}
+
+impl ComputeBackend for NativeBackend {
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn decode_fp8(&self, format: NumericFormat, bits: u8) -> Result<f32> {
+        match format {
+            NumericFormat::FP8E4M3 => Ok(FP8::from_bits(bits).to_f32()),
+            NumericFormat::FP8E5M2 => Ok(FP8E5M2::from_bits(bits).to_f32()),
+            other => Err(unsupported_format(other)),
+        }
+    }
+
+    fn encode_fp8(&self, format: NumericFormat, value: f32) -> Result<u8> {
+        match format {
+            NumericFormat::FP8E4M3 => Ok(FP8::from_f32(value).to_bits()),
+            NumericFormat::FP8E5M2 => Ok(FP8E5M2::from_f32(value).to_bits()),
+            other => Err(unsupported_format(other)),
+        }
+    }
+
+    fn operation_tensors(
+        &self,
+        operation: &MLOperation,
+        store: &TensorStore,
+    ) -> Result<OperationTensors> {
+        operation_tensors_with_store(operation, Some(store))
+    }
+
+    fn execute(&self, operation: &MLOperation, store: &TensorStore) -> Result<OperationResult> {
+        execute_ml_operation_with_store(operation, Some(store))
+    }
+}
+
+/// Conversions of one FP8 format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fp8Table {
+    pub format: NumericFormat,
+    /// f32 bits of each of the 256 encodings
+    pub decode: Vec<u32>,
+    /// (f32 bits, FP8 bits) pairs: every decoded value, the midpoints between
+    /// neighbouring values and out-of-range magnitudes
+    pub encode: Vec<(u32, u8)>,
+}
+
+/// FP8 E4M3 tensor staged in the store before the operations run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorTensor {
+    pub shape: Vec<usize>,
+    /// Hex of the FP8 bits in row-major order
+    pub data: String,
+}
+
+impl VectorTensor {
+    fn to_stored(&self) -> Result<StoredTensor> {
+        let bits = hex::decode(&self.data).map_err(|e| {
+            DemleError::SerializationError(format!("Invalid vector tensor data: {}", e))
+        })?;
+        StoredTensor::new(
+            self.shape.clone(),
+            bits.into_iter().map(FP8::from_bits).collect(),
+        )
+    }
+}
+
+impl From<&StoredTensor> for VectorTensor {
+    fn from(tensor: &StoredTensor) -> Self {
+        Self {
+            shape: tensor.shape.clone(),
+            data: hex::encode(tensor.data.iter().map(|x| x.to_bits()).collect::<Vec<_>>()),
+        }
+    }
+}
+
+/// Expected results of one operation shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationVector {
+    /// Operation with its seed derived for its index, if it takes one
+    pub operation: MLOperation,
+    pub seed: u64,
+    /// SHA3-256 of the seeded or stored operands in order, as laid out by
+    /// [`OperationTensors::inputs`]
+    pub inputs_digest: String,
+    /// Leaf hash of every `TILE_SIZE`-byte tile of the output the result hash
+    /// is the Merkle root of
+    pub tile_hashes: Vec<String>,
+    pub result_hash: String,
+    pub flops: u64,
+}
+
+/// Checked-in vectors that independent implementations test against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceVectors {
+    pub version: u32,
+    /// Chain state the operation seeds are derived from, with sequence 0
+    pub previous_hash: String,
+    pub miner_address: String,
+    pub fp8: Vec<Fp8Table>,
+    /// Tensors that tensor operations read from the store by content hash
+    pub tensors: Vec<VectorTensor>,
+    pub operations: Vec<OperationVector>,
+}
+
+/// Tensors the reference tensor operation reads: a (2, 4, 8) activation and an
+/// (8, 6) weight
+pub fn reference_tensors() -> Vec<StoredTensor> {
+    [vec![2, 4, 8], vec![8, 6]]
+        .into_iter()
+        .enumerate()
+        .map(|(t, shape)| {
+            let data = (0..shape.iter().product::<usize>())
+                .map(|i| FP8::from_f32(((7 * i + 3 * t) % 17) as f32 / 4.0 - 2.0))
+                .collect();
+            StoredTensor { shape, data }
+        })
+        .collect()
+}
+
+/// Small instance of every operation, tensor operations reading
+/// [`reference_tensors`]
+pub fn reference_operations() -> Vec<MLOperation> {
+    vec![
+        MLOperation::MatrixMultiply {
+            dimensions: (40, 24, 36),
+            seed: 0,
+        },
+        MLOperation::Convolution2D {
+            input_shape: (1, 2, 8, 8),
+            kernel_shape: (4, 2, 3, 3),
+            stride: (2, 1),
+            padding: (1, 0),
+            seed: 0,
+        },
+        MLOperation::MultiHeadAttention {
+            batch_size: 1,
+            seq_length: 4,
+            d_model: 8,
+            num_heads: 2,
+            seed: 0,
+        },
+        MLOperation::BatchNormalization {
+            shape: (2, 3, 4, 4),
+            epsilon: 1e-5,
+            seed: 0,
+        },
+        MLOperation::TransformerBlock {
+            batch_size: 1,
+            seq_length: 4,
+            d_model: 8,
+            num_heads: 2,
+            d_ff: 16,
+            feed_forward: FeedForwardType::SwiGLU,
+            seed: 0,
+        },
+        MLOperation::GatedFeedForward {
+            tokens: 4,
+            d_model: 8,
+            d_ff: 16,
+            activation: Activation::GELU,
+            seed: 0,
+        },
+        MLOperation::Pooling2D {
+            input_shape: (1, 2, 7, 7),
+            pool_type: PoolType::Average,
+            kernel_size: (3, 3),
+            stride: (2, 2),
+            padding: (1, 1),
+            seed: 0,
+        },
+        MLOperation::EmbeddingLookup {
+            vocab_size: 50,
+            embedding_dim: 8,
+            num_tokens: 10,
+            seed: 0,
+        },
+        MLOperation::Softmax {
+            rows: 4,
+            cols: 10,
+            log: true,
+            seed: 0,
+        },
+        MLOperation::MatrixMultiplyBackward {
+            dimensions: (8, 4, 6),
+            seed: 0,
+        },
+        MLOperation::Convolution2DBackward {
+            input_shape: (1, 2, 6, 6),
+            kernel_shape: (3, 2, 3, 3),
+            stride: (1, 2),
+            padding: (1, 1),
+            seed: 0,
+        },
+        MLOperation::MultiHeadAttentionBackward {
+            batch_size: 1,
+            seq_length: 4,
+            d_model: 8,
+            num_heads: 2,
+            seed: 0,
+        },
+        MLOperation::OptimizerStep {
+            num_params: 64,
+            optimizer: Optimizer::AdamW {
+                learning_rate: 1e-3,
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-8,
+                weight_decay: 0.01,
+                step: 10,
+            },
+            master_weights: MasterWeights::BF16,
+            seed: 0,
+        },
+        MLOperation::TrainingStep {
+            batch_size: 8,
+            input_dim: 6,
+            hidden_dim: 12,
+            num_classes: 3,
+            optimizer: Optimizer::SgdMomentum {
+                learning_rate: 0.1,
+                momentum: 0.9,
+                weight_decay: 1e-4,
+            },
+            master_weights: MasterWeights::F32,
+            seed: 0,
+        },
+        MLOperation::BatchedMatrixMultiply {
+            batch_size: 4,
+            dimensions: (6, 5, 3),
+            a_operand: BatchOperand::Shared,
+            b_operand: BatchOperand::PerBatch,
+            transpose_a: true,
+            transpose_b: false,
+            seed: 0,
+        },
+        MLOperation::SparseMatrixMultiply {
+            dimensions: (8, 12, 4),
+            seed: 0,
+        },
+        MLOperation::MixtureOfExperts {
+            tokens: 10,
+            d_model: 8,
+            d_ff: 12,
+            num_experts: 3,
+            top_k: 2,
+            capacity_factor: 1.5,
+            seed: 0,
+        },
+        MLOperation::Quantize {
+            num_elements: 100,
+            source: NumericFormat::FP8E5M2,
+            target: NumericFormat::BF16,
+            seed: 0,
+        },
+        MLOperation::TensorOperation {
+            op: TensorOp::MatMul,
+            inputs: reference_tensors()
+                .iter()
+                .map(|tensor| TensorInput::Stored(tensor.tensor_ref()))
+                .collect(),
+            output: None,
+        },
+    ]
+}
+
+/// Run `f` against a store in a fresh temporary directory holding `tensors`,
+/// removing the directory afterwards
+fn with_staged_store<T>(
+    tensors: &[StoredTensor],
+    f: impl FnOnce(&TensorStore) -> Result<T>,
+) -> Result<T> {
+    static STAGED: AtomicUsize = AtomicUsize::new(0);
+    let root = std::env::temp_dir().join(format!(
+        "demle-conformance-{}-{}",
+        std::process::id(),
+        STAGED.fetch_add(1, Ordering::Relaxed)
+    ));
+    let store = TensorStore::open(&root)?;
+    let result = tensors
+        .iter()
+        .try_for_each(|tensor| store.put(tensor).map(|_| ()))
+        .and_then(|()| f(&store));
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+/// f32 values probing the conversion to `format`: every decoded value, the
+/// midpoints between neighbours and magnitudes outside the format's range
+fn encode_probes(decoded: &[f32]) -> Vec<f32> {
+    let mut finite: Vec<f32> = decoded.iter().copied().filter(|x| x.is_finite()).collect();
+    finite.sort_by(f32::total_cmp);
+    finite.dedup_by(|a, b| a.to_bits() == b.to_bits());
+
+    let midpoints = finite.windows(2).map(|pair| (pair[0] + pair[1]) / 2.0);
+    let out_of_range = [
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+        1e6,
+        -1e6,
+        1e-12,
+        -1e-12,
+        f32::MIN_POSITIVE,
+    ];
+
+    let mut probes: Vec<f32> = decoded.iter().copied().chain(midpoints).collect();
+    probes.extend(out_of_range);
+    let mut seen = std::collections::HashSet::new();
+    probes.retain(|x| seen.insert(x.to_bits()));
+    probes
+}
+
+fn fp8_table(backend: &dyn ComputeBackend, format: NumericFormat) -> Result<Fp8Table> {
+    let decoded = (0..=255u8)
+        .map(|bits| backend.decode_fp8(format, bits))
+        .collect::<Result<Vec<_>>>()?;
+    let encode = encode_probes(&decoded)
+        .into_iter()
+        .map(|value| Ok((value.to_bits(), backend.encode_fp8(format, value)?)))
+        .collect::<Result<_>>()?;
+    Ok(Fp8Table {
+        format,
+        decode: decoded.iter().map(|x| x.to_bits()).collect(),
+        encode,
+    })
+}
+
+/// Inputs digest and output tile hashes of an operation
+fn tile_vector(
+    backend: &dyn ComputeBackend,
+    operation: &MLOperation,
+    store: &TensorStore,
+) -> Result<(String, Vec<String>)> {
+    let tensors = backend.operation_tensors(operation, store)?;
+    let tiles = MerkleTree::build(&tensors.output, TILE_SIZE)?.tile_hashes();
+    Ok((hex::encode(Sha3_256::digest(&tensors.inputs)), tiles))
+}
+
+/// Generate vectors for the reference operations from a backend's results
+pub fn generate_vectors(backend: &dyn ComputeBackend) -> Result<ConformanceVectors> {
+    let fp8 = [NumericFormat::FP8E4M3, NumericFormat::FP8E5M2]
+        .into_iter()
+        .map(|format| fp8_table(backend, format))
+        .collect::<Result<_>>()?;
+
+    let tensors = reference_tensors();
+    let operations = with_staged_store(&tensors, |store| {
+        let mut operations = Vec::new();
+        for (index, mut operation) in reference_operations().into_iter().enumerate() {
+            let seed = derive_seed(VECTOR_PREVIOUS_HASH, VECTOR_MINER_ADDRESS, 0, index)?;
+            if let Some(operation_seed) = operation.seed_mut() {
+                *operation_seed = seed;
+            }
+            let (inputs_digest, tile_hashes) = tile_vector(backend, &operation, store)?;
+            let result = backend.execute(&operation, store)?;
+            operations.push(OperationVector {
+                operation,
+                seed,
+                inputs_digest,
+                tile_hashes,
+                result_hash: result.result_hash,
+                flops: result.flops,
+            });
+        }
+        Ok(operations)
+    })?;
+
+    Ok(ConformanceVectors {
+        version: VECTOR_FORMAT_VERSION,
+        previous_hash: VECTOR_PREVIOUS_HASH.to_string(),
+        miner_address: VECTOR_MINER_ADDRESS.to_string(),
+        fp8,
+        tensors: tensors.iter().map(VectorTensor::from).collect(),
+        operations,
+    })
+}
+
+/// The checked-in vectors
+pub fn reference_vectors() -> Result<ConformanceVectors> {
+    serde_json::from_str(REFERENCE_VECTORS)
+        .map_err(|e| DemleError::SerializationError(format!("Invalid reference vectors: {}", e)))
+}
+
+/// A result that differs from its vector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceFailure {
+    /// Which check failed, such as `FP8E4M3 decode 0x7f` or `operation 3 result hash`
+    pub check: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Outcome of checking a backend against conformance vectors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub backend: String,
+    pub checks: usize,
+    pub failures: Vec<ConformanceFailure>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Record a backend error as a failed check
+    fn error(&mut self, check: String, error: DemleError) {
+        self.checks += 1;
+        self.failures.push(ConformanceFailure {
+            check,
+            expected: "a result".to_string(),
+            actual: error.to_string(),
+        });
+    }
+
+    fn check<T: PartialEq + std::fmt::Debug>(
+        &mut self,
+        check: impl FnOnce() -> String,
+        expected: T,
+        actual: T,
+    ) {
+        self.checks += 1;
+        if expected != actual {
+            self.failures.push(ConformanceFailure {
+                check: check(),
+                expected: format!("{:?}", expected),
+                actual: format!("{:?}", actual),
+            });
+        }
+    }
+}
+
+/// Check every FP8 conversion, seed, inputs digest, tile hash, result hash
+/// and FLOP count of the vectors against a backend, with the vector tensors
+/// staged in a temporary store. Backend errors are recorded as failures.
+pub fn run_conformance(
+    backend: &dyn ComputeBackend,
+    vectors: &ConformanceVectors,
+) -> Result<ConformanceReport> {
+    if vectors.version != VECTOR_FORMAT_VERSION {
+        return Err(DemleError::ValidationError(format!(
+            "Unsupported vector format version {}",
+            vectors.version
+        )));
+    }
+
+    let mut report = ConformanceReport {
+        backend: backend.name().to_string(),
+        checks: 0,
+        failures: Vec::new(),
+    };
+
+    for table in &vectors.fp8 {
+        let format = table.format;
+        for (bits, &expected) in table.decode.iter().enumerate() {
+            let check = || format!("{:?} decode {:#04x}", format, bits);
+            match backend.decode_fp8(format, bits as u8) {
+                Ok(actual) => report.check(check, expected, actual.to_bits()),
+                Err(e) => report.error(check(), e),
+            }
+        }
+        for &(value, expected) in &table.encode {
+            let check = || format!("{:?} encode {:e}", format, f32::from_bits(value));
+            match backend.encode_fp8(format, f32::from_bits(value)) {
+                Ok(actual) => report.check(check, expected, actual),
+                Err(e) => report.error(check(), e),
+            }
+        }
+    }
+
+    let tensors = vectors
+        .tensors
+        .iter()
+        .map(VectorTensor::to_stored)
+        .collect::<Result<Vec<_>>>()?;
+    with_staged_store(&tensors, |store| {
+        for (index, vector) in vectors.operations.iter().enumerate() {
+            check_operation(backend, store, vectors, index, vector, &mut report)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(report)
+}
+
+/// Check the seed, inputs digest, tiles, result hash and FLOP count of one
+/// operation vector
+fn check_operation(
+    backend: &dyn ComputeBackend,
+    store: &TensorStore,
+    vectors: &ConformanceVectors,
+    index: usize,
+    vector: &OperationVector,
+    report: &mut ConformanceReport,
+) -> Result<()> {
+    let name = || format!("operation {} ({})", index, vector.operation);
+    let seed = derive_seed(&vectors.previous_hash, &vectors.miner_address, 0, index)?;
+    report.check(|| format!("{} seed", name()), vector.seed, seed);
+    if let Some(operation_seed) = vector.operation.seed() {
+        report.check(
+            || format!("{} operation seed", name()),
+            vector.seed,
+            operation_seed,
+        );
+    }
+
+    match tile_vector(backend, &vector.operation, store) {
+        Ok((inputs_digest, tile_hashes)) => {
+            report.check(
+                || format!("{} inputs digest", name()),
+                &vector.inputs_digest,
+                &inputs_digest,
+            );
+            report.check(
+                || format!("{} tile hashes", name()),
+                &vector.tile_hashes,
+                &tile_hashes,
+            );
+        }
+        Err(e) => report.error(format!("{} tensors", name()), e),
+    }
+
+    match backend.execute(&vector.operation, store) {
+        Ok(result) => {
+            report.check(
+                || format!("{} result hash", name()),
+                &vector.result_hash,
+                &result.result_hash,
+            );
+            report.check(|| format!("{} flops", name()), vector.flops, result.flops);
+        }
+        Err(e) => report.error(format!("{} execution", name()), e),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_backend_conforms() {
+        let vectors = reference_vectors().unwrap();
+        let report = run_conformance(&NativeBackend, &vectors).unwrap();
+        assert!(report.passed(), "{:#?}", report.failures);
+        assert!(report.checks > 1000);
+
+        // The checked-in file is exactly what the generator writes
+        let generated = serde_json::to_string_pretty(&generate_vectors(&NativeBackend).unwrap())
+            .unwrap()
+            + "\n";
+        assert_eq!(generated, REFERENCE_VECTORS);
+    }
+
+    #[test]
+    fn test_vectors_cover_operations() {
+        let vectors = reference_vectors().unwrap();
+        assert_eq!(vectors.operations.len(), reference_operations().len());
+        let gemm = &vectors.operations[0];
+        assert_eq!(gemm.tile_hashes.len(), (40 * 36usize).div_ceil(TILE_SIZE));
+        assert!(vectors.operations.iter().all(|v| !v.tile_hashes.is_empty()));
+        assert!(vectors
+            .operations
+            .iter()
+            .any(|v| matches!(v.operation, MLOperation::TensorOperation { .. })));
+        assert_eq!(vectors.tensors.len(), reference_tensors().len());
+        for table in &vectors.fp8 {
+            assert_eq!(table.decode.len(), 256);
+            assert!(table.encode.len() > 256);
+        }
+    }
+
+    #[test]
+    fn test_tensors_match_results() {
+        // The recorded output is what the result hash commits to
+        with_staged_store(&reference_tensors(), |store| {
+            for operation in reference_operations() {
+                let tensors = NativeBackend.operation_tensors(&operation, store)?;
+                assert!(!tensors.inputs.is_empty(), "{}", operation);
+                assert_eq!(
+                    tensors.result_hash(),
+                    NativeBackend.execute(&operation, store)?.result_hash,
+                    "{}",
+                    operation
+                );
+            }
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    /// Backend with an E4M3 conversion that rounds differently, no E5M2
+    /// decoding of NaN, a GEMM output off by one bit and no convolution support
+    struct SkewedBackend;
+
+    impl ComputeBackend for SkewedBackend {
+        fn name(&self) -> &str {
+            "skewed"
+        }
+
+        fn decode_fp8(&self, format: NumericFormat, bits: u8) -> Result<f32> {
+            let value = NativeBackend.decode_fp8(format, bits)?;
+            if format == NumericFormat::FP8E5M2 && bits == 0xff {
+                return Err(DemleError::ComputationError("NaN".to_string()));
+            }
+            Ok(value)
+        }
+
+        fn encode_fp8(&self, format: NumericFormat, value: f32) -> Result<u8> {
+            let bits = NativeBackend.encode_fp8(format, value)?;
+            Ok(if format == NumericFormat::FP8E4M3 && value == 1.5 {
+                bits + 1
+            } else {
+                bits
+            })
+        }
+
+        fn operation_tensors(
+            &self,
+            operation: &MLOperation,
+            store: &TensorStore,
+        ) -> Result<OperationTensors> {
+            let mut tensors = NativeBackend.operation_tensors(operation, store)?;
+            if let MLOperation::MatrixMultiply { .. } = operation {
+                tensors.output[0] ^= 1;
+            }
+            Ok(tensors)
+        }
+
+        fn execute(&self, operation: &MLOperation, store: &TensorStore) -> Result<OperationResult> {
+            match operation {
+                MLOperation::Convolution2D { .. } => Err(DemleError::ComputationError(
+                    "Convolution is not supported".to_string(),
+                )),
+                _ => NativeBackend.execute(operation, store),
+            }
+        }
+    }
+
+    #[test]
+    fn test_divergent_backend_fails() {
+        let mut vectors = reference_vectors().unwrap();
+        vectors.operations.truncate(2);
+        let report = run_conformance(&SkewedBackend, &vectors).unwrap();
+        let failed: Vec<&str> = report.failures.iter().map(|f| f.check.as_str()).collect();
+        assert_eq!(
+            failed,
+            [
+                "FP8E4M3 encode 1.5e0",
+                "FP8E5M2 decode 0xff",
+                "operation 0 (GEMM 40x24x36) tile hashes",
+                "operation 1 (Conv2D 4x2x3x3) execution"
+            ]
+        );
+        assert_eq!(report.failures[1].actual, "Computation error: NaN");
+        assert_eq!(
+            report.failures[3].actual,
+            "Computation error: Convolution is not supported"
+        );
+
+        vectors.version += 1;
+        assert!(run_conformance(&NativeBackend, &vectors).is_err());
+    }
+}
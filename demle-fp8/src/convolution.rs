@@ -1,9 +1,15 @@
 use crate::fp8::FP8;
-use crate::operations::generate_random_tensor;
-use demle_core::{proof::Proof, DemleError, Result};
+use crate::operations::{flops_product, generate_random_tensor, OperationTensors};
+use demle_core::{DemleError, Result};
 
 #[cfg(feature = "cuda")]
-use candle_core::{Device, Tensor};
+use demle_core::proof::Proof;
+#[cfg(feature = "cuda")]
+use crate::quantize::{dequantize, from_candle, quantize, to_candle};
+#[cfg(feature = "cuda")]
+use candle_core::Device;
+#[cfg(feature = "cuda")]
+use demle_core::NumericFormat;
 #[cfg(feature = "cuda")]
 use rand::SeedableRng;
 #[cfg(feature = "cuda")]
@@ -18,6 +24,8 @@ pub fn execute_conv2d(
     padding: (usize, usize),
     seed: u64,
 ) -> Result<(String, u64)> {
+    validate_conv2d_window(input_shape, kernel_shape, stride, padding)?;
+
     #[cfg(feature = "cuda")]
     {
         // GPU-accelerated version
@@ -62,18 +70,17 @@ fn execute_conv2d_gpu(
         .map(|_| normal.sample(&mut rng) as f32)
         .collect();
 
-    // Create tensors on GPU with BF16 for H100 tensor core acceleration
-    let input_tensor = Tensor::from_vec(input_data, (batch, in_ch, ih, iw), &device).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to create input tensor: {}", e))
-    })?.to_dtype(candle_core::DType::BF16).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to convert input to BF16: {}", e))
-    })?;
-
-    let kernel_tensor = Tensor::from_vec(kernel_data, (out_ch, in_ch, kh, kw), &device).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to create kernel tensor: {}", e))
-    })?.to_dtype(candle_core::DType::BF16).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to convert kernel to BF16: {}", e))
-    })?;
+    // Upload as BF16 for H100 tensor core acceleration
+    let input_tensor = to_candle(
+        &quantize(&input_data, NumericFormat::BF16),
+        &[batch, in_ch, ih, iw],
+        &device,
+    )?;
+    let kernel_tensor = to_candle(
+        &quantize(&kernel_data, NumericFormat::BF16),
+        &[out_ch, in_ch, kh, kw],
+        &device,
+    )?;
 
     // Perform memory-optimized convolution batches for H100
     let mut total_flops = 0u64;
@@ -98,14 +105,7 @@ fn execute_conv2d_gpu(
     }
 
     // Get result back to CPU for hashing (single transfer to minimize overhead)
-    let output_data: Vec<f32> = final_output.unwrap()
-        .to_dtype(candle_core::DType::F32).map_err(|e| {
-            DemleError::ComputationError(format!("Failed to convert output to F32: {}", e))
-        })?.flatten_all().map_err(|e| {
-            DemleError::ComputationError(format!("Failed to flatten output: {}", e))
-        })?.to_vec1().map_err(|e| {
-            DemleError::ComputationError(format!("Failed to get result from GPU: {}", e))
-        })?;
+    let output_data = dequantize(&from_candle(&final_output.unwrap(), NumericFormat::F32)?);
 
     // Hash the result (convert to FP8 for consistency)
     let fp8_data: Vec<FP8> = output_data.iter().map(|&f| FP8::from_f32(f)).collect();
@@ -122,6 +122,55 @@ fn execute_conv2d_cpu(
     padding: (usize, usize),
     seed: u64,
 ) -> Result<(String, u64)> {
+    let tensors = conv2d_tensors(input_shape, kernel_shape, stride, padding, seed)?;
+
+    // Calculate FLOPS
+    let flops = conv2d_flops(input_shape, kernel_shape, stride, padding)?;
+
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded input and kernel and output of a 2D convolution
+pub fn conv2d_tensors(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<OperationTensors> {
+    validate_conv2d_window(input_shape, kernel_shape, stride, padding)?;
+    let (batch, in_ch, ih, iw) = input_shape;
+    let (out_ch, _, kh, kw) = kernel_shape;
+
+    // Generate random input and kernel tensors
+    let input_data = generate_random_tensor(&[batch, in_ch, ih, iw], seed)?;
+    let kernel_data = generate_random_tensor(&[out_ch, in_ch, kh, kw], seed.wrapping_add(1))?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&input_data);
+    tensors.add_fp8(&kernel_data);
+
+    let output = conv2d_fp8(
+        &input_data,
+        &kernel_data,
+        input_shape,
+        kernel_shape,
+        stride,
+        padding,
+    );
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// Direct FP8 2D convolution of an NCHW input with an (out_channels,
+/// in_channels, kh, kw) kernel
+pub fn conv2d_fp8(
+    input_data: &[FP8],
+    kernel_data: &[FP8],
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Vec<FP8> {
     let (batch, in_ch, ih, iw) = input_shape;
     let (out_ch, _, kh, kw) = kernel_shape;
     let (sh, sw) = stride;
@@ -131,10 +180,6 @@ fn execute_conv2d_cpu(
     let oh = (ih + 2 * ph - kh) / sh + 1;
     let ow = (iw + 2 * pw - kw) / sw + 1;
 
-    // Generate random input and kernel tensors
-    let input_data = generate_random_tensor(&[batch, in_ch, ih, iw], seed)?;
-    let kernel_data = generate_random_tensor(&[out_ch, in_ch, kh, kw], seed.wrapping_add(1))?;
-
     // Initialize output
     let mut output = vec![FP8::zero(); batch * out_ch * oh * ow];
 
@@ -180,22 +225,70 @@ fn execute_conv2d_cpu(
         }
     }
 
-    // Calculate FLOPS
-    let flops = 2
-        * (batch as u64)
-        * (out_ch as u64)
-        * (in_ch as u64)
-        * (kh as u64)
-        * (kw as u64)
-        * (oh as u64)
-        * (ow as u64);
-
-    // Hash the result
-    let result_bytes: Vec<u8> = output.iter().flat_map(|fp8| vec![fp8.to_bits()]).collect();
+    output
+}
 
-    let result_hash = Proof::hash_operation_result(&result_bytes);
+/// Output length of a sliding window along one axis, or None if the stride is
+/// zero, the padded input overflows or the window does not fit in it
+pub fn window_output_size(
+    size: usize,
+    window: usize,
+    stride: usize,
+    padding: usize,
+) -> Option<usize> {
+    let padded = padding.checked_mul(2)?.checked_add(size)?;
+    Some(padded.checked_sub(window)?.checked_div(stride)? + 1)
+}
+
+/// Output spatial dimensions of a convolution, rejecting windows that do not
+/// fit in the padded input
+pub fn conv2d_output_size(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<(usize, usize)> {
+    let (_, _, ih, iw) = input_shape;
+    let (_, _, kh, kw) = kernel_shape;
+    window_output_size(ih, kh, stride.0, padding.0)
+        .zip(window_output_size(iw, kw, stride.1, padding.1))
+        .ok_or_else(|| {
+            DemleError::ValidationError(format!(
+                "Convolution kernel {}x{} exceeds input {}x{} padded by {}x{}",
+                kh, kw, ih, iw, padding.0, padding.1
+            ))
+        })
+}
+
+/// Check that a convolution window and stride are non-zero and that the
+/// kernel fits in the padded input
+pub fn validate_conv2d_window(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<()> {
+    let (_, _, kh, kw) = kernel_shape;
+    if kh == 0 || kw == 0 || stride.0 == 0 || stride.1 == 0 {
+        return Err(DemleError::ValidationError(
+            "Convolution kernel size and stride must be non-zero".to_string(),
+        ));
+    }
+    conv2d_output_size(input_shape, kernel_shape, stride, padding).map(|_| ())
+}
+
+/// FLOP count of a 2D convolution: one multiply and one add per kernel tap
+pub fn conv2d_flops(
+    input_shape: (usize, usize, usize, usize),
+    kernel_shape: (usize, usize, usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<u64> {
+    let (batch, in_ch, _, _) = input_shape;
+    let (out_ch, _, kh, kw) = kernel_shape;
+    let (oh, ow) = conv2d_output_size(input_shape, kernel_shape, stride, padding)?;
 
-    Ok((result_hash, flops))
+    flops_product(&[2, batch, out_ch, in_ch, kh, kw, oh, ow])
 }
 
 #[cfg(test)]
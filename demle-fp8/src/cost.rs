@@ -0,0 +1,424 @@
+use crate::{
+    attention, backward, batch_norm, convolution, embedding, feed_forward, gemm, moe, optimizer,
+    pooling, quantize, softmax, sparse, tensor_ops, training, transformer,
+};
+use demle_core::{MLOperation, Result};
+
+/// Cost model: FLOPs credited for one reference execution of an operation,
+/// without executing it. Fails on operations the execution would reject.
+pub fn estimate_flops(operation: &MLOperation) -> Result<u64> {
+    match operation {
+        MLOperation::MatrixMultiply { dimensions, .. } => gemm::gemm_flops(*dimensions),
+        MLOperation::Convolution2D {
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+            ..
+        } => {
+            convolution::validate_conv2d_window(*input_shape, *kernel_shape, *stride, *padding)?;
+            convolution::conv2d_flops(*input_shape, *kernel_shape, *stride, *padding)
+        }
+        MLOperation::MultiHeadAttention {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            ..
+        } => {
+            attention::validate_attention_heads(*d_model, *num_heads)?;
+            attention::attention_flops(*batch_size, *seq_length, *d_model, *num_heads)
+        }
+        MLOperation::BatchNormalization { shape, .. } => batch_norm::batch_norm_flops(*shape),
+        MLOperation::TransformerBlock {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            d_ff,
+            feed_forward,
+            ..
+        } => {
+            attention::validate_attention_heads(*d_model, *num_heads)?;
+            transformer::transformer_block_flops(
+                *batch_size,
+                *seq_length,
+                *d_model,
+                *num_heads,
+                *d_ff,
+                *feed_forward,
+            )
+        }
+        MLOperation::GatedFeedForward {
+            tokens,
+            d_model,
+            d_ff,
+            ..
+        } => feed_forward::gated_feed_forward_flops(*tokens, *d_model, *d_ff),
+        MLOperation::Pooling2D {
+            input_shape,
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+            ..
+        } => {
+            pooling::validate_pooling2d(*input_shape, *pool_type, *kernel_size, *stride, *padding)?;
+            pooling::pooling2d_flops(*input_shape, *pool_type, *kernel_size, *stride, *padding)
+        }
+        MLOperation::EmbeddingLookup {
+            vocab_size,
+            embedding_dim,
+            num_tokens,
+            ..
+        } => {
+            embedding::validate_embedding_lookup(*vocab_size, *embedding_dim)?;
+            embedding::embedding_lookup_flops(*embedding_dim, *num_tokens)
+        }
+        MLOperation::Softmax { rows, cols, .. } => softmax::softmax_flops(*rows, *cols),
+        MLOperation::MatrixMultiplyBackward { dimensions, .. } => {
+            backward::gemm_backward_flops(*dimensions)
+        }
+        MLOperation::Convolution2DBackward {
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+            ..
+        } => {
+            backward::validate_conv2d_backward(*input_shape, *kernel_shape, *stride, *padding)?;
+            backward::conv2d_backward_flops(*input_shape, *kernel_shape, *stride, *padding)
+        }
+        MLOperation::MultiHeadAttentionBackward {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            ..
+        } => {
+            attention::validate_attention_heads(*d_model, *num_heads)?;
+            backward::attention_backward_flops(*batch_size, *seq_length, *d_model, *num_heads)
+        }
+        MLOperation::OptimizerStep {
+            num_params,
+            optimizer,
+            ..
+        } => {
+            optimizer::validate_optimizer(optimizer)?;
+            optimizer::optimizer_step_flops(*num_params, optimizer)
+        }
+        MLOperation::TrainingStep {
+            batch_size,
+            input_dim,
+            hidden_dim,
+            num_classes,
+            optimizer,
+            ..
+        } => {
+            let shape = training::MlpShape {
+                batch_size: *batch_size,
+                input_dim: *input_dim,
+                hidden_dim: *hidden_dim,
+                num_classes: *num_classes,
+            };
+            shape.validate()?;
+            optimizer::validate_optimizer(optimizer)?;
+            training::training_step_flops(shape, optimizer)
+        }
+        MLOperation::BatchedMatrixMultiply {
+            batch_size,
+            dimensions,
+            a_operand,
+            b_operand,
+            transpose_a,
+            transpose_b,
+            ..
+        } => {
+            let batch = gemm::BatchedGemm {
+                batch_size: *batch_size,
+                dimensions: *dimensions,
+                a_operand: *a_operand,
+                b_operand: *b_operand,
+                transpose_a: *transpose_a,
+                transpose_b: *transpose_b,
+            };
+            batch.validate()?;
+            gemm::batched_gemm_flops(&batch)
+        }
+        MLOperation::SparseMatrixMultiply { dimensions, .. } => {
+            sparse::validate_sparse_gemm(*dimensions)?;
+            sparse::sparse_gemm_flops(*dimensions)
+        }
+        MLOperation::MixtureOfExperts {
+            tokens,
+            d_model,
+            d_ff,
+            num_experts,
+            top_k,
+            capacity_factor,
+            ..
+        } => {
+            let config = moe::MoeConfig {
+                tokens: *tokens,
+                d_model: *d_model,
+                d_ff: *d_ff,
+                num_experts: *num_experts,
+                top_k: *top_k,
+                capacity_factor: *capacity_factor,
+            };
+            config.validate()?;
+            moe::mixture_of_experts_flops(&config)
+        }
+        MLOperation::Quantize {
+            num_elements,
+            source,
+            target,
+            ..
+        } => quantize::quantize_flops(*num_elements, *source, *target),
+        MLOperation::TensorOperation { op, inputs, .. } => {
+            let shapes: Vec<&[usize]> = inputs.iter().map(|input| input.shape()).collect();
+            tensor_ops::output_shape(op, &shapes)?;
+            tensor_ops::tensor_op_flops(op, &shapes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_ml_operation;
+    use demle_core::{
+        Activation, BatchOperand, FeedForwardType, MasterWeights, NumericFormat, Optimizer,
+        PoolType,
+    };
+
+    #[test]
+    fn test_estimate_matches_execution() {
+        let operations = vec![
+            MLOperation::MatrixMultiply {
+                dimensions: (16, 8, 12),
+                seed: 1,
+            },
+            MLOperation::Convolution2D {
+                input_shape: (1, 2, 8, 8),
+                kernel_shape: (4, 2, 3, 3),
+                stride: (2, 1),
+                padding: (1, 0),
+                seed: 2,
+            },
+            MLOperation::MultiHeadAttention {
+                batch_size: 1,
+                seq_length: 4,
+                d_model: 8,
+                num_heads: 2,
+                seed: 3,
+            },
+            MLOperation::BatchNormalization {
+                shape: (2, 3, 4, 4),
+                epsilon: 1e-5,
+                seed: 4,
+            },
+            MLOperation::TransformerBlock {
+                batch_size: 1,
+                seq_length: 4,
+                d_model: 8,
+                num_heads: 2,
+                d_ff: 16,
+                feed_forward: FeedForwardType::SwiGLU,
+                seed: 5,
+            },
+            MLOperation::GatedFeedForward {
+                tokens: 4,
+                d_model: 8,
+                d_ff: 16,
+                activation: Activation::GELU,
+                seed: 6,
+            },
+            MLOperation::Pooling2D {
+                input_shape: (1, 2, 7, 7),
+                pool_type: PoolType::Average,
+                kernel_size: (3, 3),
+                stride: (2, 2),
+                padding: (1, 1),
+                seed: 7,
+            },
+            MLOperation::Pooling2D {
+                input_shape: (2, 3, 5, 5),
+                pool_type: PoolType::GlobalMax,
+                kernel_size: (0, 0),
+                stride: (0, 0),
+                padding: (0, 0),
+                seed: 8,
+            },
+            MLOperation::EmbeddingLookup {
+                vocab_size: 50,
+                embedding_dim: 8,
+                num_tokens: 10,
+                seed: 9,
+            },
+            MLOperation::Softmax {
+                rows: 4,
+                cols: 10,
+                log: true,
+                seed: 10,
+            },
+            MLOperation::MatrixMultiplyBackward {
+                dimensions: (8, 4, 6),
+                seed: 11,
+            },
+            MLOperation::Convolution2DBackward {
+                input_shape: (1, 2, 6, 6),
+                kernel_shape: (3, 2, 3, 3),
+                stride: (1, 2),
+                padding: (1, 1),
+                seed: 12,
+            },
+            MLOperation::MultiHeadAttentionBackward {
+                batch_size: 1,
+                seq_length: 4,
+                d_model: 8,
+                num_heads: 2,
+                seed: 13,
+            },
+            MLOperation::OptimizerStep {
+                num_params: 64,
+                optimizer: Optimizer::AdamW {
+                    learning_rate: 1e-3,
+                    beta1: 0.9,
+                    beta2: 0.999,
+                    epsilon: 1e-8,
+                    weight_decay: 0.01,
+                    step: 10,
+                },
+                master_weights: MasterWeights::BF16,
+                seed: 14,
+            },
+            MLOperation::TrainingStep {
+                batch_size: 8,
+                input_dim: 6,
+                hidden_dim: 12,
+                num_classes: 3,
+                optimizer: Optimizer::SgdMomentum {
+                    learning_rate: 0.1,
+                    momentum: 0.9,
+                    weight_decay: 1e-4,
+                },
+                master_weights: MasterWeights::F32,
+                seed: 15,
+            },
+            MLOperation::BatchedMatrixMultiply {
+                batch_size: 4,
+                dimensions: (6, 5, 3),
+                a_operand: BatchOperand::Shared,
+                b_operand: BatchOperand::PerBatch,
+                transpose_a: true,
+                transpose_b: false,
+                seed: 16,
+            },
+            MLOperation::SparseMatrixMultiply {
+                dimensions: (8, 12, 4),
+                seed: 17,
+            },
+            MLOperation::MixtureOfExperts {
+                tokens: 10,
+                d_model: 8,
+                d_ff: 12,
+                num_experts: 3,
+                top_k: 2,
+                capacity_factor: 1.5,
+                seed: 18,
+            },
+            MLOperation::Quantize {
+                num_elements: 100,
+                source: NumericFormat::FP8E5M2,
+                target: NumericFormat::BF16,
+                seed: 19,
+            },
+        ];
+
+        for operation in &operations {
+            let result = execute_ml_operation(operation).unwrap();
+            assert_eq!(
+                result.flops,
+                estimate_flops(operation).unwrap(),
+                "{}",
+                operation
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_rejects_invalid_operations() {
+        let operations = vec![
+            MLOperation::Pooling2D {
+                input_shape: (1, 2, 7, 7),
+                pool_type: PoolType::Max,
+                kernel_size: (3, 3),
+                stride: (0, 2),
+                padding: (0, 0),
+                seed: 1,
+            },
+            MLOperation::Convolution2D {
+                input_shape: (1, 2, 4, 4),
+                kernel_shape: (4, 2, 7, 7),
+                stride: (1, 1),
+                padding: (1, 1),
+                seed: 2,
+            },
+            MLOperation::MultiHeadAttention {
+                batch_size: 1,
+                seq_length: 4,
+                d_model: 8,
+                num_heads: 0,
+                seed: 3,
+            },
+        ];
+
+        for operation in &operations {
+            assert!(estimate_flops(operation).is_err(), "{}", operation);
+            assert!(execute_ml_operation(operation).is_err(), "{}", operation);
+        }
+    }
+
+    #[test]
+    fn test_estimate_rejects_overflowing_operations() {
+        let huge = 1 << 22;
+        let operations = vec![
+            MLOperation::MatrixMultiply {
+                dimensions: (huge, huge, huge),
+                seed: 1,
+            },
+            MLOperation::Convolution2D {
+                input_shape: (1, 2, 4, 4),
+                kernel_shape: (4, 2, 3, 3),
+                stride: (1, 1),
+                padding: (usize::MAX / 2, 1),
+                seed: 2,
+            },
+            MLOperation::Pooling2D {
+                input_shape: (1, 2, 7, 7),
+                pool_type: PoolType::Average,
+                kernel_size: (3, 3),
+                stride: (1, 1),
+                padding: (1, usize::MAX / 2),
+                seed: 3,
+            },
+            MLOperation::TransformerBlock {
+                batch_size: huge,
+                seq_length: huge,
+                d_model: huge,
+                num_heads: 2,
+                d_ff: huge,
+                feed_forward: FeedForwardType::GELU,
+                seed: 4,
+            },
+        ];
+
+        for operation in &operations {
+            assert!(estimate_flops(operation).is_err(), "{}", operation);
+        }
+        // Rejected before any operand is allocated
+        assert!(execute_ml_operation(&operations[0]).is_err());
+        assert!(execute_ml_operation(&operations[1]).is_err());
+    }
+}
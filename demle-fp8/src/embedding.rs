@@ -0,0 +1,112 @@
+use crate::fp8::FP8;
+use crate::operations::{flops_product, generate_random_tensor, OperationTensors};
+use demle_core::{DemleError, Result};
+use rand::{Rng, SeedableRng};
+
+/// Check that the embedding table has a non-empty vocabulary and rows
+pub fn validate_embedding_lookup(vocab_size: usize, embedding_dim: usize) -> Result<()> {
+    if vocab_size == 0 || embedding_dim == 0 {
+        return Err(DemleError::ValidationError(
+            "Embedding table must have a non-empty vocabulary and dimension".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Execute an embedding-table gather: look up seeded token ids in a seeded
+/// (vocab_size, embedding_dim) table
+pub fn execute_embedding_lookup(
+    vocab_size: usize,
+    embedding_dim: usize,
+    num_tokens: usize,
+    seed: u64,
+) -> Result<(String, u64)> {
+    let flops = embedding_lookup_flops(embedding_dim, num_tokens)?;
+    let tensors = embedding_lookup_tensors(vocab_size, embedding_dim, num_tokens, seed)?;
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded table and token ids and output of an embedding lookup
+pub fn embedding_lookup_tensors(
+    vocab_size: usize,
+    embedding_dim: usize,
+    num_tokens: usize,
+    seed: u64,
+) -> Result<OperationTensors> {
+    validate_embedding_lookup(vocab_size, embedding_dim)?;
+
+    let table = generate_random_tensor(&[vocab_size, embedding_dim], seed)?;
+    let token_ids = generate_token_ids(vocab_size, num_tokens, seed.wrapping_add(1));
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&table);
+    tensors.add_ids(&token_ids);
+
+    let output = embedding_lookup(&table, embedding_dim, &token_ids)?;
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// Generate uniformly distributed token ids in [0, vocab_size) from a seed
+pub fn generate_token_ids(vocab_size: usize, num_tokens: usize, seed: u64) -> Vec<usize> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    (0..num_tokens)
+        .map(|_| rng.random_range(0..vocab_size))
+        .collect()
+}
+
+/// Gather rows of a row-major (vocab_size, embedding_dim) table
+pub fn embedding_lookup(
+    table: &[FP8],
+    embedding_dim: usize,
+    token_ids: &[usize],
+) -> Result<Vec<FP8>> {
+    let vocab_size = table.len() / embedding_dim.max(1);
+    let mut output = Vec::with_capacity(token_ids.len() * embedding_dim);
+
+    for &token in token_ids {
+        if token >= vocab_size {
+            return Err(DemleError::ValidationError(format!(
+                "Token id {} out of range for vocabulary of {}",
+                token, vocab_size
+            )));
+        }
+        output.extend_from_slice(&table[token * embedding_dim..(token + 1) * embedding_dim]);
+    }
+
+    Ok(output)
+}
+
+/// Cost of an embedding gather: a gather performs no arithmetic, so it is
+/// credited one operation per copied element
+pub fn embedding_lookup_flops(embedding_dim: usize, num_tokens: usize) -> Result<u64> {
+    flops_product(&[embedding_dim, num_tokens])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_lookup() {
+        let table: Vec<FP8> = (0..12).map(|i| FP8::from_f32(i as f32)).collect();
+
+        let output = embedding_lookup(&table, 3, &[2, 0, 2]).unwrap();
+        let values: Vec<f32> = output.iter().map(|x| x.to_f32()).collect();
+        assert_eq!(values, [6.0, 7.0, 8.0, 0.0, 1.0, 2.0, 6.0, 7.0, 8.0]);
+
+        assert!(embedding_lookup(&table, 3, &[4]).is_err());
+    }
+
+    #[test]
+    fn test_embedding_execution() {
+        let (hash, flops) = execute_embedding_lookup(1000, 64, 128, 42).unwrap();
+        assert!(!hash.is_empty());
+        assert_eq!(flops, 64 * 128);
+
+        let again = execute_embedding_lookup(1000, 64, 128, 42).unwrap();
+        assert_eq!(hash, again.0);
+
+        let token_ids = generate_token_ids(1000, 128, 43);
+        assert!(token_ids.iter().all(|&t| t < 1000));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::fp8::FP8;
+use crate::gemm::{gemm_fp8_fused, Epilogue};
+use crate::operations::{
+    flops_product, flops_sum, generate_random_tensor, ActivationType, OperationTensors,
+};
+use demle_core::{Activation, Result};
+
+/// Weights of a gated feed-forward layer with biases
+pub struct GatedFeedForwardWeights<'a> {
+    /// (d_model, d_ff)
+    pub w_gate: &'a [FP8],
+    pub b_gate: &'a [FP8],
+    /// (d_model, d_ff)
+    pub w_up: &'a [FP8],
+    pub b_up: &'a [FP8],
+    /// (d_ff, d_model)
+    pub w_down: &'a [FP8],
+    pub b_down: &'a [FP8],
+}
+
+/// Gated feed-forward layer with a residual connection:
+///
+/// ```text
+/// out = x + (act(x * W_gate + b_gate) * (x * W_up + b_up)) * W_down + b_down
+/// ```
+///
+/// Bias, activation, gating and residual are fused into the GEMM epilogues.
+pub fn gated_feed_forward(
+    input: &[FP8],
+    weights: &GatedFeedForwardWeights,
+    tokens: usize,
+    d_model: usize,
+    d_ff: usize,
+    activation: ActivationType,
+) -> Vec<FP8> {
+    let gate = gemm_fp8_fused(
+        input,
+        weights.w_gate,
+        tokens,
+        d_model,
+        d_ff,
+        &Epilogue {
+            bias: Some(weights.b_gate),
+            activation: Some(activation),
+            ..Default::default()
+        },
+    );
+
+    let hidden = gemm_fp8_fused(
+        input,
+        weights.w_up,
+        tokens,
+        d_model,
+        d_ff,
+        &Epilogue {
+            bias: Some(weights.b_up),
+            gate: Some(&gate),
+            ..Default::default()
+        },
+    );
+
+    gemm_fp8_fused(
+        &hidden,
+        weights.w_down,
+        tokens,
+        d_ff,
+        d_model,
+        &Epilogue {
+            bias: Some(weights.b_down),
+            residual: Some(input),
+            ..Default::default()
+        },
+    )
+}
+
+/// Execute a gated feed-forward (GLU-style MLP) operation on seeded inputs
+pub fn execute_gated_feed_forward(
+    tokens: usize,
+    d_model: usize,
+    d_ff: usize,
+    activation: Activation,
+    seed: u64,
+) -> Result<(String, u64)> {
+    let flops = gated_feed_forward_flops(tokens, d_model, d_ff)?;
+    let tensors = gated_feed_forward_tensors(tokens, d_model, d_ff, activation, seed)?;
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded input, weights and biases and output of a gated feed-forward layer
+pub fn gated_feed_forward_tensors(
+    tokens: usize,
+    d_model: usize,
+    d_ff: usize,
+    activation: Activation,
+    seed: u64,
+) -> Result<OperationTensors> {
+    let input = generate_random_tensor(&[tokens, d_model], seed)?;
+
+    let w_gate = generate_random_tensor(&[d_model, d_ff], seed.wrapping_add(1))?;
+    let b_gate = generate_random_tensor(&[d_ff], seed.wrapping_add(2))?;
+    let w_up = generate_random_tensor(&[d_model, d_ff], seed.wrapping_add(3))?;
+    let b_up = generate_random_tensor(&[d_ff], seed.wrapping_add(4))?;
+    let w_down = generate_random_tensor(&[d_ff, d_model], seed.wrapping_add(5))?;
+    let b_down = generate_random_tensor(&[d_model], seed.wrapping_add(6))?;
+    let mut tensors = OperationTensors::default();
+    for operand in [&input, &w_gate, &b_gate, &w_up, &b_up, &w_down, &b_down] {
+        tensors.add_fp8(operand);
+    }
+
+    let weights = GatedFeedForwardWeights {
+        w_gate: &w_gate,
+        b_gate: &b_gate,
+        w_up: &w_up,
+        b_up: &b_up,
+        w_down: &w_down,
+        b_down: &b_down,
+    };
+
+    let output = gated_feed_forward(&input, &weights, tokens, d_model, d_ff, activation.into());
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// FLOP count of a gated feed-forward layer
+pub fn gated_feed_forward_flops(tokens: usize, d_model: usize, d_ff: usize) -> Result<u64> {
+    // Gate, up and down projections
+    let gemm_flops = flops_product(&[3 * 2, tokens, d_model, d_ff])?;
+    // Epilogues: two hidden biases, activation and gating product
+    let hidden_flops = flops_product(&[4, tokens, d_ff])?;
+    // Epilogue: output bias and residual add
+    let output_flops = flops_product(&[2, tokens, d_model])?;
+
+    flops_sum(&[gemm_flops, hidden_flops, output_flops])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gated_feed_forward_execution() {
+        let (hash, flops) = execute_gated_feed_forward(16, 32, 64, Activation::Swish, 42).unwrap();
+
+        assert!(!hash.is_empty());
+        assert_eq!(flops, 3 * 2 * 16 * 32 * 64 + 4 * 16 * 64 + 2 * 16 * 32);
+    }
+
+    #[test]
+    fn test_gated_feed_forward_deterministic() {
+        let result1 = execute_gated_feed_forward(8, 16, 32, Activation::GELU, 123).unwrap();
+        let result2 = execute_gated_feed_forward(8, 16, 32, Activation::GELU, 123).unwrap();
+        assert_eq!(result1, result2);
+
+        // The gate activation is part of the computation
+        let relu = execute_gated_feed_forward(8, 16, 32, Activation::ReLU, 123).unwrap();
+        assert_ne!(result1.0, relu.0);
+        assert_eq!(result1.1, relu.1);
+    }
+}
@@ -8,6 +8,12 @@ pub struct FP8 {
 }
 
 impl FP8 {
+    /// Largest finite E4M3 magnitude, the target range of scaled quantization
+    pub const MAX: f32 = 448.0;
+
+    /// Smallest positive normal value (2^-6); smaller magnitudes are not kept
+    pub const MIN_NORMAL: f32 = 0.015625;
+
     /// Create FP8 from raw bits
     pub fn from_bits(bits: u8) -> Self {
         Self { bits }
@@ -114,6 +120,85 @@ impl Div for FP8 {
     }
 }
 
+/// FP8 data type using E5M2 format (1 sign + 5 exponent + 2 mantissa bits).
+///
+/// E5M2 trades mantissa for range and is used for gradients. It has the same
+/// layout as the upper byte of an IEEE half, including infinities and NaNs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FP8E5M2 {
+    bits: u8,
+}
+
+impl FP8E5M2 {
+    /// Largest finite E5M2 magnitude
+    pub const MAX: f32 = 57344.0;
+
+    /// Smallest positive normal value (2^-14)
+    const MIN_NORMAL: f32 = 6.103_515_6e-5;
+
+    /// Create FP8 E5M2 from raw bits
+    pub fn from_bits(bits: u8) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    pub fn to_bits(self) -> u8 {
+        self.bits
+    }
+
+    /// Create FP8 E5M2 zero
+    pub fn zero() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Convert from f32 with round-to-nearest-even; overflow saturates to infinity
+    pub fn from_f32(value: f32) -> Self {
+        let sign = ((value.to_bits() >> 24) & 0x80) as u8;
+        if value.is_nan() {
+            return Self { bits: sign | 0x7F };
+        }
+
+        let abs = value.abs();
+        if abs < Self::MIN_NORMAL {
+            // Subnormals are multiples of 2^-16; a result of 4 rounds up to the smallest normal
+            let mantissa = (abs * 65536.0).round_ties_even() as u8;
+            return Self {
+                bits: sign | mantissa,
+            };
+        }
+
+        // Round the 23-bit f32 mantissa to 2 bits, carrying into the exponent
+        let abs_bits = abs.to_bits();
+        let rounded = abs_bits + 0x000F_FFFF + ((abs_bits >> 21) & 1);
+        let exp = ((rounded >> 23) as i32) - 127 + 15;
+        if exp >= 31 {
+            return Self { bits: sign | 0x7C };
+        }
+
+        let mantissa = ((rounded >> 21) & 0x3) as u8;
+        Self {
+            bits: sign | ((exp as u8) << 2) | mantissa,
+        }
+    }
+
+    /// Convert from FP8 E5M2 to f32 (exact)
+    pub fn to_f32(self) -> f32 {
+        half::f16::from_bits((self.bits as u16) << 8).to_f32()
+    }
+}
+
+impl From<f32> for FP8E5M2 {
+    fn from(value: f32) -> Self {
+        Self::from_f32(value)
+    }
+}
+
+impl From<FP8E5M2> for f32 {
+    fn from(value: FP8E5M2) -> Self {
+        value.to_f32()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,4 +232,30 @@ mod tests {
         let product = (a * b).to_f32();
         assert!((product - 6.0).abs() < 0.1);
     }
+
+    #[test]
+    fn test_e5m2_conversion() {
+        // Exactly representable values round-trip
+        for &val in &[0.0, 1.0, -1.0, 1.5, -0.75, 57344.0, 2.0f32.powi(-16)] {
+            assert_eq!(FP8E5M2::from_f32(val).to_f32(), val);
+        }
+
+        // Round to nearest even between 1.0 and 1.25
+        assert_eq!(FP8E5M2::from_f32(1.1).to_f32(), 1.0);
+        assert_eq!(FP8E5M2::from_f32(1.2).to_f32(), 1.25);
+        assert_eq!(FP8E5M2::from_f32(1.125).to_f32(), 1.0);
+        assert_eq!(FP8E5M2::from_f32(1.375).to_f32(), 1.5);
+
+        // Overflow saturates to infinity, NaN stays NaN
+        assert_eq!(FP8E5M2::from_f32(1e6).to_f32(), f32::INFINITY);
+        assert!(FP8E5M2::from_f32(f32::NAN).to_f32().is_nan());
+
+        // Every non-NaN bit pattern round-trips through f32
+        for bits in 0..=255u8 {
+            let value = FP8E5M2::from_bits(bits).to_f32();
+            if !value.is_nan() {
+                assert_eq!(FP8E5M2::from_f32(value).to_bits(), bits);
+            }
+        }
+    }
 }
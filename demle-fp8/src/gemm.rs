@@ -1,11 +1,19 @@
 use crate::fp8::FP8;
-use demle_core::{proof::Proof, DemleError, Result};
+use crate::operations::{
+    activate, flops_product, generate_random_tensor, ActivationType, OperationTensors,
+};
+use demle_core::proof::{GemmClaim, Proof};
+use demle_core::{BatchOperand, DemleError, Result};
 use rand::SeedableRng;
 use rand_distr::{Distribution, Normal};
 use rayon::prelude::*;
 
 #[cfg(feature = "cuda")]
-use candle_core::{Device, Tensor};
+use crate::quantize::{dequantize, from_candle, quantize, to_candle};
+#[cfg(feature = "cuda")]
+use candle_core::Device;
+#[cfg(feature = "cuda")]
+use demle_core::NumericFormat;
 
 /// Execute FP8 GEMM operation: C = A * B
 /// Uses GPU acceleration when available
@@ -49,18 +57,9 @@ fn execute_gemm_gpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(Str
         .map(|_| normal.sample(&mut rng) as f32)
         .collect();
 
-    // Create tensors on GPU with BF16 for maximum H100 tensor core utilization
-    let a_tensor = Tensor::from_vec(a_data, (m, k), &device).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to create tensor A: {}", e))
-    })?.to_dtype(candle_core::DType::BF16).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to convert A to BF16: {}", e))
-    })?;
-    
-    let b_tensor = Tensor::from_vec(b_data, (k, n), &device).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to create tensor B: {}", e))
-    })?.to_dtype(candle_core::DType::BF16).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to convert B to BF16: {}", e))
-    })?;
+    // Upload as BF16 for maximum H100 tensor core utilization
+    let a_tensor = to_candle(&quantize(&a_data, NumericFormat::BF16), &[m, k], &device)?;
+    let b_tensor = to_candle(&quantize(&b_data, NumericFormat::BF16), &[k, n], &device)?;
 
     // Execute optimized batch operations for H100 tensor cores (balanced for memory)
     let mut total_flops = 0u64;
@@ -78,11 +77,7 @@ fn execute_gemm_gpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(Str
 
     // Single transfer back to CPU for hashing (minimize PCIe overhead)
     let final_result = &all_results[0]; // Use first result for hash
-    let c_data: Vec<f32> = final_result.to_dtype(candle_core::DType::F32).map_err(|e| {
-        DemleError::ComputationError(format!("Failed to convert result to F32: {}", e))
-    })?.to_vec2().map_err(|e| {
-        DemleError::ComputationError(format!("Failed to get result from GPU: {}", e))
-    })?.into_iter().flatten().collect();
+    let c_data = dequantize(&from_candle(final_result, NumericFormat::F32)?);
 
     // Hash the result (convert to FP8 for consistency)
     let fp8_data: Vec<FP8> = c_data.iter().map(|&f| FP8::from_f32(f)).collect();
@@ -92,7 +87,11 @@ fn execute_gemm_gpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(Str
     Ok((result_hash, total_flops))
 }
 
-fn execute_gemm_cpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(String, u64)> {
+/// Seeded GEMM operands A(m×k) and B(k×n) of a matrix-multiply operation
+pub fn generate_gemm_inputs(
+    dimensions: (usize, usize, usize),
+    seed: u64,
+) -> Result<(Vec<FP8>, Vec<FP8>)> {
     let (m, k, n) = dimensions;
 
     // Generate random matrices using seed for reproducibility
@@ -110,8 +109,87 @@ fn execute_gemm_cpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(Str
         .map(|_| FP8::from_f32(normal.sample(&mut rng) as f32))
         .collect();
 
+    Ok((a_data, b_data))
+}
+
+/// Seeded operands and output of a matrix-multiply operation
+pub fn gemm_tensors(dimensions: (usize, usize, usize), seed: u64) -> Result<OperationTensors> {
+    let (m, k, n) = dimensions;
+    let (a_data, b_data) = generate_gemm_inputs(dimensions, seed)?;
+
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&a_data);
+    tensors.add_fp8(&b_data);
+
     // Perform GEMM in parallel
-    let c_data: Vec<FP8> = (0..m * n)
+    let c_data = gemm_fp8(&a_data, &b_data, m, k, n);
+
+    Ok(tensors.with_fp8_output(&c_data))
+}
+
+fn execute_gemm_cpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(String, u64)> {
+    // Calculate FLOPS (2 * m * k * n for GEMM)
+    let flops = gemm_flops(dimensions)?;
+    let tensors = gemm_tensors(dimensions, seed)?;
+
+    Ok((tensors.result_hash(), flops))
+}
+
+/// FLOP count of a GEMM: one multiply and one add per inner-product term
+pub fn gemm_flops(dimensions: (usize, usize, usize)) -> Result<u64> {
+    let (m, k, n) = dimensions;
+    flops_product(&[2, m, k, n])
+}
+
+/// Element-wise operations fused into the GEMM output write, applied in order:
+/// bias add, activation, gating multiply, residual add
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Epilogue<'a> {
+    /// Per-column bias of length n
+    pub bias: Option<&'a [FP8]>,
+    pub activation: Option<ActivationType>,
+    /// Element-wise gate of shape (m, n)
+    pub gate: Option<&'a [FP8]>,
+    /// Element-wise residual of shape (m, n)
+    pub residual: Option<&'a [FP8]>,
+}
+
+impl Epilogue<'_> {
+    /// Apply the epilogue to the accumulated value of output element (i, j)
+    fn apply(&self, value: FP8, idx: usize, j: usize) -> FP8 {
+        let mut value = value;
+        if let Some(bias) = self.bias {
+            value = value + bias[j];
+        }
+        if let Some(activation) = self.activation {
+            value = activate(value, activation);
+        }
+        if let Some(gate) = self.gate {
+            value = value * gate[idx];
+        }
+        if let Some(residual) = self.residual {
+            value = value + residual[idx];
+        }
+        value
+    }
+}
+
+/// Row-major FP8 GEMM kernel: C(m×n) = A(m×k) * B(k×n), parallel over output elements
+pub fn gemm_fp8(a: &[FP8], b: &[FP8], m: usize, k: usize, n: usize) -> Vec<FP8> {
+    gemm_fp8_fused(a, b, m, k, n, &Epilogue::default())
+}
+
+/// Row-major FP8 GEMM kernel with a fused epilogue, so bias, activation, gating and
+/// residual connections are applied without an extra pass over the output
+pub fn gemm_fp8_fused(
+    a: &[FP8],
+    b: &[FP8],
+    m: usize,
+    k: usize,
+    n: usize,
+    epilogue: &Epilogue,
+) -> Vec<FP8> {
+    (0..m * n)
         .into_par_iter()
         .map(|idx| {
             let i = idx / n;
@@ -119,23 +197,39 @@ fn execute_gemm_cpu(dimensions: (usize, usize, usize), seed: u64) -> Result<(Str
 
             let mut sum = FP8::zero();
             for l in 0..k {
-                let a_val = a_data[i * k + l];
-                let b_val = b_data[l * n + j];
+                let a_val = a[i * k + l];
+                let b_val = b[l * n + j];
                 sum = sum + (a_val * b_val);
             }
-            sum
+            epilogue.apply(sum, idx, j)
         })
-        .collect();
-
-    // Calculate FLOPS (2 * m * k * n for GEMM)
-    let flops = 2 * (m as u64) * (k as u64) * (n as u64);
-
-    // Hash the result
-    let result_bytes: Vec<u8> = c_data.iter().flat_map(|fp8| vec![fp8.to_bits()]).collect();
+        .collect()
+}
 
-    let result_hash = Proof::hash_operation_result(&result_bytes);
+/// FP8 GEMM with FP32 accumulation, as on tensor cores. The FP32 output is
+/// linear in its inputs up to rounding, so it can be checked with Freivalds.
+pub fn gemm_fp8_f32(a: &[FP8], b: &[FP8], m: usize, k: usize, n: usize) -> Vec<f32> {
+    (0..m * n)
+        .into_par_iter()
+        .map(|idx| {
+            let i = idx / n;
+            let j = idx % n;
+            (0..k)
+                .map(|l| a[i * k + l].to_f32() * b[l * n + j].to_f32())
+                .sum()
+        })
+        .collect()
+}
 
-    Ok((result_hash, flops))
+/// Committable FP32-accumulated output of a seeded matrix-multiply operation.
+/// This is not the output a mined result hashes, which accumulates in FP8
+pub fn gemm_claim(dimensions: (usize, usize, usize), seed: u64) -> Result<GemmClaim> {
+    let (m, k, n) = dimensions;
+    let (a, b) = generate_gemm_inputs(dimensions, seed)?;
+    Ok(GemmClaim {
+        dimensions,
+        output: gemm_fp8_f32(&a, &b, m, k, n),
+    })
 }
 
 /// Optimized GEMM using blocked algorithm for better cache performance
@@ -157,11 +251,11 @@ pub fn execute_gemm_blocked(
     let mut c = vec![FP8::zero(); m * n];
 
     // Initialize A and B
-    for i in 0..m * k {
-        a[i] = FP8::from_f32(normal.sample(&mut rng) as f32);
+    for value in a.iter_mut() {
+        *value = FP8::from_f32(normal.sample(&mut rng) as f32);
     }
-    for i in 0..k * n {
-        b[i] = FP8::from_f32(normal.sample(&mut rng) as f32);
+    for value in b.iter_mut() {
+        *value = FP8::from_f32(normal.sample(&mut rng) as f32);
     }
 
     // Blocked GEMM
@@ -185,7 +279,7 @@ pub fn execute_gemm_blocked(
         }
     }
 
-    let flops = 2 * (m as u64) * (k as u64) * (n as u64);
+    let flops = gemm_flops(dimensions)?;
     let result_bytes: Vec<u8> = c.iter().flat_map(|fp8| vec![fp8.to_bits()]).collect();
 
     let result_hash = Proof::hash_operation_result(&result_bytes);
@@ -193,6 +287,150 @@ pub fn execute_gemm_blocked(
     Ok((result_hash, flops))
 }
 
+/// Shape and operand layout of a batched GEMM: C[i] = op(A[i]) * op(B[i])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchedGemm {
+    pub batch_size: usize,
+    pub dimensions: (usize, usize, usize), // (m, k, n)
+    pub a_operand: BatchOperand,
+    pub b_operand: BatchOperand,
+    /// A is stored as (k, m) row-major
+    pub transpose_a: bool,
+    /// B is stored as (n, k) row-major
+    pub transpose_b: bool,
+}
+
+impl BatchedGemm {
+    /// Check that the batch is non-empty
+    pub fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 {
+            return Err(DemleError::ValidationError(
+                "Batched GEMM batch size must be non-zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of matrices stored for an operand
+    fn count(&self, operand: BatchOperand) -> usize {
+        match operand {
+            BatchOperand::PerBatch => self.batch_size,
+            BatchOperand::Shared => 1,
+        }
+    }
+
+    /// Stored (rows, cols) of A and B, after accounting for transposition
+    fn stored_shapes(&self) -> ((usize, usize), (usize, usize)) {
+        let (m, k, n) = self.dimensions;
+        let a = if self.transpose_a { (k, m) } else { (m, k) };
+        let b = if self.transpose_b { (n, k) } else { (k, n) };
+        (a, b)
+    }
+
+    /// Number of FP8 elements in the A and B buffers
+    pub fn operand_lengths(&self) -> (usize, usize) {
+        let ((ar, ac), (br, bc)) = self.stored_shapes();
+        (
+            self.count(self.a_operand) * ar * ac,
+            self.count(self.b_operand) * br * bc,
+        )
+    }
+}
+
+/// Execute a batched FP8 GEMM on seeded operands. This runs the CPU kernel
+/// on every build: the result hash commits to its FP8 accumulation, which a
+/// BF16 GPU matmul does not reproduce bit for bit.
+pub fn execute_batched_gemm(batch: &BatchedGemm, seed: u64) -> Result<(String, u64)> {
+    batch.validate()?;
+    execute_batched_gemm_cpu(batch, seed)
+}
+
+/// Seeded A and B buffers of a batched GEMM
+fn batched_gemm_inputs(batch: &BatchedGemm, seed: u64) -> Result<(Vec<FP8>, Vec<FP8>)> {
+    let (a_len, b_len) = batch.operand_lengths();
+    let a = generate_random_tensor(&[a_len], seed)?;
+    let b = generate_random_tensor(&[b_len], seed.wrapping_add(1))?;
+    Ok((a, b))
+}
+
+/// Seeded operands and output of a batched GEMM
+pub fn batched_gemm_tensors(batch: &BatchedGemm, seed: u64) -> Result<OperationTensors> {
+    batch.validate()?;
+    let (a, b) = batched_gemm_inputs(batch, seed)?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&a);
+    tensors.add_fp8(&b);
+    let c = batched_gemm_fp8(&a, &b, batch)?;
+    Ok(tensors.with_fp8_output(&c))
+}
+
+fn execute_batched_gemm_cpu(batch: &BatchedGemm, seed: u64) -> Result<(String, u64)> {
+    let flops = batched_gemm_flops(batch)?;
+    let tensors = batched_gemm_tensors(batch, seed)?;
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Batched FP8 GEMM kernel, parallel over the batch. Returns the (batch, m, n)
+/// outputs in row-major order
+pub fn batched_gemm_fp8(a: &[FP8], b: &[FP8], batch: &BatchedGemm) -> Result<Vec<FP8>> {
+    let (m, k, n) = batch.dimensions;
+    let (a_len, b_len) = batch.operand_lengths();
+    if a.len() != a_len || b.len() != b_len {
+        return Err(DemleError::ValidationError(format!(
+            "Batched GEMM expects operands of {} and {} elements, got {} and {}",
+            a_len,
+            b_len,
+            a.len(),
+            b.len()
+        )));
+    }
+
+    let a_stride = match batch.a_operand {
+        BatchOperand::PerBatch => m * k,
+        BatchOperand::Shared => 0,
+    };
+    let b_stride = match batch.b_operand {
+        BatchOperand::PerBatch => k * n,
+        BatchOperand::Shared => 0,
+    };
+
+    let mut c = vec![FP8::zero(); batch.batch_size * m * n];
+    c.par_chunks_mut((m * n).max(1))
+        .enumerate()
+        .for_each(|(entry, c_matrix)| {
+            let a_matrix = &a[entry * a_stride..][..m * k];
+            let b_matrix = &b[entry * b_stride..][..k * n];
+
+            for i in 0..m {
+                for j in 0..n {
+                    let mut sum = FP8::zero();
+                    for l in 0..k {
+                        let a_val = if batch.transpose_a {
+                            a_matrix[l * m + i]
+                        } else {
+                            a_matrix[i * k + l]
+                        };
+                        let b_val = if batch.transpose_b {
+                            b_matrix[j * k + l]
+                        } else {
+                            b_matrix[l * n + j]
+                        };
+                        sum = sum + (a_val * b_val);
+                    }
+                    c_matrix[i * n + j] = sum;
+                }
+            }
+        });
+
+    Ok(c)
+}
+
+/// FLOP count of a batched GEMM: one GEMM per batch entry, shared or not
+pub fn batched_gemm_flops(batch: &BatchedGemm) -> Result<u64> {
+    let (m, k, n) = batch.dimensions;
+    flops_product(&[2, batch.batch_size, m, k, n])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -223,6 +461,43 @@ mod tests {
         assert_eq!(result1.1, result2.1);
     }
 
+    #[test]
+    fn test_fused_epilogue_matches_separate_passes() {
+        use crate::operations::{
+            add_tensors, apply_activation, generate_random_tensor, mul_tensors,
+        };
+
+        let (m, k, n) = (8, 16, 12);
+        let a = generate_random_tensor(&[m, k], 1).unwrap();
+        let b = generate_random_tensor(&[k, n], 2).unwrap();
+        let bias = generate_random_tensor(&[n], 3).unwrap();
+        let gate = generate_random_tensor(&[m, n], 4).unwrap();
+        let residual = generate_random_tensor(&[m, n], 5).unwrap();
+
+        let fused = gemm_fp8_fused(
+            &a,
+            &b,
+            m,
+            k,
+            n,
+            &Epilogue {
+                bias: Some(&bias),
+                activation: Some(ActivationType::GELU),
+                gate: Some(&gate),
+                residual: Some(&residual),
+            },
+        );
+
+        let biased: Vec<FP8> = gemm_fp8(&a, &b, m, k, n)
+            .chunks(n)
+            .flat_map(|row| add_tensors(row, &bias))
+            .collect();
+        let activated = apply_activation(&biased, ActivationType::GELU);
+        let separate = add_tensors(&mul_tensors(&activated, &gate), &residual);
+
+        assert_eq!(fused, separate);
+    }
+
     #[test]
     fn test_blocked_gemm() {
         let dimensions = (128, 128, 128);
@@ -236,4 +511,95 @@ mod tests {
         assert!(!hash.is_empty());
         assert_eq!(flops, 2 * 128 * 128 * 128);
     }
+
+    #[test]
+    fn test_batched_gemm_matches_gemm() {
+        let (m, k, n) = (4, 6, 5);
+        let mut batch = BatchedGemm {
+            batch_size: 3,
+            dimensions: (m, k, n),
+            a_operand: BatchOperand::PerBatch,
+            b_operand: BatchOperand::Shared,
+            transpose_a: false,
+            transpose_b: false,
+        };
+        let a = generate_random_tensor(&[3, m, k], 1).unwrap();
+        let b = generate_random_tensor(&[k, n], 2).unwrap();
+
+        let c = batched_gemm_fp8(&a, &b, &batch).unwrap();
+        for entry in 0..3 {
+            let expected = gemm_fp8(&a[entry * m * k..][..m * k], &b, m, k, n);
+            assert_eq!(&c[entry * m * n..][..m * n], expected.as_slice());
+        }
+
+        // Storing both operands transposed gives the same product
+        let transpose = |data: &[FP8], rows: usize, cols: usize| -> Vec<FP8> {
+            (0..cols)
+                .flat_map(|j| (0..rows).map(move |i| data[i * cols + j]))
+                .collect()
+        };
+        let a_t: Vec<FP8> = a
+            .chunks(m * k)
+            .flat_map(|matrix| transpose(matrix, m, k))
+            .collect();
+        let b_t = transpose(&b, k, n);
+        batch.transpose_a = true;
+        batch.transpose_b = true;
+        assert_eq!(batched_gemm_fp8(&a_t, &b_t, &batch).unwrap(), c);
+
+        assert!(batched_gemm_fp8(&a, &a, &batch).is_err());
+    }
+
+    #[test]
+    fn test_batched_gemm_execution() {
+        let batch = BatchedGemm {
+            batch_size: 8,
+            dimensions: (16, 8, 16),
+            a_operand: BatchOperand::PerBatch,
+            b_operand: BatchOperand::PerBatch,
+            transpose_a: false,
+            transpose_b: true,
+        };
+
+        let (hash, flops) = execute_batched_gemm(&batch, 42).unwrap();
+        assert_eq!(flops, 8 * 2 * 16 * 8 * 16);
+        assert_eq!(hash, execute_batched_gemm(&batch, 42).unwrap().0);
+
+        let shared = BatchedGemm {
+            a_operand: BatchOperand::Shared,
+            ..batch
+        };
+        assert_ne!(hash, execute_batched_gemm(&shared, 42).unwrap().0);
+
+        let empty = BatchedGemm {
+            batch_size: 0,
+            ..batch
+        };
+        assert!(execute_batched_gemm(&empty, 42).is_err());
+    }
+
+    #[test]
+    fn test_gemm_claim_passes_freivalds() {
+        use demle_core::proof::FreivaldsVerifier;
+        use rand::SeedableRng;
+
+        let dimensions = (32, 64, 48);
+        let claim = gemm_claim(dimensions, 7).unwrap();
+        let (a, b) = generate_gemm_inputs(dimensions, 7).unwrap();
+        let a: Vec<f32> = a.iter().map(|x| x.to_f32()).collect();
+        let b: Vec<f32> = b.iter().map(|x| x.to_f32()).collect();
+
+        let verifier = FreivaldsVerifier::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let commitment = claim.commitment();
+        assert!(verifier
+            .verify(&a, &b, &claim, &commitment, &mut rng)
+            .unwrap());
+
+        let mut tampered = claim.clone();
+        tampered.output[100] = -tampered.output[100] - 1.0;
+        assert!(!verifier
+            .verify(&a, &b, &tampered, &tampered.commitment(), &mut rng)
+            .unwrap());
+    }
 }
@@ -0,0 +1,293 @@
+use crate::execute_ml_operation_with_store;
+use crate::store::TensorStore;
+use crate::tensor_ops::{output_shape, resolve_inputs};
+use demle_core::{DemleError, MLOperation, OperationResult, Result, TensorInput, TensorRef};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Dependency graph of a work unit's operations. A tensor operation that reads
+/// an edge depends on the operation that names the edge as its output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationGraph {
+    /// Node names: the output edge of a tensor operation, otherwise `#<index>`
+    pub names: Vec<String>,
+    /// Indices of the nodes each node reads from
+    pub dependencies: Vec<Vec<usize>>,
+    /// Topological waves; every node depends only on nodes of earlier waves
+    pub waves: Vec<Vec<usize>>,
+}
+
+impl OperationGraph {
+    /// Build the graph, rejecting duplicate or missing edges and cycles
+    pub fn build(operations: &[MLOperation]) -> Result<Self> {
+        let mut producers = HashMap::new();
+        let names: Vec<String> = operations
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| match operation {
+                MLOperation::TensorOperation {
+                    output: Some(name), ..
+                } => name.clone(),
+                _ => format!("#{}", i),
+            })
+            .collect();
+
+        for (i, operation) in operations.iter().enumerate() {
+            if let MLOperation::TensorOperation {
+                output: Some(name), ..
+            } = operation
+            {
+                if producers.insert(name.as_str(), i).is_some() {
+                    return Err(DemleError::ValidationError(format!(
+                        "Edge '{}' is produced by more than one operation",
+                        name
+                    )));
+                }
+            }
+        }
+
+        let dependencies = operations
+            .iter()
+            .map(|operation| match operation {
+                MLOperation::TensorOperation { inputs, .. } => inputs
+                    .iter()
+                    .filter_map(|input| match input {
+                        TensorInput::Edge { name, .. } => Some(name),
+                        TensorInput::Stored(_) => None,
+                    })
+                    .map(|name| {
+                        producers.get(name.as_str()).copied().ok_or_else(|| {
+                            DemleError::ValidationError(format!(
+                                "Edge '{}' is not produced by any operation",
+                                name
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>(),
+                _ => Ok(Vec::new()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Kahn's algorithm, one wave at a time
+        let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut dependents = vec![Vec::new(); operations.len()];
+        for (node, deps) in dependencies.iter().enumerate() {
+            for &dep in deps {
+                dependents[dep].push(node);
+            }
+        }
+
+        let mut waves = Vec::new();
+        let mut ready: Vec<usize> = (0..operations.len())
+            .filter(|&node| remaining[node] == 0)
+            .collect();
+        let mut scheduled = 0;
+        while !ready.is_empty() {
+            scheduled += ready.len();
+            let mut next = Vec::new();
+            for &node in &ready {
+                for &dependent in &dependents[node] {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
+            }
+            next.sort_unstable();
+            waves.push(std::mem::replace(&mut ready, next));
+        }
+
+        if scheduled != operations.len() {
+            let cyclic: Vec<&str> = (0..operations.len())
+                .filter(|&node| remaining[node] > 0)
+                .map(|node| names[node].as_str())
+                .collect();
+            return Err(DemleError::ValidationError(format!(
+                "Operations form a dependency cycle: {}",
+                cyclic.join(", ")
+            )));
+        }
+
+        Ok(Self {
+            names,
+            dependencies,
+            waves,
+        })
+    }
+
+    /// Result hash of every node, by node name
+    pub fn node_hashes(&self, results: &[OperationResult]) -> Vec<(String, String)> {
+        self.names
+            .iter()
+            .cloned()
+            .zip(results.iter().map(|result| result.result_hash.clone()))
+            .collect()
+    }
+}
+
+/// Execute the operations of a graph wave by wave, running the nodes of a wave
+/// in parallel when `parallel` is set. Results are returned in operation order.
+pub fn execute_graph(
+    graph: &OperationGraph,
+    operations: &[MLOperation],
+    store: Option<&TensorStore>,
+    parallel: bool,
+) -> Result<Vec<OperationResult>> {
+    let mut edges: HashMap<String, TensorRef> = HashMap::new();
+    let mut results: Vec<Option<OperationResult>> = vec![None; operations.len()];
+
+    for wave in &graph.waves {
+        let run = |&node: &usize| execute_node(&operations[node], &edges, store);
+        let outputs: Vec<(OperationResult, Option<(String, TensorRef)>)> = if parallel {
+            wave.par_iter().map(run).collect::<Result<_>>()?
+        } else {
+            wave.iter().map(run).collect::<Result<_>>()?
+        };
+
+        for (&node, (result, edge)) in wave.iter().zip(outputs) {
+            edges.extend(edge);
+            results[node] = Some(result);
+        }
+    }
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Execute one node with its edge inputs resolved, returning the edge it
+/// produces, if any
+fn execute_node(
+    operation: &MLOperation,
+    edges: &HashMap<String, TensorRef>,
+    store: Option<&TensorStore>,
+) -> Result<(OperationResult, Option<(String, TensorRef)>)> {
+    let MLOperation::TensorOperation { op, inputs, output } = operation else {
+        return Ok((execute_ml_operation_with_store(operation, store)?, None));
+    };
+
+    let inputs = resolve_inputs(inputs, edges)?;
+    let resolved = MLOperation::TensorOperation {
+        op: op.clone(),
+        inputs: inputs.iter().cloned().map(TensorInput::Stored).collect(),
+        output: output.clone(),
+    };
+    let result = execute_ml_operation_with_store(&resolved, store)?;
+
+    let edge = match output {
+        Some(name) => {
+            let shapes: Vec<&[usize]> = inputs.iter().map(|t| t.shape.as_slice()).collect();
+            let tensor = TensorRef {
+                hash: result.result_hash.clone(),
+                shape: output_shape(op, &shapes)?,
+            };
+            Some((name.clone(), tensor))
+        }
+        None => None,
+    };
+    Ok((result, edge))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::generate_random_tensor;
+    use crate::store::{temp_store, StoredTensor};
+    use demle_core::{Activation, TensorOp};
+
+    fn edge(name: &str, shape: &[usize]) -> TensorInput {
+        TensorInput::Edge {
+            name: name.to_string(),
+            shape: shape.to_vec(),
+        }
+    }
+
+    fn tensor_op(op: TensorOp, inputs: Vec<TensorInput>, output: &str) -> MLOperation {
+        MLOperation::TensorOperation {
+            op,
+            inputs,
+            output: Some(output.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_graph_waves_and_errors() {
+        let x = TensorInput::Stored(TensorRef {
+            hash: "0".repeat(64),
+            shape: vec![2, 4],
+        });
+        let relu = TensorOp::Activation(Activation::ReLU);
+        // "sum" is listed before the branches it joins
+        let operations = vec![
+            tensor_op(
+                TensorOp::Add,
+                vec![edge("a", &[2, 4]), edge("b", &[2, 4])],
+                "sum",
+            ),
+            tensor_op(relu.clone(), vec![x.clone()], "a"),
+            MLOperation::Softmax {
+                rows: 2,
+                cols: 4,
+                log: false,
+                seed: 1,
+            },
+            tensor_op(relu.clone(), vec![x.clone()], "b"),
+        ];
+
+        let graph = OperationGraph::build(&operations).unwrap();
+        assert_eq!(graph.names, vec!["sum", "a", "#2", "b"]);
+        assert_eq!(graph.dependencies, vec![vec![1, 3], vec![], vec![], vec![]]);
+        assert_eq!(graph.waves, vec![vec![1, 2, 3], vec![0]]);
+
+        let cycle = vec![
+            tensor_op(relu.clone(), vec![edge("b", &[2, 4])], "a"),
+            tensor_op(relu.clone(), vec![edge("a", &[2, 4])], "b"),
+        ];
+        let err = OperationGraph::build(&cycle).unwrap_err().to_string();
+        assert!(err.contains("cycle: a, b"), "{}", err);
+
+        let missing = vec![tensor_op(relu.clone(), vec![edge("c", &[2, 4])], "a")];
+        assert!(OperationGraph::build(&missing).is_err());
+
+        let duplicate = vec![
+            tensor_op(relu.clone(), vec![x.clone()], "a"),
+            tensor_op(relu, vec![x], "a"),
+        ];
+        assert!(OperationGraph::build(&duplicate).is_err());
+    }
+
+    #[test]
+    fn test_execute_graph_parallel_matches_sequential() {
+        let store = temp_store("graph");
+        let x = StoredTensor::new(vec![4, 8], generate_random_tensor(&[4, 8], 1).unwrap()).unwrap();
+        let w = StoredTensor::new(vec![8, 8], generate_random_tensor(&[8, 8], 2).unwrap()).unwrap();
+        let x = TensorInput::Stored(store.put(&x).unwrap());
+        let w = TensorInput::Stored(store.put(&w).unwrap());
+
+        // Two branches from x joined by an add, then a softmax
+        let operations = vec![
+            tensor_op(
+                TensorOp::Softmax { log: false },
+                vec![edge("sum", &[4, 8])],
+                "probs",
+            ),
+            tensor_op(
+                TensorOp::Add,
+                vec![edge("left", &[4, 8]), edge("right", &[4, 8])],
+                "sum",
+            ),
+            tensor_op(TensorOp::MatMul, vec![x.clone(), w], "left"),
+            tensor_op(TensorOp::Activation(Activation::GELU), vec![x], "right"),
+        ];
+        let graph = OperationGraph::build(&operations).unwrap();
+        assert_eq!(graph.waves, vec![vec![2, 3], vec![1], vec![0]]);
+
+        let parallel = execute_graph(&graph, &operations, Some(&store), true).unwrap();
+        let sequential = execute_graph(&graph, &operations, Some(&store), false).unwrap();
+        let hashes = graph.node_hashes(&parallel);
+        assert_eq!(hashes, graph.node_hashes(&sequential));
+        assert_eq!(hashes[0].0, "probs");
+        assert_eq!(hashes[0].1, parallel[0].result_hash);
+        assert!(store.contains(&hashes[0].1));
+
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+}
@@ -0,0 +1,484 @@
+use crate::convolution::{conv2d_flops, conv2d_fp8};
+use crate::fp8::FP8;
+use crate::gemm::{gemm_flops, gemm_fp8_fused, Epilogue};
+use crate::operations::{
+    apply_activation, flops_product, flops_sum, hash_tensor, ActivationType, OperationOutput,
+};
+use crate::pooling::{pool2d, pooling2d_flops};
+use crate::quantize::TensorData;
+use crate::store::StoredTensor;
+use crate::tensor_file::{read_safetensors, NamedTensor};
+use demle_core::proof::Proof;
+use demle_core::{DemleError, PoolType, Result};
+use rand::{Rng, SeedableRng};
+
+/// Hand-set weights of the synthetic stripe benchmark
+const BENCHMARK_MODEL: &[u8] = include_bytes!("../models/stripes_cnn.safetensors");
+/// Generated images of the synthetic stripe benchmark
+const BENCHMARK_DATASET: &[u8] = include_bytes!("../models/stripes_test.safetensors");
+
+/// Side length of the benchmark images
+const IMAGE_SIZE: usize = 8;
+/// Classes of the benchmark images: stripe orientation
+pub const BENCHMARK_CLASSES: [&str; 4] = ["horizontal", "vertical", "diagonal", "anti-diagonal"];
+
+/// LeNet-style image classifier: a same-padded convolution with bias and ReLU,
+/// 2×2 max pooling, then a fully connected layer producing class logits.
+/// Tensors are named `conv.weight` [out_ch, in_ch, kh, kw], `conv.bias`
+/// [out_ch], `fc.weight` [classes, features] and `fc.bias` [classes], following
+/// PyTorch's layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CnnModel {
+    conv_weight: StoredTensor,
+    conv_bias: Vec<FP8>,
+    /// Fully connected weight transposed to [features, classes] for the GEMM
+    fc_weight: Vec<FP8>,
+    fc_bias: Vec<FP8>,
+    num_features: usize,
+    num_classes: usize,
+}
+
+impl CnnModel {
+    /// Build the model from its named tensors, checking that their shapes agree
+    pub fn from_tensors(tensors: &[NamedTensor]) -> Result<Self> {
+        let conv_weight = find_tensor(tensors, "conv.weight")?.to_stored()?;
+        let conv_bias = find_tensor(tensors, "conv.bias")?.to_stored()?;
+        let fc_weight = find_tensor(tensors, "fc.weight")?.to_stored()?;
+        let fc_bias = find_tensor(tensors, "fc.bias")?.to_stored()?;
+
+        let out_ch = match conv_weight.shape[..] {
+            [out_ch, in_ch, kh, kw] if out_ch > 0 && in_ch > 0 && kh % 2 == 1 && kw % 2 == 1 => {
+                out_ch
+            }
+            _ => {
+                return Err(DemleError::ValidationError(format!(
+                    "conv.weight must be [out_ch, in_ch, kh, kw] with non-zero channels and odd kernel sizes, got {:?}",
+                    conv_weight.shape
+                )))
+            }
+        };
+        let (num_classes, num_features) = match fc_weight.shape[..] {
+            [classes, features] if classes > 0 && features > 0 && features % out_ch == 0 => {
+                (classes, features)
+            }
+            _ => {
+                return Err(DemleError::ValidationError(format!(
+                    "fc.weight must be [classes, features] with non-zero classes and features a multiple of {}, got {:?}",
+                    out_ch, fc_weight.shape
+                )))
+            }
+        };
+        if conv_bias.shape != [out_ch] || fc_bias.shape != [num_classes] {
+            return Err(DemleError::ValidationError(format!(
+                "Bias shapes {:?} and {:?} do not match {} channels and {} classes",
+                conv_bias.shape, fc_bias.shape, out_ch, num_classes
+            )));
+        }
+        let mut transposed = vec![FP8::zero(); num_features * num_classes];
+        for class in 0..num_classes {
+            for feature in 0..num_features {
+                transposed[feature * num_classes + class] =
+                    fc_weight.data[class * num_features + feature];
+            }
+        }
+
+        Ok(Self {
+            conv_weight,
+            conv_bias: conv_bias.data,
+            fc_weight: transposed,
+            fc_bias: fc_bias.data,
+            num_features,
+            num_classes,
+        })
+    }
+
+    pub fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    fn kernel_shape(&self) -> (usize, usize, usize, usize) {
+        let shape = &self.conv_weight.shape;
+        (shape[0], shape[1], shape[2], shape[3])
+    }
+
+    fn padding(&self) -> (usize, usize) {
+        let (_, _, kh, kw) = self.kernel_shape();
+        (kh / 2, kw / 2)
+    }
+
+    /// Check that images of shape (batch, channels, height, width) fit the model
+    fn check_input(&self, input_shape: (usize, usize, usize, usize)) -> Result<()> {
+        let (_, channels, height, width) = input_shape;
+        let (out_ch, in_ch, _, _) = self.kernel_shape();
+        if channels != in_ch || (height / 2) * (width / 2) * out_ch != self.num_features {
+            return Err(DemleError::ValidationError(format!(
+                "Images of {}×{}×{} do not fit a model with {} input channels and {} features",
+                channels, height, width, in_ch, self.num_features
+            )));
+        }
+        Ok(())
+    }
+
+    /// Class logits [batch, classes] of a batch of images
+    pub fn forward(
+        &self,
+        images: &[FP8],
+        input_shape: (usize, usize, usize, usize),
+    ) -> Result<Vec<FP8>> {
+        self.check_input(input_shape)?;
+        let (batch, _, height, width) = input_shape;
+        let kernel_shape = self.kernel_shape();
+        let out_ch = kernel_shape.0;
+
+        let mut features = conv2d_fp8(
+            images,
+            &self.conv_weight.data,
+            input_shape,
+            kernel_shape,
+            (1, 1),
+            self.padding(),
+        );
+        let plane = height * width;
+        for (i, value) in features.iter_mut().enumerate() {
+            *value = *value + self.conv_bias[(i / plane) % out_ch];
+        }
+        let features = apply_activation(&features, ActivationType::ReLU);
+        let pooled = pool2d(
+            &features,
+            (batch, out_ch, height, width),
+            PoolType::Max,
+            (2, 2),
+            (2, 2),
+            (0, 0),
+        )?;
+
+        let epilogue = Epilogue {
+            bias: Some(&self.fc_bias),
+            ..Default::default()
+        };
+        Ok(gemm_fp8_fused(
+            &pooled,
+            &self.fc_weight,
+            batch,
+            self.num_features,
+            self.num_classes,
+            &epilogue,
+        ))
+    }
+
+    /// FLOP count of a forward pass over a batch
+    pub fn forward_flops(&self, input_shape: (usize, usize, usize, usize)) -> Result<u64> {
+        let (batch, _, height, width) = input_shape;
+        let kernel_shape = self.kernel_shape();
+        let conv_shape = (batch, kernel_shape.0, height, width);
+
+        flops_sum(&[
+            conv2d_flops(input_shape, kernel_shape, (1, 1), self.padding())?,
+            flops_product(&[2, batch, kernel_shape.0, height, width])?,
+            pooling2d_flops(conv_shape, PoolType::Max, (2, 2), (2, 2), (0, 0))?,
+            gemm_flops((batch, self.num_features, self.num_classes))?,
+            flops_product(&[batch, self.num_classes])?,
+        ])
+    }
+}
+
+/// Images [count, channels, height, width] with one class label per image
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledImages {
+    pub images: StoredTensor,
+    pub labels: Vec<usize>,
+}
+
+impl LabeledImages {
+    /// Read the `images` and integer `labels` tensors of a dataset
+    pub fn from_tensors(tensors: &[NamedTensor]) -> Result<Self> {
+        let images = find_tensor(tensors, "images")?.to_stored()?;
+        let labels = find_tensor(tensors, "labels")?;
+
+        let labels: Vec<usize> = match &labels.data {
+            TensorData::INT8(values) if values.iter().all(|&v| v >= 0) => {
+                values.iter().map(|&v| v as usize).collect()
+            }
+            _ => {
+                return Err(DemleError::ValidationError(
+                    "labels must be a non-negative INT8 tensor".to_string(),
+                ))
+            }
+        };
+        if images.shape.len() != 4 || images.shape[0] != labels.len() {
+            return Err(DemleError::ValidationError(format!(
+                "images must be [count, channels, height, width] with {} labels, got {:?}",
+                labels.len(),
+                images.shape
+            )));
+        }
+        Ok(Self { images, labels })
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Outcome of running a model over a labeled dataset
+#[derive(Debug, Clone)]
+pub struct InferenceReport {
+    /// Class logits [count, classes]
+    pub logits: StoredTensor,
+    /// Highest-scoring class of every image
+    pub predictions: Vec<usize>,
+    /// Fraction of predictions matching the labels
+    pub accuracy: f64,
+    /// Logits hash, FLOP count, predictions hash and accuracy
+    pub output: OperationOutput,
+}
+
+/// Run the model over the dataset in batches of `batch_size` images. Every
+/// image is computed independently, so the logits do not depend on the batch
+/// size.
+pub fn run_inference(
+    model: &CnnModel,
+    dataset: &LabeledImages,
+    batch_size: usize,
+) -> Result<InferenceReport> {
+    if batch_size == 0 {
+        return Err(DemleError::ValidationError(
+            "Batch size must be positive".to_string(),
+        ));
+    }
+    let (count, channels, height, width) = match dataset.images.shape[..] {
+        [count, channels, height, width] => (count, channels, height, width),
+        _ => unreachable!("dataset images are validated as 4D"),
+    };
+    let image_len = channels * height * width;
+
+    let mut logits = Vec::with_capacity(count * model.num_classes);
+    let mut flops = 0;
+    for start in (0..count).step_by(batch_size) {
+        let batch = batch_size.min(count - start);
+        let input_shape = (batch, channels, height, width);
+        let images = &dataset.images.data[start * image_len..(start + batch) * image_len];
+        logits.extend(model.forward(images, input_shape)?);
+        flops = flops_sum(&[flops, model.forward_flops(input_shape)?])?;
+    }
+
+    let predictions: Vec<usize> = logits.chunks(model.num_classes).map(argmax).collect();
+    let correct = predictions
+        .iter()
+        .zip(&dataset.labels)
+        .filter(|(prediction, label)| prediction == label)
+        .count();
+    let accuracy = if count == 0 {
+        0.0
+    } else {
+        correct as f64 / count as f64
+    };
+
+    let prediction_bytes: Vec<u8> = predictions
+        .iter()
+        .flat_map(|&p| (p as u32).to_le_bytes())
+        .collect();
+    let output = OperationOutput {
+        result_hash: hash_tensor(&logits),
+        flops,
+        intermediate_hashes: vec![(
+            "predictions".to_string(),
+            Proof::hash_operation_result(&prediction_bytes),
+        )],
+        metrics: vec![("accuracy".to_string(), accuracy)],
+    };
+
+    Ok(InferenceReport {
+        logits: StoredTensor::new(vec![count, model.num_classes], logits)?,
+        predictions,
+        accuracy,
+        output,
+    })
+}
+
+/// Index of the largest logit, the first one on ties
+fn argmax(logits: &[FP8]) -> usize {
+    let mut best = 0;
+    for (i, logit) in logits.iter().enumerate() {
+        if logit.to_f32() > logits[best].to_f32() {
+            best = i;
+        }
+    }
+    best
+}
+
+fn find_tensor<'a>(tensors: &'a [NamedTensor], name: &str) -> Result<&'a NamedTensor> {
+    tensors
+        .iter()
+        .find(|tensor| tensor.name == name)
+        .ok_or_else(|| DemleError::ValidationError(format!("Missing tensor '{}'", name)))
+}
+
+/// Weights of the synthetic benchmark, which classifies the orientation of a
+/// stripe in an 8×8 image. They are set by hand, not trained, so the benchmark
+/// exercises the inference pipeline rather than a real model; load trained
+/// weights with [`CnnModel::from_tensors`] for that.
+pub fn benchmark_model() -> Result<Vec<NamedTensor>> {
+    read_safetensors(BENCHMARK_MODEL)
+}
+
+/// Labeled images of the synthetic benchmark
+pub fn benchmark_dataset() -> Result<Vec<NamedTensor>> {
+    read_safetensors(BENCHMARK_DATASET)
+}
+
+/// Hand-set weights of the synthetic benchmark. The convolution holds one
+/// zero-sum line detector per class and the fully connected layer sums the
+/// pooled responses of each detector.
+pub fn stripe_detector_model() -> Result<Vec<NamedTensor>> {
+    let on = 1.0;
+    let off = -0.5;
+    let detectors: [[usize; 3]; 4] = [
+        [3, 4, 5], // horizontal: middle row
+        [1, 4, 7], // vertical: middle column
+        [0, 4, 8], // diagonal
+        [2, 4, 6], // anti-diagonal
+    ];
+    let conv_weight: Vec<f32> = detectors
+        .iter()
+        .flat_map(|taps| (0..9).map(move |i| if taps.contains(&i) { on } else { off }))
+        .collect();
+
+    let classes = BENCHMARK_CLASSES.len();
+    let pooled = (IMAGE_SIZE / 2) * (IMAGE_SIZE / 2);
+    let fc_weight: Vec<f32> = (0..classes)
+        .flat_map(|class| {
+            (0..classes * pooled).map(move |feature| (feature / pooled == class) as u8 as f32)
+        })
+        .collect();
+
+    let fp8 =
+        |values: Vec<f32>| TensorData::FP8E4M3(values.into_iter().map(FP8::from_f32).collect());
+    Ok(vec![
+        NamedTensor::new("conv.bias", vec![classes], fp8(vec![-1.0; classes]))?,
+        NamedTensor::new("conv.weight", vec![classes, 1, 3, 3], fp8(conv_weight))?,
+        NamedTensor::new("fc.bias", vec![classes], fp8(vec![0.0; classes]))?,
+        NamedTensor::new("fc.weight", vec![classes, classes * pooled], fp8(fc_weight))?,
+    ])
+}
+
+/// Labeled images for the synthetic benchmark: one stripe of full intensity
+/// per image over uniform background noise, `per_class` images of each class
+pub fn stripe_dataset(per_class: usize, seed: u64) -> Result<Vec<NamedTensor>> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let classes = BENCHMARK_CLASSES.len();
+    let mut images = Vec::with_capacity(classes * per_class * IMAGE_SIZE * IMAGE_SIZE);
+    let mut labels = Vec::with_capacity(classes * per_class);
+
+    for i in 0..classes * per_class {
+        let class = i % classes;
+        let offset = rng.random_range(0..IMAGE_SIZE) as isize;
+        // Diagonal stripes stay within 3 pixels of the main diagonals
+        let shift = offset % 7 - 3;
+        let last = IMAGE_SIZE as isize - 1;
+        for row in 0..IMAGE_SIZE as isize {
+            for col in 0..IMAGE_SIZE as isize {
+                let on_stripe = match class {
+                    0 => row == offset,
+                    1 => col == offset,
+                    2 => col - row == shift,
+                    _ => col + row == last + shift,
+                };
+                let value = if on_stripe {
+                    1.0
+                } else {
+                    rng.random::<f32>() * 0.25
+                };
+                images.push(FP8::from_f32(value));
+            }
+        }
+        labels.push(class as i8);
+    }
+
+    Ok(vec![
+        NamedTensor::new(
+            "images",
+            vec![labels.len(), 1, IMAGE_SIZE, IMAGE_SIZE],
+            TensorData::FP8E4M3(images),
+        )?,
+        NamedTensor::new("labels", vec![labels.len()], TensorData::INT8(labels))?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor_file::write_safetensors;
+
+    #[test]
+    fn test_benchmark_files_match_generators() {
+        assert_eq!(benchmark_model().unwrap(), stripe_detector_model().unwrap());
+        assert_eq!(
+            benchmark_dataset().unwrap(),
+            stripe_dataset(16, 40).unwrap()
+        );
+        assert_eq!(
+            write_safetensors(&stripe_detector_model().unwrap()).unwrap(),
+            BENCHMARK_MODEL
+        );
+    }
+
+    #[test]
+    fn test_synthetic_benchmark() {
+        let model = CnnModel::from_tensors(&benchmark_model().unwrap()).unwrap();
+        let dataset = LabeledImages::from_tensors(&benchmark_dataset().unwrap()).unwrap();
+        assert_eq!(dataset.len(), 64);
+
+        let report = run_inference(&model, &dataset, 16).unwrap();
+        assert_eq!(report.logits.shape, vec![64, 4]);
+        assert!(report.accuracy >= 0.95, "accuracy {}", report.accuracy);
+        assert_eq!(
+            report.output.metrics[0],
+            ("accuracy".to_string(), report.accuracy)
+        );
+        assert_eq!(
+            report.output.flops,
+            model
+                .forward_flops((64, 1, IMAGE_SIZE, IMAGE_SIZE))
+                .unwrap()
+        );
+
+        // Batching changes neither the logits nor the predictions
+        let uneven = run_inference(&model, &dataset, 7).unwrap();
+        assert_eq!(uneven.output.result_hash, report.output.result_hash);
+        assert_eq!(uneven.predictions, report.predictions);
+        assert!(run_inference(&model, &dataset, 0).is_err());
+    }
+
+    #[test]
+    fn test_model_validation() {
+        let mut tensors = stripe_detector_model().unwrap();
+        tensors.retain(|tensor| tensor.name != "fc.bias");
+        assert!(CnnModel::from_tensors(&tensors).is_err());
+
+        // No output channels
+        let zeros = |name: &str, shape: Vec<usize>| {
+            let data = vec![FP8::zero(); shape.iter().product()];
+            NamedTensor::new(name, shape, TensorData::FP8E4M3(data)).unwrap()
+        };
+        let tensors = vec![
+            zeros("conv.bias", vec![0]),
+            zeros("conv.weight", vec![0, 1, 3, 3]),
+            zeros("fc.bias", vec![4]),
+            zeros("fc.weight", vec![4, 0]),
+        ];
+        assert!(CnnModel::from_tensors(&tensors).is_err());
+
+        let model = CnnModel::from_tensors(&stripe_detector_model().unwrap()).unwrap();
+        let images = vec![FP8::zero(); 2 * 6 * 6];
+        assert!(model.forward(&images, (2, 1, 6, 6)).is_err());
+
+        let mut dataset = stripe_dataset(1, 1).unwrap();
+        dataset[1].data = TensorData::INT8(vec![0, 1, -1, 3]);
+        assert!(LabeledImages::from_tensors(&dataset).is_err());
+    }
+}
@@ -1,19 +1,56 @@
 pub mod attention;
+pub mod backward;
 pub mod batch_norm;
+pub mod challenge;
+pub mod conformance;
 pub mod convolution;
+pub mod cost;
+pub mod embedding;
+pub mod feed_forward;
 pub mod fp8;
 pub mod gemm;
+pub mod graph;
+pub mod inference;
+pub mod moe;
+pub mod npy;
+pub mod onnx;
 pub mod operations;
+pub mod optimizer;
+pub mod pooling;
+pub mod quantize;
+pub mod softmax;
+pub mod sparse;
+pub mod store;
+pub mod tensor_file;
+pub mod tensor_ops;
+pub mod training;
+pub mod transformer;
+pub mod verify;
 
-use demle_core::{MLOperation, OperationResult, Result};
+use demle_core::{DemleError, MLOperation, OperationResult, Result};
+use std::collections::HashMap;
 use std::time::Instant;
 
-pub use fp8::FP8;
+pub use fp8::{FP8, FP8E5M2};
+pub use operations::OperationTensors;
+pub use store::{StoredTensor, TensorStore};
 
 /// Execute a machine learning operation and return timing and result information
 pub fn execute_ml_operation(operation: &MLOperation) -> Result<OperationResult> {
+    execute_ml_operation_with_store(operation, None)
+}
+
+/// Execute a machine learning operation, loading stored input tensors from and
+/// writing outputs to `store`. Operations on stored tensors fail without one.
+pub fn execute_ml_operation_with_store(
+    operation: &MLOperation,
+    store: Option<&TensorStore>,
+) -> Result<OperationResult> {
     let start = Instant::now();
 
+    let mut intermediate_hashes = Vec::new();
+    let mut metrics = Vec::new();
+
     let (result_hash, flops) = match operation {
         MLOperation::MatrixMultiply { dimensions, seed } => gemm::execute_gemm(*dimensions, *seed)?,
         MLOperation::Convolution2D {
@@ -35,6 +72,197 @@ pub fn execute_ml_operation(operation: &MLOperation) -> Result<OperationResult>
             epsilon,
             seed,
         } => batch_norm::execute_batch_norm(*shape, *epsilon, *seed)?,
+        MLOperation::TransformerBlock {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            d_ff,
+            feed_forward,
+            seed,
+        } => {
+            let output = transformer::execute_transformer_block(
+                *batch_size,
+                *seq_length,
+                *d_model,
+                *num_heads,
+                *d_ff,
+                *feed_forward,
+                *seed,
+            )?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::GatedFeedForward {
+            tokens,
+            d_model,
+            d_ff,
+            activation,
+            seed,
+        } => {
+            feed_forward::execute_gated_feed_forward(*tokens, *d_model, *d_ff, *activation, *seed)?
+        }
+        MLOperation::Pooling2D {
+            input_shape,
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+            seed,
+        } => pooling::execute_pooling2d(
+            *input_shape,
+            *pool_type,
+            *kernel_size,
+            *stride,
+            *padding,
+            *seed,
+        )?,
+        MLOperation::EmbeddingLookup {
+            vocab_size,
+            embedding_dim,
+            num_tokens,
+            seed,
+        } => embedding::execute_embedding_lookup(*vocab_size, *embedding_dim, *num_tokens, *seed)?,
+        MLOperation::Softmax {
+            rows,
+            cols,
+            log,
+            seed,
+        } => softmax::execute_softmax(*rows, *cols, *log, *seed)?,
+        MLOperation::MatrixMultiplyBackward { dimensions, seed } => {
+            let output = backward::execute_gemm_backward(*dimensions, *seed)?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::Convolution2DBackward {
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+            seed,
+        } => {
+            let output = backward::execute_conv2d_backward(
+                *input_shape,
+                *kernel_shape,
+                *stride,
+                *padding,
+                *seed,
+            )?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::MultiHeadAttentionBackward {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            seed,
+        } => {
+            let output = backward::execute_attention_backward(
+                *batch_size,
+                *seq_length,
+                *d_model,
+                *num_heads,
+                *seed,
+            )?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::OptimizerStep {
+            num_params,
+            optimizer,
+            master_weights,
+            seed,
+        } => {
+            let output =
+                optimizer::execute_optimizer_step(*num_params, optimizer, *master_weights, *seed)?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::TrainingStep {
+            batch_size,
+            input_dim,
+            hidden_dim,
+            num_classes,
+            optimizer,
+            master_weights,
+            seed,
+        } => {
+            let shape = training::MlpShape {
+                batch_size: *batch_size,
+                input_dim: *input_dim,
+                hidden_dim: *hidden_dim,
+                num_classes: *num_classes,
+            };
+            let output = training::execute_training_step(shape, optimizer, *master_weights, *seed)?;
+            intermediate_hashes = output.intermediate_hashes;
+            metrics = output.metrics;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::BatchedMatrixMultiply {
+            batch_size,
+            dimensions,
+            a_operand,
+            b_operand,
+            transpose_a,
+            transpose_b,
+            seed,
+        } => gemm::execute_batched_gemm(
+            &gemm::BatchedGemm {
+                batch_size: *batch_size,
+                dimensions: *dimensions,
+                a_operand: *a_operand,
+                b_operand: *b_operand,
+                transpose_a: *transpose_a,
+                transpose_b: *transpose_b,
+            },
+            *seed,
+        )?,
+        MLOperation::SparseMatrixMultiply { dimensions, seed } => {
+            sparse::execute_sparse_gemm(*dimensions, *seed)?
+        }
+        MLOperation::MixtureOfExperts {
+            tokens,
+            d_model,
+            d_ff,
+            num_experts,
+            top_k,
+            capacity_factor,
+            seed,
+        } => {
+            let config = moe::MoeConfig {
+                tokens: *tokens,
+                d_model: *d_model,
+                d_ff: *d_ff,
+                num_experts: *num_experts,
+                top_k: *top_k,
+                capacity_factor: *capacity_factor,
+            };
+            let output = moe::execute_mixture_of_experts(&config, *seed)?;
+            intermediate_hashes = output.intermediate_hashes;
+            metrics = output.metrics;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::Quantize {
+            num_elements,
+            source,
+            target,
+            seed,
+        } => {
+            let output = quantize::execute_quantize(*num_elements, *source, *target, *seed)?;
+            intermediate_hashes = output.intermediate_hashes;
+            metrics = output.metrics;
+            (output.result_hash, output.flops)
+        }
+        MLOperation::TensorOperation { op, inputs, .. } => {
+            let store = store.ok_or_else(|| {
+                DemleError::ValidationError(format!("{} needs a tensor store", operation))
+            })?;
+            let inputs = tensor_ops::resolve_inputs(inputs, &HashMap::new())?;
+            let output = tensor_ops::execute_tensor_operation(op, &inputs, store)?;
+            intermediate_hashes = output.intermediate_hashes;
+            (output.result_hash, output.flops)
+        }
     };
 
     let execution_time_ms = start.elapsed().as_millis() as u64;
@@ -43,12 +271,214 @@ pub fn execute_ml_operation(operation: &MLOperation) -> Result<OperationResult>
         result_hash,
         flops,
         execution_time_ms,
+        intermediate_hashes,
+        metrics,
     })
 }
 
+/// Seeded operands and committed output of an operation on the CPU kernels.
+/// Tensor operations fail, as they read their inputs from a store.
+pub fn operation_tensors(operation: &MLOperation) -> Result<OperationTensors> {
+    operation_tensors_with_store(operation, None)
+}
+
+/// Operands and committed output of an operation on the CPU kernels, loading
+/// the inputs of tensor operations from `store`
+pub fn operation_tensors_with_store(
+    operation: &MLOperation,
+    store: Option<&TensorStore>,
+) -> Result<OperationTensors> {
+    match operation {
+        MLOperation::MatrixMultiply { dimensions, seed } => gemm::gemm_tensors(*dimensions, *seed),
+        MLOperation::Convolution2D {
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+            seed,
+        } => convolution::conv2d_tensors(*input_shape, *kernel_shape, *stride, *padding, *seed),
+        MLOperation::MultiHeadAttention {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            seed,
+        } => attention::attention_tensors(*batch_size, *seq_length, *d_model, *num_heads, *seed),
+        MLOperation::BatchNormalization {
+            shape,
+            epsilon,
+            seed,
+        } => batch_norm::batch_norm_tensors(*shape, *epsilon, *seed),
+        MLOperation::TransformerBlock {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            d_ff,
+            feed_forward,
+            seed,
+        } => transformer::transformer_block_tensors(
+            *batch_size,
+            *seq_length,
+            *d_model,
+            *num_heads,
+            *d_ff,
+            *feed_forward,
+            *seed,
+        ),
+        MLOperation::GatedFeedForward {
+            tokens,
+            d_model,
+            d_ff,
+            activation,
+            seed,
+        } => feed_forward::gated_feed_forward_tensors(*tokens, *d_model, *d_ff, *activation, *seed),
+        MLOperation::Pooling2D {
+            input_shape,
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+            seed,
+        } => pooling::pooling2d_tensors(
+            *input_shape,
+            *pool_type,
+            *kernel_size,
+            *stride,
+            *padding,
+            *seed,
+        ),
+        MLOperation::EmbeddingLookup {
+            vocab_size,
+            embedding_dim,
+            num_tokens,
+            seed,
+        } => embedding::embedding_lookup_tensors(*vocab_size, *embedding_dim, *num_tokens, *seed),
+        MLOperation::Softmax {
+            rows,
+            cols,
+            log,
+            seed,
+        } => softmax::softmax_tensors(*rows, *cols, *log, *seed),
+        MLOperation::MatrixMultiplyBackward { dimensions, seed } => {
+            backward::gemm_backward_tensors(*dimensions, *seed)
+        }
+        MLOperation::Convolution2DBackward {
+            input_shape,
+            kernel_shape,
+            stride,
+            padding,
+            seed,
+        } => {
+            backward::conv2d_backward_tensors(*input_shape, *kernel_shape, *stride, *padding, *seed)
+        }
+        MLOperation::MultiHeadAttentionBackward {
+            batch_size,
+            seq_length,
+            d_model,
+            num_heads,
+            seed,
+        } => backward::attention_backward_tensors(
+            *batch_size,
+            *seq_length,
+            *d_model,
+            *num_heads,
+            *seed,
+        ),
+        MLOperation::OptimizerStep {
+            num_params,
+            optimizer,
+            master_weights,
+            seed,
+        } => optimizer::optimizer_step_tensors(*num_params, optimizer, *master_weights, *seed),
+        MLOperation::TrainingStep {
+            batch_size,
+            input_dim,
+            hidden_dim,
+            num_classes,
+            optimizer,
+            master_weights,
+            seed,
+        } => {
+            let shape = training::MlpShape {
+                batch_size: *batch_size,
+                input_dim: *input_dim,
+                hidden_dim: *hidden_dim,
+                num_classes: *num_classes,
+            };
+            training::training_step_tensors(shape, optimizer, *master_weights, *seed)
+        }
+        MLOperation::BatchedMatrixMultiply {
+            batch_size,
+            dimensions,
+            a_operand,
+            b_operand,
+            transpose_a,
+            transpose_b,
+            seed,
+        } => gemm::batched_gemm_tensors(
+            &gemm::BatchedGemm {
+                batch_size: *batch_size,
+                dimensions: *dimensions,
+                a_operand: *a_operand,
+                b_operand: *b_operand,
+                transpose_a: *transpose_a,
+                transpose_b: *transpose_b,
+            },
+            *seed,
+        ),
+        MLOperation::SparseMatrixMultiply { dimensions, seed } => {
+            sparse::sparse_gemm_tensors(*dimensions, *seed)
+        }
+        MLOperation::MixtureOfExperts {
+            tokens,
+            d_model,
+            d_ff,
+            num_experts,
+            top_k,
+            capacity_factor,
+            seed,
+        } => moe::mixture_of_experts_tensors(
+            &moe::MoeConfig {
+                tokens: *tokens,
+                d_model: *d_model,
+                d_ff: *d_ff,
+                num_experts: *num_experts,
+                top_k: *top_k,
+                capacity_factor: *capacity_factor,
+            },
+            *seed,
+        ),
+        MLOperation::Quantize {
+            num_elements,
+            source,
+            target,
+            seed,
+        } => quantize::quantize_tensors(*num_elements, *source, *target, *seed),
+        MLOperation::TensorOperation { op, inputs, .. } => {
+            let store = store.ok_or_else(|| {
+                DemleError::ValidationError(format!("{} needs a tensor store", operation))
+            })?;
+            let inputs = tensor_ops::resolve_inputs(inputs, &HashMap::new())?;
+            tensor_ops::tensor_operation_tensors(op, &inputs, store)
+        }
+    }
+}
+
 /// Execute a complete work unit (sequence of ML operations)
 pub fn execute_work_unit(operations: &[MLOperation]) -> Result<Vec<OperationResult>> {
-    operations.iter().map(execute_ml_operation).collect()
+    execute_work_unit_with_store(operations, None)
+}
+
+/// Execute a work unit against a tensor store. Tensor operations that name
+/// their output feed it to operations reading that edge; independent branches
+/// of the dependency graph run in parallel.
+pub fn execute_work_unit_with_store(
+    operations: &[MLOperation],
+    store: Option<&TensorStore>,
+) -> Result<Vec<OperationResult>> {
+    let graph = graph::OperationGraph::build(operations)?;
+    graph::execute_graph(&graph, operations, store, true)
 }
 
 /// Calculate total FLOPS from a list of operation results
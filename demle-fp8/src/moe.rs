@@ -0,0 +1,561 @@
+use crate::fp8::FP8;
+use crate::gemm::{gemm_flops, gemm_fp8_fused, Epilogue};
+use crate::operations::{
+    flops_product, flops_sum, generate_random_tensor, hash_tensor, ActivationType, OperationOutput,
+    OperationTensors,
+};
+use crate::softmax::softmax_flops;
+use demle_core::{proof::Proof, DemleError, Result};
+
+/// Shape of a mixture-of-experts layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoeConfig {
+    pub tokens: usize,
+    pub d_model: usize,
+    pub d_ff: usize,
+    pub num_experts: usize,
+    /// Experts each token is routed to
+    pub top_k: usize,
+    /// Expert capacity as a multiple of an even share of the routed tokens
+    pub capacity_factor: f32,
+}
+
+impl MoeConfig {
+    /// Check the dimensions, routing and capacity factor
+    pub fn validate(&self) -> Result<()> {
+        if self.tokens == 0 || self.d_model == 0 || self.d_ff == 0 || self.num_experts == 0 {
+            return Err(DemleError::ValidationError(
+                "Mixture-of-experts dimensions must be non-zero".to_string(),
+            ));
+        }
+        if self.top_k == 0 || self.top_k > self.num_experts {
+            return Err(DemleError::ValidationError(format!(
+                "Cannot route to top-{} of {} experts",
+                self.top_k, self.num_experts
+            )));
+        }
+        if !(self.capacity_factor.is_finite() && self.capacity_factor > 0.0) {
+            return Err(DemleError::ValidationError(format!(
+                "Invalid capacity factor {}",
+                self.capacity_factor
+            )));
+        }
+        self.slot_buffer_len()?;
+        Ok(())
+    }
+
+    /// Token slots per expert: ceil(capacity_factor * tokens * top_k / num_experts),
+    /// at most the tokens * top_k choices there are to dispatch
+    pub fn capacity(&self) -> usize {
+        let choices = self.tokens.saturating_mul(self.top_k);
+        let even_share = choices as f32 / self.num_experts as f32;
+        ((self.capacity_factor * even_share).ceil() as usize).min(choices)
+    }
+
+    /// Elements of one expert's capacity-sized batch, rejecting layers whose
+    /// slot buffers would overflow
+    fn slot_buffer_len(&self) -> Result<usize> {
+        self.capacity()
+            .checked_mul(self.d_model)
+            .filter(|len| len.checked_mul(self.num_experts).is_some())
+            .ok_or_else(|| {
+                DemleError::ValidationError(format!(
+                    "Mixture-of-experts slot buffers of {} experts x {} slots x {} overflow",
+                    self.num_experts,
+                    self.capacity(),
+                    self.d_model
+                ))
+            })
+    }
+}
+
+/// One routing choice of a token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteChoice {
+    pub expert: usize,
+    /// Combine weight, renormalized over the token's top-k choices
+    pub weight: f32,
+    /// Expert slot the token was dispatched to, or None if the expert was full
+    pub slot: Option<usize>,
+}
+
+/// Router decisions for every token and the resulting load statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Routing {
+    /// top_k choices per token, in descending router probability
+    pub choices: Vec<Vec<RouteChoice>>,
+    /// Mean router probability of each expert
+    pub mean_probs: Vec<f32>,
+    /// Tokens routed to each expert, including those dropped at capacity
+    pub routed: Vec<usize>,
+    /// Tokens dispatched to each expert
+    pub load: Vec<usize>,
+}
+
+impl Routing {
+    /// Canonical encoding of the routing decisions: per token and choice, the
+    /// expert (u32 LE), combine weight (f32 LE) and slot (u32 LE, u32::MAX if dropped)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for choice in self.choices.iter().flatten() {
+            bytes.extend((choice.expert as u32).to_le_bytes());
+            bytes.extend(choice.weight.to_le_bytes());
+            bytes.extend(choice.slot.map_or(u32::MAX, |s| s as u32).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Switch-Transformer auxiliary loss: num_experts * sum_i(f_i * P_i), where
+    /// f_i is the fraction of routed choices and P_i the mean probability of
+    /// expert i. Equals 1.0 for perfectly balanced routing.
+    pub fn load_balance_loss(&self) -> f32 {
+        let total: usize = self.routed.iter().sum();
+        let num_experts = self.routed.len() as f32;
+        num_experts
+            * self
+                .routed
+                .iter()
+                .zip(&self.mean_probs)
+                .map(|(&routed, &p)| routed as f32 / total as f32 * p)
+                .sum::<f32>()
+    }
+
+    /// Fraction of routing choices dropped because their expert was full
+    pub fn drop_rate(&self) -> f32 {
+        let total: usize = self.routed.iter().sum();
+        let dispatched: usize = self.load.iter().sum();
+        (total - dispatched) as f32 / total as f32
+    }
+}
+
+/// Route tokens with a linear router, softmax and top-k selection.
+///
+/// Router logits are accumulated in f32. Ties between equal probabilities go
+/// to the lower expert index. Slots are assigned rank by rank (every token's
+/// first choice before any second choice), in token order, until an expert
+/// reaches capacity.
+pub fn route_tokens(input: &[FP8], router: &[FP8], config: &MoeConfig) -> Routing {
+    let MoeConfig {
+        tokens,
+        d_model,
+        num_experts,
+        top_k,
+        ..
+    } = *config;
+
+    let mut mean_probs = vec![0.0f32; num_experts];
+    let mut ranked: Vec<Vec<(usize, f32)>> = Vec::with_capacity(tokens);
+
+    for t in 0..tokens {
+        let x = &input[t * d_model..(t + 1) * d_model];
+        let logits: Vec<f32> = (0..num_experts)
+            .map(|e| {
+                x.iter()
+                    .enumerate()
+                    .map(|(i, v)| v.to_f32() * router[i * num_experts + e].to_f32())
+                    .sum()
+            })
+            .collect();
+
+        let max = logits.iter().fold(f32::NEG_INFINITY, |acc, &l| acc.max(l));
+        let exps: Vec<f32> = logits.iter().map(|&l| (l - max).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+        let probs: Vec<f32> = exps.iter().map(|&e| e / sum).collect();
+        for (mean, &p) in mean_probs.iter_mut().zip(&probs) {
+            *mean += p / tokens as f32;
+        }
+
+        let mut order: Vec<usize> = (0..num_experts).collect();
+        // Stable sort keeps the lower expert index first on ties
+        order.sort_by(|&a, &b| probs[b].total_cmp(&probs[a]));
+        let top: Vec<(usize, f32)> = order[..top_k].iter().map(|&e| (e, probs[e])).collect();
+        let top_sum: f32 = top.iter().map(|(_, p)| p).sum();
+        ranked.push(top.into_iter().map(|(e, p)| (e, p / top_sum)).collect());
+    }
+
+    let capacity = config.capacity();
+    let mut routed = vec![0usize; num_experts];
+    let mut load = vec![0usize; num_experts];
+    let mut choices: Vec<Vec<RouteChoice>> = ranked
+        .iter()
+        .map(|top| {
+            top.iter()
+                .map(|&(expert, weight)| RouteChoice {
+                    expert,
+                    weight,
+                    slot: None,
+                })
+                .collect()
+        })
+        .collect();
+
+    for rank in 0..top_k {
+        for token_choices in choices.iter_mut() {
+            let choice = &mut token_choices[rank];
+            routed[choice.expert] += 1;
+            if load[choice.expert] < capacity {
+                choice.slot = Some(load[choice.expert]);
+                load[choice.expert] += 1;
+            }
+        }
+    }
+
+    Routing {
+        choices,
+        mean_probs,
+        routed,
+        load,
+    }
+}
+
+/// Weights of one expert FFN: out = GELU(x * w1 + b1) * w2 + b2
+pub struct ExpertWeights {
+    /// (d_model, d_ff)
+    pub w1: Vec<FP8>,
+    pub b1: Vec<FP8>,
+    /// (d_ff, d_model)
+    pub w2: Vec<FP8>,
+    pub b2: Vec<FP8>,
+}
+
+/// Mixture-of-experts layer with a residual connection.
+///
+/// Every expert processes a fixed batch of `capacity` slots, with unused slots
+/// zero-padded, and the weighted expert outputs are combined in f32:
+///
+/// ```text
+/// out = x + sum over dispatched choices of weight * expert(x)
+/// ```
+pub fn mixture_of_experts(
+    input: &[FP8],
+    routing: &Routing,
+    experts: &[ExpertWeights],
+    config: &MoeConfig,
+) -> Result<Vec<FP8>> {
+    let MoeConfig { d_model, d_ff, .. } = *config;
+    let capacity = config.capacity();
+
+    // Dispatch: gather each expert's tokens into its capacity-sized batch
+    let mut batches = vec![vec![FP8::zero(); config.slot_buffer_len()?]; experts.len()];
+    for (t, token_choices) in routing.choices.iter().enumerate() {
+        for choice in token_choices {
+            if let Some(slot) = choice.slot {
+                batches[choice.expert][slot * d_model..(slot + 1) * d_model]
+                    .copy_from_slice(&input[t * d_model..(t + 1) * d_model]);
+            }
+        }
+    }
+
+    let outputs: Vec<Vec<FP8>> = experts
+        .iter()
+        .zip(&batches)
+        .map(|(expert, batch)| {
+            let hidden = gemm_fp8_fused(
+                batch,
+                &expert.w1,
+                capacity,
+                d_model,
+                d_ff,
+                &Epilogue {
+                    bias: Some(&expert.b1),
+                    activation: Some(ActivationType::GELU),
+                    ..Default::default()
+                },
+            );
+            gemm_fp8_fused(
+                &hidden,
+                &expert.w2,
+                capacity,
+                d_ff,
+                d_model,
+                &Epilogue {
+                    bias: Some(&expert.b2),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+
+    // Combine, in token and rank order
+    let mut output = Vec::with_capacity(input.len());
+    for (t, token_choices) in routing.choices.iter().enumerate() {
+        let mut combined: Vec<f32> = input[t * d_model..(t + 1) * d_model]
+            .iter()
+            .map(|x| x.to_f32())
+            .collect();
+        for choice in token_choices {
+            if let Some(slot) = choice.slot {
+                let expert_out = &outputs[choice.expert][slot * d_model..(slot + 1) * d_model];
+                for (acc, y) in combined.iter_mut().zip(expert_out) {
+                    *acc += choice.weight * y.to_f32();
+                }
+            }
+        }
+        output.extend(combined.into_iter().map(FP8::from_f32));
+    }
+
+    Ok(output)
+}
+
+/// Execute a mixture-of-experts layer on seeded inputs, router and experts.
+///
+/// The result hash covers the routing decisions and the layer output; load
+/// statistics are reported as metrics.
+pub fn execute_mixture_of_experts(config: &MoeConfig, seed: u64) -> Result<OperationOutput> {
+    Ok(run_mixture_of_experts(config, seed)?.0)
+}
+
+/// Seeded input, router and expert weights and the routing and output of a
+/// mixture-of-experts layer
+pub fn mixture_of_experts_tensors(config: &MoeConfig, seed: u64) -> Result<OperationTensors> {
+    Ok(run_mixture_of_experts(config, seed)?.1)
+}
+
+fn run_mixture_of_experts(
+    config: &MoeConfig,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    config.validate()?;
+    let MoeConfig {
+        tokens,
+        d_model,
+        d_ff,
+        num_experts,
+        ..
+    } = *config;
+    let flops = mixture_of_experts_flops(config)?;
+
+    let input = generate_random_tensor(&[tokens, d_model], seed)?;
+    let router = generate_random_tensor(&[d_model, num_experts], seed.wrapping_add(1))?;
+    let experts = (0..num_experts as u64)
+        .map(|e| {
+            let expert_seed = seed.wrapping_add(2 + 4 * e);
+            Ok(ExpertWeights {
+                w1: generate_random_tensor(&[d_model, d_ff], expert_seed)?,
+                b1: generate_random_tensor(&[d_ff], expert_seed.wrapping_add(1))?,
+                w2: generate_random_tensor(&[d_ff, d_model], expert_seed.wrapping_add(2))?,
+                b2: generate_random_tensor(&[d_model], expert_seed.wrapping_add(3))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&input);
+    tensors.add_fp8(&router);
+    for expert in &experts {
+        for operand in [&expert.w1, &expert.b1, &expert.w2, &expert.b2] {
+            tensors.add_fp8(operand);
+        }
+    }
+
+    let routing = route_tokens(&input, &router, config);
+    let output = mixture_of_experts(&input, &routing, &experts, config)?;
+
+    let routing_bytes = routing.to_bytes();
+    let mut result_bytes = routing_bytes.clone();
+    result_bytes.extend(output.iter().map(|x| x.to_bits()));
+
+    let mut metrics = vec![
+        (
+            "load_balance_loss".to_string(),
+            routing.load_balance_loss() as f64,
+        ),
+        ("drop_rate".to_string(), routing.drop_rate() as f64),
+    ];
+    metrics.extend(
+        routing
+            .load
+            .iter()
+            .enumerate()
+            .map(|(e, &load)| (format!("expert_{}_load", e), load as f64)),
+    );
+
+    let output = OperationOutput {
+        result_hash: Proof::hash_operation_result(&result_bytes),
+        flops,
+        intermediate_hashes: vec![
+            (
+                "routing".to_string(),
+                Proof::hash_operation_result(&routing_bytes),
+            ),
+            ("output".to_string(), hash_tensor(&output)),
+        ],
+        metrics,
+    };
+    Ok((
+        output,
+        OperationTensors {
+            output: result_bytes,
+            ..tensors
+        },
+    ))
+}
+
+/// FLOP count of a mixture-of-experts layer: router, top-k selection, every
+/// expert over its full capacity (padding included) and the weighted combine
+pub fn mixture_of_experts_flops(config: &MoeConfig) -> Result<u64> {
+    let MoeConfig {
+        tokens,
+        d_model,
+        d_ff,
+        num_experts,
+        top_k,
+        ..
+    } = *config;
+    let capacity = config.capacity();
+
+    // Router GEMM, softmax, one compare per logit and rank, and renormalization
+    let router_flops = flops_sum(&[
+        gemm_flops((tokens, d_model, num_experts))?,
+        softmax_flops(tokens, num_experts)?,
+        flops_product(&[tokens, num_experts, top_k])?,
+        flops_product(&[2, tokens, top_k])?,
+    ])?;
+    // Two GEMMs plus hidden bias, activation and output bias per slot
+    let expert_flops = flops_sum(&[
+        flops_product(&[num_experts, capacity, 4, d_model, d_ff])?,
+        flops_product(&[num_experts, capacity, 2, d_ff])?,
+        flops_product(&[num_experts, capacity, d_model])?,
+    ])?;
+    // Weighted sum of the top-k outputs
+    let combine_flops = flops_product(&[2, tokens, top_k, d_model])?;
+
+    flops_sum(&[router_flops, expert_flops, combine_flops])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(capacity_factor: f32) -> MoeConfig {
+        MoeConfig {
+            tokens: 16,
+            d_model: 8,
+            d_ff: 16,
+            num_experts: 4,
+            top_k: 2,
+            capacity_factor,
+        }
+    }
+
+    #[test]
+    fn test_routing_respects_capacity() {
+        let config = config(1.0);
+        let input = generate_random_tensor(&[16, 8], 1).unwrap();
+        let router = generate_random_tensor(&[8, 4], 2).unwrap();
+
+        let routing = route_tokens(&input, &router, &config);
+        assert_eq!(config.capacity(), 8);
+        assert!(routing.load.iter().all(|&load| load <= 8));
+        assert_eq!(routing.routed.iter().sum::<usize>(), 32);
+
+        for token_choices in &routing.choices {
+            let weights: f32 = token_choices.iter().map(|c| c.weight).sum();
+            assert!((weights - 1.0).abs() < 1e-6);
+            assert_ne!(token_choices[0].expert, token_choices[1].expert);
+        }
+
+        // Plenty of capacity dispatches everything
+        let roomy = route_tokens(
+            &input,
+            &router,
+            &MoeConfig {
+                capacity_factor: 4.0,
+                ..config
+            },
+        );
+        assert_eq!(roomy.drop_rate(), 0.0);
+        assert_eq!(roomy.load, roomy.routed);
+    }
+
+    #[test]
+    fn test_load_balance_loss() {
+        let balanced = Routing {
+            choices: Vec::new(),
+            mean_probs: vec![0.25; 4],
+            routed: vec![3; 4],
+            load: vec![3; 4],
+        };
+        assert!((balanced.load_balance_loss() - 1.0).abs() < 1e-6);
+
+        let collapsed = Routing {
+            mean_probs: vec![1.0, 0.0, 0.0, 0.0],
+            routed: vec![12, 0, 0, 0],
+            load: vec![6, 0, 0, 0],
+            ..balanced
+        };
+        assert!((collapsed.load_balance_loss() - 4.0).abs() < 1e-6);
+        assert!((collapsed.drop_rate() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dropped_tokens_pass_through() {
+        // A capacity of one slot per expert drops most choices; tokens with no
+        // dispatched choice keep their input unchanged
+        let config = MoeConfig {
+            capacity_factor: 0.1,
+            ..config(1.0)
+        };
+        let input = generate_random_tensor(&[16, 8], 3).unwrap();
+        let router = generate_random_tensor(&[8, 4], 4).unwrap();
+        let experts: Vec<ExpertWeights> = (0..4)
+            .map(|e| ExpertWeights {
+                w1: generate_random_tensor(&[8, 16], 10 + e).unwrap(),
+                b1: generate_random_tensor(&[16], 20 + e).unwrap(),
+                w2: generate_random_tensor(&[16, 8], 30 + e).unwrap(),
+                b2: generate_random_tensor(&[8], 40 + e).unwrap(),
+            })
+            .collect();
+
+        let routing = route_tokens(&input, &router, &config);
+        let output = mixture_of_experts(&input, &routing, &experts, &config).unwrap();
+
+        let mut passed_through = 0;
+        for (t, token_choices) in routing.choices.iter().enumerate() {
+            if token_choices.iter().all(|c| c.slot.is_none()) {
+                assert_eq!(&output[t * 8..(t + 1) * 8], &input[t * 8..(t + 1) * 8]);
+                passed_through += 1;
+            }
+        }
+        assert!(passed_through > 0);
+    }
+
+    #[test]
+    fn test_moe_execution() {
+        let config = config(1.25);
+        let result = execute_mixture_of_experts(&config, 42).unwrap();
+        let again = execute_mixture_of_experts(&config, 42).unwrap();
+
+        assert_eq!(result.result_hash, again.result_hash);
+        assert_eq!(result.flops, mixture_of_experts_flops(&config).unwrap());
+        assert_eq!(result.intermediate_hashes[0].0, "routing");
+        assert_eq!(result.metrics.len(), 2 + 4);
+
+        let top1 = execute_mixture_of_experts(&MoeConfig { top_k: 1, ..config }, 42).unwrap();
+        assert_ne!(result.intermediate_hashes[0], top1.intermediate_hashes[0]);
+
+        assert!(execute_mixture_of_experts(&MoeConfig { top_k: 5, ..config }, 42).is_err());
+        assert!(execute_mixture_of_experts(
+            &MoeConfig {
+                capacity_factor: 0.0,
+                ..config
+            },
+            42
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        // A huge factor cannot give an expert more slots than there are choices
+        let config = config(1e30);
+        assert_eq!(config.capacity(), 16 * 2);
+        assert!(execute_mixture_of_experts(&config, 42).is_ok());
+
+        let overflowing = MoeConfig {
+            d_model: usize::MAX / 4,
+            ..config
+        };
+        assert!(overflowing.validate().is_err());
+        assert!(execute_mixture_of_experts(&overflowing, 42).is_err());
+    }
+}
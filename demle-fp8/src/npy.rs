@@ -0,0 +1,302 @@
+use crate::fp8::FP8;
+use crate::quantize::TensorData;
+use demle_core::{DemleError, NumericFormat, Result};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Array stored in the NumPy .npy format, with its raw little-endian,
+/// C-ordered element bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray {
+    /// NumPy dtype descriptor such as `<f4` or `|u1`
+    pub descr: String,
+    pub shape: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+impl NpyArray {
+    /// Bytes per element, taken from the descriptor
+    pub fn item_size(&self) -> Result<usize> {
+        item_size(&self.descr)
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Whether the array has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn item_size(descr: &str) -> Result<usize> {
+    descr
+        .get(2..)
+        .and_then(|size| size.parse().ok())
+        .filter(|&size: &usize| size > 0)
+        .ok_or_else(|| {
+            DemleError::SerializationError(format!("Unsupported .npy dtype '{}'", descr))
+        })
+}
+
+/// Parse a .npy file. Only little-endian, C-ordered arrays are accepted.
+pub fn read_npy(bytes: &[u8]) -> Result<NpyArray> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err(DemleError::SerializationError(
+            "Not a .npy file: bad magic".to_string(),
+        ));
+    }
+
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        version => {
+            return Err(DemleError::SerializationError(format!(
+                "Unsupported .npy version {}",
+                version
+            )))
+        }
+    };
+    let data_start = header_start + header_len;
+    let header = bytes
+        .get(header_start..data_start)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or_else(|| DemleError::SerializationError("Truncated .npy header".to_string()))?;
+
+    let descr = header_value(header, "descr")?
+        .trim_matches(|c| c == '\'' || c == '"')
+        .to_string();
+    if header_value(header, "fortran_order")? != "False" {
+        return Err(DemleError::SerializationError(
+            "Fortran-ordered .npy arrays are not supported".to_string(),
+        ));
+    }
+    let shape = parse_shape(header_value(header, "shape")?)?;
+
+    let size = item_size(&descr)?;
+    if descr.starts_with('>') && size > 1 {
+        return Err(DemleError::SerializationError(format!(
+            "Big-endian .npy dtype '{}' is not supported",
+            descr
+        )));
+    }
+
+    let data = &bytes[data_start..];
+    let expected = shape.iter().product::<usize>() * size;
+    if data.len() != expected {
+        return Err(DemleError::SerializationError(format!(
+            "Expected {} bytes of .npy data for shape {:?}, got {}",
+            expected,
+            shape,
+            data.len()
+        )));
+    }
+
+    Ok(NpyArray {
+        descr,
+        shape,
+        data: data.to_vec(),
+    })
+}
+
+/// Raw text of a value in the header dictionary, up to the next top-level comma
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let missing = || DemleError::SerializationError(format!(".npy header has no '{}'", key));
+    let key_start = header
+        .find(&format!("'{}'", key))
+        .or_else(|| header.find(&format!("\"{}\"", key)))
+        .ok_or_else(missing)?;
+    let rest = &header[key_start + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':').ok_or_else(missing)?;
+
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' | '}' if depth == 0 => return Ok(rest[..i].trim()),
+            _ => {}
+        }
+    }
+    Err(missing())
+}
+
+fn parse_shape(text: &str) -> Result<Vec<usize>> {
+    let inner = text
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .ok_or_else(|| DemleError::SerializationError(format!("Bad .npy shape '{}'", text)))?;
+
+    inner
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| {
+            dim.parse().map_err(|_| {
+                DemleError::SerializationError(format!("Bad .npy dimension '{}'", dim))
+            })
+        })
+        .collect()
+}
+
+/// Serialize an array as a version 1.0 .npy file
+pub fn write_npy(array: &NpyArray) -> Vec<u8> {
+    let shape = match array.shape.as_slice() {
+        [dim] => format!("({},)", dim),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        array.descr, shape
+    );
+    // Pad with spaces so the data starts on a 64-byte boundary
+    let unpadded = MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + header.len() + array.data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&array.data);
+    bytes
+}
+
+/// Encode FP8 E4M3 values as a `|u1` array of their raw bits, since NumPy
+/// has no native FP8 dtype
+pub fn fp8_to_npy(shape: &[usize], data: &[FP8]) -> NpyArray {
+    NpyArray {
+        descr: "|u1".to_string(),
+        shape: shape.to_vec(),
+        data: data.iter().map(|x| x.to_bits()).collect(),
+    }
+}
+
+/// Decode a `|u1` array of raw bits as FP8 E4M3 values
+pub fn fp8_from_npy(array: &NpyArray) -> Result<Vec<FP8>> {
+    if array.descr != "|u1" {
+        return Err(DemleError::SerializationError(format!(
+            "Expected FP8 bits as '|u1', got '{}'",
+            array.descr
+        )));
+    }
+    Ok(array
+        .data
+        .iter()
+        .map(|&bits| FP8::from_bits(bits))
+        .collect())
+}
+
+/// Encode tensor elements as an array. NumPy has no BF16 or FP8 dtypes, so
+/// those are written as their raw bits (`<u2` and `|u1`); reinterpret them
+/// with `.view()` on the PyTorch or ml_dtypes side.
+pub fn tensor_to_npy(shape: &[usize], data: &TensorData) -> NpyArray {
+    let descr = match data {
+        TensorData::F32(_) => "<f4",
+        TensorData::F16(_) => "<f2",
+        TensorData::BF16(_) => "<u2",
+        TensorData::FP8E4M3(_) | TensorData::FP8E5M2(_) => "|u1",
+        TensorData::INT8(_) => "|i1",
+    };
+    NpyArray {
+        descr: descr.to_string(),
+        shape: shape.to_vec(),
+        data: data.to_le_bytes(),
+    }
+}
+
+/// Decode an array as elements of a format. Raw-bit arrays are accepted for
+/// every format of matching width, including the `|V1`/`<V2` void dtypes
+/// that ml_dtypes arrays are saved with.
+pub fn tensor_from_npy(array: &NpyArray, format: NumericFormat) -> Result<TensorData> {
+    let compatible: &[&str] = match format {
+        NumericFormat::F32 => &["<f4", "<u4", "|V4"],
+        NumericFormat::F16 => &["<f2", "<u2", "|V2", "<V2"],
+        NumericFormat::BF16 => &["<u2", "|V2", "<V2"],
+        NumericFormat::FP8E4M3 | NumericFormat::FP8E5M2 => &["|u1", "|V1", "<V1"],
+        NumericFormat::INT8 => &["|i1"],
+    };
+    if !compatible.contains(&array.descr.as_str()) {
+        return Err(DemleError::SerializationError(format!(
+            "Cannot read .npy dtype '{}' as {:?}",
+            array.descr, format
+        )));
+    }
+    TensorData::from_le_bytes(format, &array.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_round_trip() {
+        let data: Vec<FP8> = (0..=255u8).map(FP8::from_bits).collect();
+        for shape in [vec![256], vec![16, 16], vec![2, 4, 32]] {
+            let bytes = write_npy(&fp8_to_npy(&shape, &data));
+            assert_eq!(&bytes[..6], MAGIC);
+            assert_eq!((bytes.len() - data.len()) % 64, 0);
+
+            let array = read_npy(&bytes).unwrap();
+            assert_eq!(array.shape, shape);
+            assert_eq!(fp8_from_npy(&array).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_npy_header_parsing() {
+        // Header as written by numpy.save for a (2, 3) float32 array
+        let header = "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&[0u8; 24]);
+
+        let array = read_npy(&bytes).unwrap();
+        assert_eq!(array.descr, "<f4");
+        assert_eq!(array.shape, vec![2, 3]);
+        assert_eq!(array.item_size().unwrap(), 4);
+
+        // Wrong data length and non-npy input are rejected
+        assert!(read_npy(&bytes[..bytes.len() - 1]).is_err());
+        assert!(read_npy(b"not an npy file").is_err());
+        assert!(fp8_from_npy(&array).is_err());
+    }
+
+    #[test]
+    fn test_npy_tensor_formats() {
+        let bits: Vec<u8> = (0..=255).collect();
+        for format in [
+            NumericFormat::FP8E4M3,
+            NumericFormat::FP8E5M2,
+            NumericFormat::INT8,
+        ] {
+            let data = TensorData::from_le_bytes(format, &bits).unwrap();
+            let bytes = write_npy(&tensor_to_npy(&[16, 16], &data));
+            let array = read_npy(&bytes).unwrap();
+            assert_eq!(tensor_from_npy(&array, format).unwrap(), data);
+        }
+
+        let halves = TensorData::BF16(vec![half::bf16::from_f32(-1.5), half::bf16::MAX]);
+        let array = read_npy(&write_npy(&tensor_to_npy(&[2], &halves))).unwrap();
+        assert_eq!(array.descr, "<u2");
+        assert_eq!(
+            tensor_from_npy(&array, NumericFormat::BF16).unwrap(),
+            halves
+        );
+        assert!(tensor_from_npy(&array, NumericFormat::F32).is_err());
+    }
+}
@@ -0,0 +1,907 @@
+use crate::quantize::{format_size, TensorData};
+use crate::store::{StoredTensor, TensorStore};
+use crate::tensor_file::NamedTensor;
+use crate::tensor_ops::output_shape;
+use demle_core::{
+    Activation, DemleError, MLOperation, NumericFormat, Result, TensorInput, TensorOp, TensorRef,
+};
+use prost::Message;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// ONNX operators that can be lowered to tensor operations
+pub const SUPPORTED_OPS: &[&str] = &[
+    "MatMul",
+    "Gemm",
+    "Conv",
+    "LayerNormalization",
+    "Softmax",
+    "Relu",
+    "Gelu",
+    "Add",
+    "Reshape",
+];
+
+// The subset of onnx.proto needed to read a graph; field tags follow the spec
+
+#[derive(Clone, PartialEq, Message)]
+struct ModelProto {
+    #[prost(message, optional, tag = "7")]
+    graph: Option<GraphProto>,
+    #[prost(message, repeated, tag = "8")]
+    opset_import: Vec<OperatorSetIdProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    domain: String,
+    #[prost(int64, tag = "2")]
+    version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    node: Vec<NodeProto>,
+    #[prost(message, repeated, tag = "5")]
+    initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    output: Vec<String>,
+    #[prost(string, tag = "3")]
+    name: String,
+    #[prost(string, tag = "4")]
+    op_type: String,
+    #[prost(message, repeated, tag = "5")]
+    attribute: Vec<AttributeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AttributeProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(float, tag = "2")]
+    f: f32,
+    #[prost(int64, tag = "3")]
+    i: i64,
+    #[prost(bytes = "vec", tag = "4")]
+    s: Vec<u8>,
+    #[prost(int64, repeated, tag = "8")]
+    ints: Vec<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    float_data: Vec<f32>,
+    #[prost(int32, repeated, tag = "5")]
+    int32_data: Vec<i32>,
+    #[prost(int64, repeated, tag = "7")]
+    int64_data: Vec<i64>,
+    #[prost(string, tag = "8")]
+    name: String,
+    #[prost(bytes = "vec", tag = "9")]
+    raw_data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+// TensorProto.DataType values
+const FLOAT: i32 = 1;
+const INT8: i32 = 3;
+const INT64: i32 = 7;
+const FLOAT16: i32 = 10;
+const BFLOAT16: i32 = 16;
+const FLOAT8E4M3FN: i32 = 17;
+const FLOAT8E5M2: i32 = 19;
+
+/// An ONNX graph lowered to tensor operations over a tensor store
+#[derive(Debug, Clone)]
+pub struct OnnxProgram {
+    /// Operations in a valid execution order; each names its output after the
+    /// ONNX value it computes
+    pub operations: Vec<MLOperation>,
+    /// Edges holding the graph outputs once the operations have run
+    pub outputs: Vec<TensorInput>,
+}
+
+/// Lower the ONNX model in `path`; see [`compile_onnx`]
+pub fn load_onnx(
+    path: &Path,
+    inputs: &HashMap<String, TensorRef>,
+    store: &TensorStore,
+) -> Result<OnnxProgram> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        DemleError::ComputationError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    compile_onnx(&bytes, inputs, store)
+}
+
+/// Lower a serialized ONNX model to tensor operations. Graph inputs are bound
+/// to stored tensors by name and initializers are written to the store as FP8.
+/// Tensor operations read FP8 values unscaled, so initializers with non-zero
+/// values outside the E4M3 range are rejected instead of being flushed to zero
+/// or saturated. Unsupported operators are all reported in a single error.
+pub fn compile_onnx(
+    model: &[u8],
+    inputs: &HashMap<String, TensorRef>,
+    store: &TensorStore,
+) -> Result<OnnxProgram> {
+    let model = ModelProto::decode(model)
+        .map_err(|e| DemleError::SerializationError(format!("Invalid ONNX model: {}", e)))?;
+    let graph = model
+        .graph
+        .ok_or_else(|| DemleError::SerializationError("ONNX model has no graph".to_string()))?;
+
+    let unsupported: Vec<String> = graph
+        .node
+        .iter()
+        .filter(|node| !SUPPORTED_OPS.contains(&node.op_type.as_str()))
+        .map(|node| format!("{} (node '{}')", node.op_type, node_name(node)))
+        .collect();
+    if !unsupported.is_empty() {
+        return Err(DemleError::ValidationError(format!(
+            "Unsupported ONNX operators: {}; supported are {}",
+            unsupported.join(", "),
+            SUPPORTED_OPS.join(", ")
+        )));
+    }
+
+    let opset = model
+        .opset_import
+        .iter()
+        .find(|opset| opset.domain.is_empty() || opset.domain == "ai.onnx")
+        .map_or(1, |opset| opset.version);
+    let mut lowering = Lowering {
+        store,
+        opset,
+        values: HashMap::new(),
+        constants: HashMap::new(),
+        int_constants: HashMap::new(),
+        operations: Vec::new(),
+    };
+
+    for tensor in &graph.initializer {
+        if tensor.data_type == INT64 {
+            lowering
+                .int_constants
+                .insert(tensor.name.clone(), int64_values(tensor)?);
+        } else {
+            lowering
+                .constants
+                .insert(tensor.name.clone(), float_initializer(tensor)?);
+        }
+    }
+    for input in &graph.input {
+        if let Some(tensor) = inputs.get(&input.name) {
+            lowering
+                .values
+                .insert(input.name.clone(), TensorInput::Stored(tensor.clone()));
+        } else if !lowering.constants.contains_key(&input.name) {
+            return Err(DemleError::ValidationError(format!(
+                "ONNX graph input '{}' is not bound to a stored tensor",
+                input.name
+            )));
+        }
+    }
+
+    for node in &graph.node {
+        lowering.lower(node).map_err(|e| {
+            let reason = match e {
+                DemleError::ValidationError(reason) => reason,
+                other => other.to_string(),
+            };
+            DemleError::ValidationError(format!(
+                "Cannot lower ONNX {} node '{}': {}",
+                node.op_type,
+                node_name(node),
+                reason
+            ))
+        })?;
+    }
+
+    let outputs = graph
+        .output
+        .iter()
+        .map(|output| match lowering.values.get(&output.name) {
+            Some(edge @ TensorInput::Edge { .. }) => Ok(edge.clone()),
+            _ => Err(DemleError::ValidationError(format!(
+                "ONNX graph output '{}' is not computed by any node",
+                output.name
+            ))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(OnnxProgram {
+        operations: lowering.operations,
+        outputs,
+    })
+}
+
+fn node_name(node: &NodeProto) -> &str {
+    if node.name.is_empty() {
+        node.output.first().map_or("", String::as_str)
+    } else {
+        &node.name
+    }
+}
+
+fn dims(tensor: &TensorProto) -> Result<Vec<usize>> {
+    tensor
+        .dims
+        .iter()
+        .map(|&d| {
+            usize::try_from(d).map_err(|_| {
+                DemleError::SerializationError(format!(
+                    "Initializer '{}' has negative dimension {}",
+                    tensor.name, d
+                ))
+            })
+        })
+        .collect()
+}
+
+fn int64_values(tensor: &TensorProto) -> Result<Vec<i64>> {
+    if tensor.raw_data.is_empty() {
+        return Ok(tensor.int64_data.clone());
+    }
+    if !tensor.raw_data.len().is_multiple_of(8) {
+        return Err(DemleError::SerializationError(format!(
+            "Initializer '{}' has {} bytes of INT64 data, not a multiple of 8",
+            tensor.name,
+            tensor.raw_data.len()
+        )));
+    }
+    Ok(tensor
+        .raw_data
+        .chunks_exact(8)
+        .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+        .collect())
+}
+
+/// Convert a floating point initializer to FP8; FP8 E4M3 data keeps its bits
+/// and other formats must already lie in the E4M3 range
+fn float_initializer(tensor: &TensorProto) -> Result<StoredTensor> {
+    let format = match tensor.data_type {
+        FLOAT => NumericFormat::F32,
+        FLOAT16 => NumericFormat::F16,
+        BFLOAT16 => NumericFormat::BF16,
+        FLOAT8E4M3FN => NumericFormat::FP8E4M3,
+        FLOAT8E5M2 => NumericFormat::FP8E5M2,
+        INT8 => NumericFormat::INT8,
+        other => {
+            return Err(DemleError::ValidationError(format!(
+                "Initializer '{}' has unsupported ONNX data type {}",
+                tensor.name, other
+            )))
+        }
+    };
+
+    let data = if !tensor.raw_data.is_empty() {
+        TensorData::from_le_bytes(format, &tensor.raw_data)?
+    } else if format == NumericFormat::F32 {
+        TensorData::F32(tensor.float_data.clone())
+    } else {
+        // Narrower types keep one element's bits in the low bytes of each int32
+        let size = format_size(format);
+        let bytes: Vec<u8> = tensor
+            .int32_data
+            .iter()
+            .flat_map(|&v| v.to_le_bytes()[..size].to_vec())
+            .collect();
+        TensorData::from_le_bytes(format, &bytes)?
+    };
+
+    NamedTensor::new(tensor.name.clone(), dims(tensor)?, data)?.to_stored()
+}
+
+struct Lowering<'a> {
+    store: &'a TensorStore,
+    opset: i64,
+    /// Tensor each ONNX value name resolves to
+    values: HashMap<String, TensorInput>,
+    constants: HashMap<String, StoredTensor>,
+    int_constants: HashMap<String, Vec<i64>>,
+    operations: Vec<MLOperation>,
+}
+
+impl Lowering<'_> {
+    fn input(&mut self, name: &str) -> Result<TensorInput> {
+        if let Some(value) = self.values.get(name) {
+            return Ok(value.clone());
+        }
+        let constant = self.constants.get(name).ok_or_else(|| {
+            DemleError::ValidationError(format!("value '{}' is not defined", name))
+        })?;
+        let value = TensorInput::Stored(self.store.put(constant)?);
+        self.values.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    fn constant(&self, name: &str) -> Result<&StoredTensor> {
+        self.constants.get(name).ok_or_else(|| {
+            DemleError::ValidationError(format!("'{}' must be an initializer", name))
+        })
+    }
+
+    fn emit(
+        &mut self,
+        op: TensorOp,
+        inputs: Vec<TensorInput>,
+        output: &str,
+    ) -> Result<TensorInput> {
+        let shapes: Vec<&[usize]> = inputs.iter().map(|input| input.shape()).collect();
+        let edge = TensorInput::Edge {
+            name: output.to_string(),
+            shape: output_shape(&op, &shapes)?,
+        };
+        self.operations.push(MLOperation::TensorOperation {
+            op,
+            inputs,
+            output: Some(output.to_string()),
+        });
+        self.values.insert(output.to_string(), edge.clone());
+        Ok(edge)
+    }
+
+    /// Add with the lower-rank operand broadcast over the leading dimensions
+    /// of the other, dropping its leading unit dimensions if needed
+    fn emit_add(&mut self, a: TensorInput, b: TensorInput, output: &str) -> Result<TensorInput> {
+        let (a, b) = if a.shape().len() >= b.shape().len() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        if a.shape().ends_with(b.shape()) {
+            return self.emit(TensorOp::Add, vec![a, b], output);
+        }
+
+        let squeezed: Vec<usize> = b.shape().iter().copied().skip_while(|&d| d == 1).collect();
+        if !a.shape().ends_with(&squeezed) {
+            return Err(DemleError::ValidationError(format!(
+                "cannot broadcast {:?} to {:?}",
+                b.shape(),
+                a.shape()
+            )));
+        }
+        let b = self.emit(
+            TensorOp::Reshape { shape: squeezed },
+            vec![b],
+            &format!("{}/broadcast", output),
+        )?;
+        self.emit(TensorOp::Add, vec![a, b], output)
+    }
+
+    fn lower(&mut self, node: &NodeProto) -> Result<()> {
+        let output = node
+            .output
+            .first()
+            .ok_or_else(|| DemleError::ValidationError("node has no output".to_string()))?
+            .as_str();
+        let input_name = |i: usize| node.input.get(i).filter(|name| !name.is_empty());
+        let required = |i: usize| {
+            input_name(i).ok_or_else(|| DemleError::ValidationError(format!("missing input {}", i)))
+        };
+
+        match node.op_type.as_str() {
+            "MatMul" => {
+                let a = self.input(required(0)?)?;
+                let b = self.input(required(1)?)?;
+                if b.shape().len() != 2 {
+                    return Err(DemleError::ValidationError(format!(
+                        "right operand of rank {} is not supported",
+                        b.shape().len()
+                    )));
+                }
+                self.emit(TensorOp::MatMul, vec![a, b], output)?;
+            }
+            "Gemm" => {
+                if attr_float(node, "alpha", 1.0) != 1.0 || attr_float(node, "beta", 1.0) != 1.0 {
+                    return Err(unsupported_attr("alpha/beta other than 1"));
+                }
+                if attr_int(node, "transA", 0) != 0 {
+                    return Err(unsupported_attr("transA"));
+                }
+                let a = self.input(required(0)?)?;
+                let b = if attr_int(node, "transB", 0) != 0 {
+                    let b = transpose(self.constant(required(1)?)?)?;
+                    TensorInput::Stored(self.store.put(&b)?)
+                } else {
+                    self.input(required(1)?)?
+                };
+
+                match input_name(2) {
+                    Some(c) => {
+                        let c = self.input(c)?;
+                        let product =
+                            self.emit(TensorOp::MatMul, vec![a, b], &format!("{}/matmul", output))?;
+                        self.emit_add(product, c, output)?;
+                    }
+                    None => {
+                        self.emit(TensorOp::MatMul, vec![a, b], output)?;
+                    }
+                }
+            }
+            "Conv" => {
+                if attr_int(node, "group", 1) != 1 {
+                    return Err(unsupported_attr("group other than 1"));
+                }
+                if attr_ints(node, "dilations").is_some_and(|d| d.iter().any(|&x| x != 1)) {
+                    return Err(unsupported_attr("dilations other than 1"));
+                }
+                if attr_string(node, "auto_pad").is_some_and(|p| p != "NOTSET") {
+                    return Err(unsupported_attr("auto_pad"));
+                }
+                let stride = match attr_ints(node, "strides").as_deref() {
+                    None => (1, 1),
+                    Some(&[sh, sw]) => (to_usize(sh)?, to_usize(sw)?),
+                    Some(_) => return Err(unsupported_attr("non-2D strides")),
+                };
+                let padding = match attr_ints(node, "pads").as_deref() {
+                    None => (0, 0),
+                    Some(&[top, left, bottom, right]) if top == bottom && left == right => {
+                        (to_usize(top)?, to_usize(left)?)
+                    }
+                    Some(_) => return Err(unsupported_attr("asymmetric or non-2D pads")),
+                };
+
+                let x = self.input(required(0)?)?;
+                let w = self.input(required(1)?)?;
+                let op = TensorOp::Conv2D { stride, padding };
+                match input_name(2) {
+                    Some(bias) => {
+                        let conv = self.emit(op, vec![x, w], &format!("{}/conv", output))?;
+                        // Expand the per-channel bias over the output plane so
+                        // it broadcasts as a trailing (C, H, W) operand
+                        let plane = conv.shape()[1..].to_vec();
+                        let bias = expand_bias(self.constant(bias)?, &plane)?;
+                        let bias = TensorInput::Stored(self.store.put(&bias)?);
+                        self.emit(TensorOp::Add, vec![conv, bias], output)?;
+                    }
+                    None => {
+                        self.emit(op, vec![x, w], output)?;
+                    }
+                }
+            }
+            "LayerNormalization" => {
+                let x = self.input(required(0)?)?;
+                let rank = x.shape().len() as i64;
+                let axis = attr_int(node, "axis", -1);
+                if axis != rank - 1 && axis != -1 {
+                    return Err(unsupported_attr(
+                        "normalization over more than the last axis",
+                    ));
+                }
+                let scale = self.input(required(1)?)?;
+                let bias = match input_name(2) {
+                    Some(bias) => self.input(bias)?,
+                    None => {
+                        let dim = x.shape().last().copied().unwrap_or(0);
+                        let zeros = StoredTensor::new(vec![dim], vec![crate::FP8::zero(); dim])?;
+                        TensorInput::Stored(self.store.put(&zeros)?)
+                    }
+                };
+                let epsilon = attr_float(node, "epsilon", 1e-5);
+                self.emit(
+                    TensorOp::LayerNorm { epsilon },
+                    vec![x, scale, bias],
+                    output,
+                )?;
+            }
+            "Softmax" => {
+                let x = self.input(required(0)?)?;
+                let rank = x.shape().len() as i64;
+                // The default axis moved from 1 to -1 in opset 13
+                let default_axis = if self.opset >= 13 { -1 } else { 1 };
+                let axis = attr_int(node, "axis", default_axis);
+                if axis != rank - 1 && axis != -1 {
+                    return Err(unsupported_attr("softmax over an axis other than the last"));
+                }
+                self.emit(TensorOp::Softmax { log: false }, vec![x], output)?;
+            }
+            "Relu" | "Gelu" => {
+                let activation = if node.op_type == "Relu" {
+                    Activation::ReLU
+                } else {
+                    Activation::GELU
+                };
+                let x = self.input(required(0)?)?;
+                self.emit(TensorOp::Activation(activation), vec![x], output)?;
+            }
+            "Add" => {
+                let a = self.input(required(0)?)?;
+                let b = self.input(required(1)?)?;
+                self.emit_add(a, b, output)?;
+            }
+            "Reshape" => {
+                if attr_int(node, "allowzero", 0) != 0 {
+                    return Err(unsupported_attr("allowzero"));
+                }
+                let x = self.input(required(0)?)?;
+                let target = self.int_constants.get(required(1)?).ok_or_else(|| {
+                    DemleError::ValidationError("shape must be an INT64 initializer".to_string())
+                })?;
+                let shape = reshape_target(x.shape(), target)?;
+                self.emit(TensorOp::Reshape { shape }, vec![x], output)?;
+            }
+            other => {
+                return Err(DemleError::ValidationError(format!(
+                    "unsupported operator {}",
+                    other
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unsupported_attr(what: &str) -> DemleError {
+    DemleError::ValidationError(format!("{} is not supported", what))
+}
+
+fn attr<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute.iter().find(|attr| attr.name == name)
+}
+
+fn attr_int(node: &NodeProto, name: &str, default: i64) -> i64 {
+    attr(node, name).map_or(default, |attr| attr.i)
+}
+
+fn attr_float(node: &NodeProto, name: &str, default: f32) -> f32 {
+    attr(node, name).map_or(default, |attr| attr.f)
+}
+
+fn attr_ints(node: &NodeProto, name: &str) -> Option<Vec<i64>> {
+    attr(node, name).map(|attr| attr.ints.clone())
+}
+
+fn attr_string(node: &NodeProto, name: &str) -> Option<String> {
+    attr(node, name).map(|attr| String::from_utf8_lossy(&attr.s).into_owned())
+}
+
+fn to_usize(value: i64) -> Result<usize> {
+    usize::try_from(value)
+        .map_err(|_| DemleError::ValidationError(format!("negative value {}", value)))
+}
+
+fn transpose(tensor: &StoredTensor) -> Result<StoredTensor> {
+    let &[rows, cols] = tensor.shape.as_slice() else {
+        return Err(DemleError::ValidationError(format!(
+            "cannot transpose a tensor of shape {:?}",
+            tensor.shape
+        )));
+    };
+    let data = (0..rows * cols)
+        .map(|idx| tensor.data[(idx % rows) * cols + idx / rows])
+        .collect();
+    StoredTensor::new(vec![cols, rows], data)
+}
+
+/// Repeat a (C) bias over each (H, W) output plane of a convolution
+fn expand_bias(bias: &StoredTensor, plane: &[usize]) -> Result<StoredTensor> {
+    let channels = plane.first().copied().unwrap_or(0);
+    if bias.shape != [channels] {
+        return Err(DemleError::ValidationError(format!(
+            "bias of shape {:?} does not match {} output channels",
+            bias.shape, channels
+        )));
+    }
+    let plane_size: usize = plane[1..].iter().product();
+    let data = bias
+        .data
+        .iter()
+        .flat_map(|&b| std::iter::repeat_n(b, plane_size))
+        .collect();
+    StoredTensor::new(plane.to_vec(), data)
+}
+
+/// Resolve an ONNX Reshape target, where 0 copies the input dimension and -1
+/// is inferred from the remaining ones
+fn reshape_target(input: &[usize], target: &[i64]) -> Result<Vec<usize>> {
+    let mut shape = Vec::with_capacity(target.len());
+    let mut inferred = None;
+    for (i, &dim) in target.iter().enumerate() {
+        match dim {
+            0 => shape.push(*input.get(i).ok_or_else(|| {
+                DemleError::ValidationError(format!("no input dimension {} to copy", i))
+            })?),
+            -1 if inferred.is_none() => {
+                inferred = Some(i);
+                shape.push(1);
+            }
+            _ => shape.push(to_usize(dim)?),
+        }
+    }
+
+    let total: usize = input.iter().product();
+    if let Some(i) = inferred {
+        let known: usize = shape.iter().product();
+        if known == 0 || !total.is_multiple_of(known) {
+            return Err(DemleError::ValidationError(format!(
+                "cannot reshape {:?} to {:?}",
+                input, target
+            )));
+        }
+        shape[i] = total / known;
+    }
+    Ok(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_work_unit_with_store;
+    use crate::operations::generate_random_tensor;
+    use crate::store::temp_store;
+    use crate::tensor_ops::apply_tensor_op;
+
+    fn random(shape: &[usize], seed: u64) -> StoredTensor {
+        StoredTensor::new(shape.to_vec(), generate_random_tensor(shape, seed).unwrap()).unwrap()
+    }
+
+    fn initializer(name: &str, tensor: &StoredTensor) -> TensorProto {
+        TensorProto {
+            dims: tensor.shape.iter().map(|&d| d as i64).collect(),
+            data_type: FLOAT8E4M3FN,
+            name: name.to_string(),
+            raw_data: tensor.data.iter().map(|x| x.to_bits()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn node(
+        op_type: &str,
+        inputs: &[&str],
+        output: &str,
+        attribute: Vec<AttributeProto>,
+    ) -> NodeProto {
+        NodeProto {
+            input: inputs.iter().map(|s| s.to_string()).collect(),
+            output: vec![output.to_string()],
+            name: format!("{}_node", output),
+            op_type: op_type.to_string(),
+            attribute,
+        }
+    }
+
+    fn int_attr(name: &str, i: i64) -> AttributeProto {
+        AttributeProto {
+            name: name.to_string(),
+            i,
+            ..Default::default()
+        }
+    }
+
+    fn value(name: &str) -> ValueInfoProto {
+        ValueInfoProto {
+            name: name.to_string(),
+        }
+    }
+
+    fn model(node: Vec<NodeProto>, initializer: Vec<TensorProto>, outputs: &[&str]) -> Vec<u8> {
+        ModelProto {
+            graph: Some(GraphProto {
+                node,
+                initializer,
+                input: vec![value("x")],
+                output: outputs.iter().map(|name| value(name)).collect(),
+            }),
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: 17,
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_compile_and_execute_mlp() {
+        let store = temp_store("onnx-mlp");
+        let x = random(&[4, 6], 1);
+        let w1 = random(&[8, 6], 2); // (out, in) as exported for transB = 1
+        let b1 = random(&[8], 3);
+        let w2 = random(&[8, 3], 4);
+        let b2 = random(&[1, 3], 5);
+
+        let bytes = model(
+            vec![
+                node("Gemm", &["x", "w1", "b1"], "h", vec![int_attr("transB", 1)]),
+                node("Relu", &["h"], "a", vec![]),
+                node("MatMul", &["a", "w2"], "logits", vec![]),
+                node("Add", &["logits", "b2"], "biased", vec![]),
+                node("Softmax", &["biased"], "probs", vec![]),
+            ],
+            vec![
+                initializer("w1", &w1),
+                initializer("b1", &b1),
+                initializer("w2", &w2),
+                initializer("b2", &b2),
+            ],
+            &["probs"],
+        );
+
+        let inputs = HashMap::from([("x".to_string(), store.put(&x).unwrap())]);
+        let program = compile_onnx(&bytes, &inputs, &store).unwrap();
+        assert_eq!(
+            program.outputs,
+            vec![TensorInput::Edge {
+                name: "probs".to_string(),
+                shape: vec![4, 3],
+            }]
+        );
+
+        let results = execute_work_unit_with_store(&program.operations, Some(&store)).unwrap();
+        assert_eq!(results.len(), program.operations.len());
+
+        // Recompute with the kernels directly
+        let h = apply_tensor_op(&TensorOp::MatMul, &[x, transpose(&w1).unwrap()]).unwrap();
+        let h = apply_tensor_op(&TensorOp::Add, &[h, b1]).unwrap();
+        let a = apply_tensor_op(&TensorOp::Activation(Activation::ReLU), &[h]).unwrap();
+        let logits = apply_tensor_op(&TensorOp::MatMul, &[a, w2]).unwrap();
+        let b2 = StoredTensor::new(vec![3], b2.data).unwrap();
+        let biased = apply_tensor_op(&TensorOp::Add, &[logits, b2]).unwrap();
+        let probs = apply_tensor_op(&TensorOp::Softmax { log: false }, &[biased]).unwrap();
+
+        assert_eq!(results.last().unwrap().result_hash, probs.content_hash());
+        assert!(store.contains(&probs.content_hash()));
+
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_compile_conv_and_reshape() {
+        let store = temp_store("onnx-conv");
+        let x = random(&[1, 2, 6, 6], 1);
+        let w = random(&[3, 2, 3, 3], 2);
+        let b = random(&[3], 3);
+        let shape = TensorProto {
+            dims: vec![2],
+            data_type: INT64,
+            name: "shape".to_string(),
+            int64_data: vec![0, -1],
+            ..Default::default()
+        };
+        let pads = AttributeProto {
+            name: "pads".to_string(),
+            ints: vec![1, 1, 1, 1],
+            ..Default::default()
+        };
+
+        let bytes = model(
+            vec![
+                node("Conv", &["x", "w", "b"], "y", vec![pads]),
+                node("Reshape", &["y", "shape"], "flat", vec![]),
+            ],
+            vec![initializer("w", &w), initializer("b", &b), shape],
+            &["flat"],
+        );
+        let inputs = HashMap::from([("x".to_string(), store.put(&x).unwrap())]);
+        let program = compile_onnx(&bytes, &inputs, &store).unwrap();
+        assert_eq!(program.outputs[0].shape(), &[1, 3 * 6 * 6]);
+
+        let results = execute_work_unit_with_store(&program.operations, Some(&store)).unwrap();
+        let conv = apply_tensor_op(
+            &TensorOp::Conv2D {
+                stride: (1, 1),
+                padding: (1, 1),
+            },
+            &[x, w],
+        )
+        .unwrap();
+        let y = apply_tensor_op(
+            &TensorOp::Add,
+            &[conv, expand_bias(&b, &[3, 6, 6]).unwrap()],
+        )
+        .unwrap();
+        let flat = StoredTensor::new(vec![1, 108], y.data).unwrap();
+        assert_eq!(results.last().unwrap().result_hash, flat.content_hash());
+
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_graphs_are_reported() {
+        let store = temp_store("onnx-unsupported");
+        let x = store.put(&random(&[2, 4], 1)).unwrap();
+        let inputs = HashMap::from([("x".to_string(), x)]);
+
+        let bytes = model(
+            vec![
+                node("Sigmoid", &["x"], "s", vec![]),
+                node("Relu", &["s"], "r", vec![]),
+                node("Transpose", &["r"], "t", vec![]),
+            ],
+            vec![],
+            &["t"],
+        );
+        let err = compile_onnx(&bytes, &inputs, &store)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Sigmoid (node 's_node')"), "{}", err);
+        assert!(err.contains("Transpose (node 't_node')"), "{}", err);
+
+        // Supported operators with unsupported attributes name the node
+        let bytes = model(
+            vec![node("Softmax", &["x"], "p", vec![int_attr("axis", 0)])],
+            vec![],
+            &["p"],
+        );
+        let err = compile_onnx(&bytes, &inputs, &store)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Softmax node 'p_node'"), "{}", err);
+
+        assert!(compile_onnx(&bytes, &HashMap::new(), &store).is_err());
+        assert!(compile_onnx(b"not onnx", &inputs, &store).is_err());
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_float_initializers_must_fit_fp8() {
+        let store = temp_store("onnx-float");
+        let x = store.put(&random(&[2, 4], 1)).unwrap();
+        let inputs = HashMap::from([("x".to_string(), x)]);
+        let weights = |float_data: Vec<f32>| TensorProto {
+            dims: vec![4, 2],
+            data_type: FLOAT,
+            name: "w".to_string(),
+            float_data,
+            ..Default::default()
+        };
+        let compile = |w: TensorProto| {
+            let bytes = model(
+                vec![node("MatMul", &["x", "w"], "y", vec![])],
+                vec![w],
+                &["y"],
+            );
+            compile_onnx(&bytes, &inputs, &store)
+        };
+
+        let fits = vec![0.5, -1.25, 0.0, 2.0, 0.03125, -448.0, 8.0, 0.75];
+        assert!(compile(weights(fits.clone())).is_ok());
+
+        // Magnitudes that would flush to zero or saturate are rejected
+        for value in [1e-3, -1000.0, f32::NAN] {
+            let mut data = fits.clone();
+            data[2] = value;
+            let err = compile(weights(data)).unwrap_err().to_string();
+            assert!(err.contains("outside the FP8 E4M3 range"), "{}", err);
+        }
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_reshape_target() {
+        assert_eq!(reshape_target(&[2, 3, 4], &[0, -1]).unwrap(), vec![2, 12]);
+        assert_eq!(reshape_target(&[2, 3, 4], &[-1]).unwrap(), vec![24]);
+        assert!(reshape_target(&[2, 3, 4], &[5, -1]).is_err());
+
+        // Raw INT64 data must be whole values
+        let mut shape = TensorProto {
+            data_type: INT64,
+            raw_data: [0i64, -1].iter().flat_map(|v| v.to_le_bytes()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(int64_values(&shape).unwrap(), vec![0, -1]);
+        shape.raw_data.pop();
+        assert!(int64_values(&shape).is_err());
+    }
+}
@@ -1,8 +1,61 @@
-use crate::fp8::FP8;
-use demle_core::{DemleError, Result};
+use crate::fp8::{FP8, FP8E5M2};
+use demle_core::{proof::Proof, Activation, DemleError, Result};
 use rand::SeedableRng;
 use rand_distr::{Distribution, Normal};
 
+/// Result of an operation that reports more than its output hash
+#[derive(Debug, Clone)]
+pub struct OperationOutput {
+    /// Canonical hash of the operation output
+    pub result_hash: String,
+    pub flops: u64,
+    /// Named hashes of intermediate tensors, in execution order
+    pub intermediate_hashes: Vec<(String, String)>,
+    /// Named scalar values such as losses
+    pub metrics: Vec<(String, f64)>,
+}
+
+/// Seeded operands of an operation and the output its result hash commits to,
+/// which conformance vectors digest and hash tile by tile
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperationTensors {
+    /// Operands in generation order: FP8 values as their bits, f32 values and
+    /// token ids as little-endian f32 and u64
+    pub inputs: Vec<u8>,
+    /// Bytes whose Merkle root is the result hash
+    pub output: Vec<u8>,
+}
+
+impl OperationTensors {
+    pub fn add_fp8(&mut self, data: &[FP8]) {
+        self.inputs.extend(data.iter().map(|x| x.to_bits()));
+    }
+
+    pub fn add_gradient(&mut self, data: &[FP8E5M2]) {
+        self.inputs.extend(data.iter().map(|x| x.to_bits()));
+    }
+
+    pub fn add_f32(&mut self, data: &[f32]) {
+        self.inputs
+            .extend(data.iter().flat_map(|x| x.to_le_bytes()));
+    }
+
+    pub fn add_ids(&mut self, ids: &[usize]) {
+        self.inputs
+            .extend(ids.iter().flat_map(|&id| (id as u64).to_le_bytes()));
+    }
+
+    /// Output given as FP8 values
+    pub fn with_fp8_output(mut self, data: &[FP8]) -> Self {
+        self.output = data.iter().map(|x| x.to_bits()).collect();
+        self
+    }
+
+    pub fn result_hash(&self) -> String {
+        Proof::hash_operation_result(&self.output)
+    }
+}
+
 /// Generate random FP8 tensor with given shape and seed
 pub fn generate_random_tensor(shape: &[usize], seed: u64) -> Result<Vec<FP8>> {
     let total_size = shape.iter().product();
@@ -18,15 +71,45 @@ pub fn generate_random_tensor(shape: &[usize], seed: u64) -> Result<Vec<FP8>> {
     Ok(data)
 }
 
+/// Generate random FP8 E5M2 gradient tensor with given shape and seed
+pub fn generate_random_gradient(shape: &[usize], seed: u64) -> Result<Vec<FP8E5M2>> {
+    let total_size = shape.iter().product();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let normal = Normal::new(0.0, 1.0).map_err(|e| {
+        DemleError::ComputationError(format!("Failed to create normal distribution: {}", e))
+    })?;
+
+    let data: Vec<FP8E5M2> = (0..total_size)
+        .map(|_| FP8E5M2::from_f32(normal.sample(&mut rng) as f32))
+        .collect();
+
+    Ok(data)
+}
+
+/// Generate normally distributed f32 values with the given standard deviation,
+/// for tensors kept at full precision such as master weights
+pub fn generate_random_f32(shape: &[usize], std_dev: f32, seed: u64) -> Result<Vec<f32>> {
+    let total_size = shape.iter().product();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let normal = Normal::new(0.0, std_dev).map_err(|e| {
+        DemleError::ComputationError(format!("Failed to create normal distribution: {}", e))
+    })?;
+
+    Ok((0..total_size).map(|_| normal.sample(&mut rng)).collect())
+}
+
 /// Apply activation function to tensor
 pub fn apply_activation(data: &[FP8], activation: ActivationType) -> Vec<FP8> {
-    data.iter()
-        .map(|&x| match activation {
-            ActivationType::ReLU => relu(x),
-            ActivationType::GELU => gelu(x),
-            ActivationType::Swish => swish(x),
-        })
-        .collect()
+    data.iter().map(|&x| activate(x, activation)).collect()
+}
+
+/// Apply activation function to a single value
+pub fn activate(x: FP8, activation: ActivationType) -> FP8 {
+    match activation {
+        ActivationType::ReLU => relu(x),
+        ActivationType::GELU => gelu(x),
+        ActivationType::Swish => swish(x),
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -36,6 +119,16 @@ pub enum ActivationType {
     Swish,
 }
 
+impl From<Activation> for ActivationType {
+    fn from(activation: Activation) -> Self {
+        match activation {
+            Activation::ReLU => ActivationType::ReLU,
+            Activation::GELU => ActivationType::GELU,
+            Activation::Swish => ActivationType::Swish,
+        }
+    }
+}
+
 /// ReLU activation function
 fn relu(x: FP8) -> FP8 {
     let val = x.to_f32();
@@ -45,7 +138,7 @@ fn relu(x: FP8) -> FP8 {
 /// GELU activation function (approximation)
 fn gelu(x: FP8) -> FP8 {
     let val = x.to_f32();
-    let result = 0.5 * val * (1.0 + (0.7978845608 * (val + 0.044715 * val.powi(3))).tanh());
+    let result = 0.5 * val * (1.0 + (0.797_884_6 * (val + 0.044715 * val.powi(3))).tanh());
     FP8::from_f32(result)
 }
 
@@ -70,6 +163,95 @@ pub fn softmax(input: &[FP8]) -> Vec<FP8> {
     exp_values.iter().map(|&x| FP8::from_f32(x / sum)).collect()
 }
 
+/// Log-softmax function, computed as x - max - ln(sum(exp(x - max)))
+pub fn log_softmax(input: &[FP8]) -> Vec<FP8> {
+    let max_val = input
+        .iter()
+        .map(|x| x.to_f32())
+        .fold(f32::NEG_INFINITY, f32::max);
+
+    let log_sum = input
+        .iter()
+        .map(|x| (x.to_f32() - max_val).exp())
+        .sum::<f32>()
+        .ln();
+
+    input
+        .iter()
+        .map(|x| FP8::from_f32(x.to_f32() - max_val - log_sum))
+        .collect()
+}
+
+/// Layer normalization over the last dimension of a row-major (rows, dim) tensor,
+/// followed by a per-feature scale (gamma) and shift (beta)
+pub fn layer_norm(
+    input: &[FP8],
+    gamma: &[FP8],
+    beta: &[FP8],
+    dim: usize,
+    epsilon: f32,
+) -> Vec<FP8> {
+    let mut output = vec![FP8::zero(); input.len()];
+
+    for (row_in, row_out) in input.chunks(dim).zip(output.chunks_mut(dim)) {
+        let mean = row_in.iter().map(|x| x.to_f32()).sum::<f32>() / dim as f32;
+        let variance = row_in
+            .iter()
+            .map(|x| (x.to_f32() - mean).powi(2))
+            .sum::<f32>()
+            / dim as f32;
+        let inv_std = 1.0 / (variance + epsilon).sqrt();
+
+        for (i, (x, out)) in row_in.iter().zip(row_out.iter_mut()).enumerate() {
+            let normalized = (x.to_f32() - mean) * inv_std;
+            *out = FP8::from_f32(normalized * gamma[i].to_f32() + beta[i].to_f32());
+        }
+    }
+
+    output
+}
+
+/// Element-wise addition of two tensors of the same shape (residual connections)
+pub fn add_tensors(a: &[FP8], b: &[FP8]) -> Vec<FP8> {
+    a.iter().zip(b.iter()).map(|(&x, &y)| x + y).collect()
+}
+
+/// Element-wise multiplication of two tensors of the same shape (gating)
+pub fn mul_tensors(a: &[FP8], b: &[FP8]) -> Vec<FP8> {
+    a.iter().zip(b.iter()).map(|(&x, &y)| x * y).collect()
+}
+
+/// Hash the raw FP8 bits of a tensor
+pub fn hash_tensor(data: &[FP8]) -> String {
+    let bytes: Vec<u8> = data.iter().map(|fp8| fp8.to_bits()).collect();
+    Proof::hash_operation_result(&bytes)
+}
+
+/// Product of operation sizes, rejecting shapes whose FLOP count overflows
+pub fn flops_product(factors: &[usize]) -> Result<u64> {
+    factors.iter().try_fold(1u64, |product, &factor| {
+        product
+            .checked_mul(factor as u64)
+            .ok_or_else(|| flops_overflow(factors))
+    })
+}
+
+/// Sum of FLOP counts, rejecting totals that overflow
+pub fn flops_sum(terms: &[u64]) -> Result<u64> {
+    terms.iter().try_fold(0u64, |sum, &term| {
+        sum.checked_add(term).ok_or_else(|| {
+            DemleError::ValidationError("FLOP count overflows a 64-bit counter".to_string())
+        })
+    })
+}
+
+fn flops_overflow(factors: &[usize]) -> DemleError {
+    DemleError::ValidationError(format!(
+        "FLOP count of sizes {:?} overflows a 64-bit counter",
+        factors
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,4 +303,47 @@ mod tests {
         assert!(output[2].to_f32() > output[1].to_f32());
         assert!(output[1].to_f32() > output[0].to_f32());
     }
+
+    #[test]
+    fn test_log_softmax() {
+        let input = vec![FP8::from_f32(1.0), FP8::from_f32(2.0), FP8::from_f32(3.0)];
+
+        let output = log_softmax(&input);
+
+        // All log-probabilities are negative and ordered like the inputs
+        assert!(output.iter().all(|x| x.to_f32() < 0.0));
+        assert!(output[2].to_f32() > output[1].to_f32());
+        assert!(output[1].to_f32() > output[0].to_f32());
+        assert!((output[2].to_f32() + 0.41).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_layer_norm() {
+        let input: Vec<FP8> = [1.0, 2.0, 3.0, 4.0, -2.0, -2.0, 2.0, 2.0]
+            .iter()
+            .map(|&x| FP8::from_f32(x))
+            .collect();
+        let gamma = vec![FP8::one(); 4];
+        let beta = vec![FP8::zero(); 4];
+
+        let output = layer_norm(&input, &gamma, &beta, 4, 1e-5);
+        assert_eq!(output.len(), 8);
+
+        // Each row is normalized to zero mean and (roughly) unit variance
+        for row in output.chunks(4) {
+            let mean: f32 = row.iter().map(|x| x.to_f32()).sum::<f32>() / 4.0;
+            assert!(mean.abs() < 0.15);
+        }
+        assert!((output[4].to_f32() + 1.0).abs() < 0.1);
+        assert!((output[7].to_f32() - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_flops_overflow() {
+        assert_eq!(flops_product(&[2, 3, 4]).unwrap(), 24);
+        assert_eq!(flops_sum(&[1, 2, 3]).unwrap(), 6);
+
+        assert!(flops_product(&[2, 1 << 22, 1 << 22, 1 << 22]).is_err());
+        assert!(flops_sum(&[u64::MAX, 1]).is_err());
+    }
 }
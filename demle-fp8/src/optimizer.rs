@@ -0,0 +1,422 @@
+use crate::fp8::FP8E5M2;
+use crate::operations::{
+    flops_product, generate_random_f32, generate_random_gradient, OperationOutput, OperationTensors,
+};
+use crate::quantize::quantize;
+use demle_core::{proof::Proof, DemleError, MasterWeights, NumericFormat, Optimizer, Result};
+use half::bf16;
+
+/// Master copy of the weights in the precision the optimizer updates
+#[derive(Debug, Clone, PartialEq)]
+pub enum MasterParams {
+    F32(Vec<f32>),
+    BF16(Vec<bf16>),
+}
+
+impl MasterParams {
+    /// Store f32 values at the given master precision
+    pub fn from_f32(values: Vec<f32>, precision: MasterWeights) -> Self {
+        match precision {
+            MasterWeights::F32 => MasterParams::F32(values),
+            MasterWeights::BF16 => {
+                MasterParams::BF16(values.into_iter().map(bf16::from_f32).collect())
+            }
+        }
+    }
+
+    pub fn to_f32(&self) -> Vec<f32> {
+        match self {
+            MasterParams::F32(values) => values.clone(),
+            MasterParams::BF16(values) => values.iter().map(|v| v.to_f32()).collect(),
+        }
+    }
+
+    /// Little-endian bytes of the master weights at their storage precision
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        match self {
+            MasterParams::F32(values) => f32_bytes(values),
+            MasterParams::BF16(values) => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+}
+
+/// Optimizer state carried between steps, kept in f32
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptimizerState {
+    SgdMomentum {
+        momentum_buffer: Vec<f32>,
+    },
+    AdamW {
+        exp_avg: Vec<f32>,
+        exp_avg_sq: Vec<f32>,
+    },
+}
+
+/// Check the optimizer hyperparameters: AdamW step numbers start at 1
+pub fn validate_optimizer(optimizer: &Optimizer) -> Result<()> {
+    if let Optimizer::AdamW { step: 0, .. } = optimizer {
+        return Err(DemleError::ValidationError(
+            "AdamW step numbers start at 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Apply one optimizer step to the master weights and state.
+///
+/// The update is computed in f32 and rounded back to the master precision.
+pub fn optimizer_step(
+    params: &mut MasterParams,
+    grads: &[f32],
+    state: &mut OptimizerState,
+    optimizer: &Optimizer,
+) -> Result<()> {
+    validate_optimizer(optimizer)?;
+    let mut weights = params.to_f32();
+    if weights.len() != grads.len() {
+        return Err(DemleError::ValidationError(format!(
+            "{} parameters but {} gradients",
+            weights.len(),
+            grads.len()
+        )));
+    }
+
+    match (optimizer, state) {
+        (
+            Optimizer::SgdMomentum {
+                learning_rate,
+                momentum,
+                weight_decay,
+            },
+            OptimizerState::SgdMomentum { momentum_buffer },
+        ) => {
+            for ((w, &g), buf) in weights
+                .iter_mut()
+                .zip(grads)
+                .zip(momentum_buffer.iter_mut())
+            {
+                let g = g + weight_decay * *w;
+                *buf = momentum * *buf + g;
+                *w -= learning_rate * *buf;
+            }
+        }
+        (
+            Optimizer::AdamW {
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+                step,
+            },
+            OptimizerState::AdamW {
+                exp_avg,
+                exp_avg_sq,
+            },
+        ) => {
+            // Beyond i32::MAX steps the powers have long since underflowed
+            let step = (*step).min(i32::MAX as u64) as i32;
+            let bias_correction1 = 1.0 - beta1.powi(step);
+            let bias_correction2 = 1.0 - beta2.powi(step);
+
+            for (((w, &g), m), v) in weights
+                .iter_mut()
+                .zip(grads)
+                .zip(exp_avg.iter_mut())
+                .zip(exp_avg_sq.iter_mut())
+            {
+                // Decoupled weight decay
+                *w -= learning_rate * weight_decay * *w;
+
+                *m = beta1 * *m + (1.0 - beta1) * g;
+                *v = beta2 * *v + (1.0 - beta2) * g * g;
+
+                let m_hat = *m / bias_correction1;
+                let v_hat = *v / bias_correction2;
+                *w -= learning_rate * m_hat / (v_hat.sqrt() + epsilon);
+            }
+        }
+        _ => {
+            return Err(DemleError::ValidationError(
+                "Optimizer state does not match the optimizer".to_string(),
+            ))
+        }
+    }
+
+    let precision = match params {
+        MasterParams::F32(_) => MasterWeights::F32,
+        MasterParams::BF16(_) => MasterWeights::BF16,
+    };
+    *params = MasterParams::from_f32(weights, precision);
+    Ok(())
+}
+
+/// Execute an optimizer step on seeded master weights, E5M2 gradients and
+/// optimizer state, then re-quantize the updated weights to scaled FP8
+pub fn execute_optimizer_step(
+    num_params: usize,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_optimizer_step(num_params, optimizer, master_weights, seed)?.0)
+}
+
+/// Seeded weights, gradients and optimizer state and the output of an
+/// optimizer step
+pub fn optimizer_step_tensors(
+    num_params: usize,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_optimizer_step(num_params, optimizer, master_weights, seed)?.1)
+}
+
+fn run_optimizer_step(
+    num_params: usize,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    let mut tensors = OperationTensors::default();
+    let weights = generate_random_f32(&[num_params], 1.0, seed)?;
+    tensors.add_f32(&weights);
+    let mut params = MasterParams::from_f32(weights, master_weights);
+    let grads = generate_random_gradient(&[num_params], seed.wrapping_add(1))?;
+    tensors.add_gradient(&grads);
+    let grads: Vec<f32> = grads.iter().map(|g: &FP8E5M2| g.to_f32()).collect();
+
+    let mut state = match optimizer {
+        Optimizer::SgdMomentum { .. } => OptimizerState::SgdMomentum {
+            momentum_buffer: generate_random_f32(&[num_params], 0.1, seed.wrapping_add(2))?,
+        },
+        Optimizer::AdamW { .. } => OptimizerState::AdamW {
+            exp_avg: generate_random_f32(&[num_params], 0.1, seed.wrapping_add(2))?,
+            exp_avg_sq: generate_random_f32(&[num_params], 0.1, seed.wrapping_add(3))?
+                .into_iter()
+                .map(|x| x * x)
+                .collect(),
+        },
+    };
+
+    match &state {
+        OptimizerState::SgdMomentum { momentum_buffer } => tensors.add_f32(momentum_buffer),
+        OptimizerState::AdamW {
+            exp_avg,
+            exp_avg_sq,
+        } => {
+            tensors.add_f32(exp_avg);
+            tensors.add_f32(exp_avg_sq);
+        }
+    }
+
+    optimizer_step(&mut params, &grads, &mut state, optimizer)?;
+
+    Ok(optimizer_output(
+        tensors,
+        &params,
+        &state,
+        optimizer_step_flops(num_params, optimizer)?,
+    ))
+}
+
+/// Re-quantize updated master weights to scaled FP8 and hash them together
+/// with the master weights and optimizer state
+pub(crate) fn optimizer_output(
+    tensors: OperationTensors,
+    params: &MasterParams,
+    state: &OptimizerState,
+    flops: u64,
+) -> (OperationOutput, OperationTensors) {
+    let fp8_weights = quantize(&params.to_f32(), NumericFormat::FP8E4M3);
+
+    let mut intermediate_hashes = vec![(
+        "master_weights".to_string(),
+        Proof::hash_operation_result(&params.to_le_bytes()),
+    )];
+    let mut state_bytes = Vec::new();
+    match state {
+        OptimizerState::SgdMomentum { momentum_buffer } => {
+            let bytes = f32_bytes(momentum_buffer);
+            intermediate_hashes.push((
+                "momentum_buffer".to_string(),
+                Proof::hash_operation_result(&bytes),
+            ));
+            state_bytes.extend(bytes);
+        }
+        OptimizerState::AdamW {
+            exp_avg,
+            exp_avg_sq,
+        } => {
+            for (name, values) in [("exp_avg", exp_avg), ("exp_avg_sq", exp_avg_sq)] {
+                let bytes = f32_bytes(values);
+                intermediate_hashes.push((name.to_string(), Proof::hash_operation_result(&bytes)));
+                state_bytes.extend(bytes);
+            }
+        }
+    }
+    intermediate_hashes.push((
+        "fp8_weights".to_string(),
+        Proof::hash_operation_result(&fp8_weights.data.to_le_bytes()),
+    ));
+
+    // Canonical output: FP8 weights, their scale, master weights and optimizer state
+    let mut result_bytes = fp8_weights.to_bytes();
+    result_bytes.extend(params.to_le_bytes());
+    result_bytes.extend(state_bytes);
+
+    let output = OperationOutput {
+        result_hash: Proof::hash_operation_result(&result_bytes),
+        flops,
+        intermediate_hashes,
+        metrics: Vec::new(),
+    };
+    (
+        output,
+        OperationTensors {
+            output: result_bytes,
+            ..tensors
+        },
+    )
+}
+
+/// FLOP count of an optimizer step, including the re-quantization to FP8
+pub fn optimizer_step_flops(num_params: usize, optimizer: &Optimizer) -> Result<u64> {
+    let per_param = match optimizer {
+        // Weight decay, momentum update and parameter update
+        Optimizer::SgdMomentum { .. } => 6,
+        // Weight decay, both moment updates, bias correction, sqrt, epsilon and update
+        Optimizer::AdamW { .. } => 16,
+    };
+    // Absolute-max reduction and scaling for the FP8 re-quantization
+    let quantize = 2;
+
+    flops_product(&[num_params, per_param + quantize])
+}
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sgd() -> Optimizer {
+        Optimizer::SgdMomentum {
+            learning_rate: 0.1,
+            momentum: 0.9,
+            weight_decay: 0.01,
+        }
+    }
+
+    fn adamw(step: u64) -> Optimizer {
+        Optimizer::AdamW {
+            learning_rate: 0.01,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            weight_decay: 0.1,
+            step,
+        }
+    }
+
+    #[test]
+    fn test_sgd_momentum_step() {
+        let mut params = MasterParams::F32(vec![1.0, -2.0]);
+        let mut state = OptimizerState::SgdMomentum {
+            momentum_buffer: vec![0.5, 0.0],
+        };
+
+        optimizer_step(&mut params, &[0.2, 1.0], &mut state, &sgd()).unwrap();
+
+        // g = 0.2 + 0.01 * 1.0 = 0.21; buf = 0.45 + 0.21 = 0.66; w = 1.0 - 0.066
+        // g = 1.0 - 0.02 = 0.98; buf = 0.98; w = -2.0 - 0.098
+        let weights = params.to_f32();
+        assert!((weights[0] - 0.934).abs() < 1e-6);
+        assert!((weights[1] + 2.098).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_adamw_first_step() {
+        let mut params = MasterParams::F32(vec![1.0, 1.0]);
+        let mut state = OptimizerState::AdamW {
+            exp_avg: vec![0.0; 2],
+            exp_avg_sq: vec![0.0; 2],
+        };
+
+        optimizer_step(&mut params, &[0.5, -3.0], &mut state, &adamw(1)).unwrap();
+
+        // After bias correction the first step moves each weight by lr * sign(g),
+        // on top of the decoupled decay 1.0 - 0.01 * 0.1
+        let weights = params.to_f32();
+        assert!((weights[0] - (0.999 - 0.01)).abs() < 1e-5);
+        assert!((weights[1] - (0.999 + 0.01)).abs() < 1e-5);
+
+        assert!(optimizer_step(&mut params, &[0.5, -3.0], &mut state, &adamw(0)).is_err());
+        assert!(optimizer_step(&mut params, &[0.5, -3.0], &mut state, &sgd()).is_err());
+    }
+
+    #[test]
+    fn test_adamw_late_steps() {
+        // Steps past i32::MAX keep a bias correction of 1
+        let run = |step: u64| {
+            let mut params = MasterParams::F32(vec![1.0, 1.0]);
+            let mut state = OptimizerState::AdamW {
+                exp_avg: vec![0.0; 2],
+                exp_avg_sq: vec![0.0; 2],
+            };
+            optimizer_step(&mut params, &[0.5, -3.0], &mut state, &adamw(step)).unwrap();
+            params.to_f32()
+        };
+        assert_eq!(run(u64::MAX), run(1 << 20));
+        assert_eq!(run(i32::MAX as u64 + 1), run(1 << 20));
+    }
+
+    #[test]
+    fn test_bf16_master_weights() {
+        let mut params = MasterParams::from_f32(vec![1.0], MasterWeights::BF16);
+        let mut state = OptimizerState::SgdMomentum {
+            momentum_buffer: vec![0.0],
+        };
+        let optimizer = Optimizer::SgdMomentum {
+            learning_rate: 1e-4,
+            momentum: 0.0,
+            weight_decay: 0.0,
+        };
+
+        // An update below half a BF16 ulp at 1.0 (2^-8) is lost to rounding
+        optimizer_step(&mut params, &[1.0], &mut state, &optimizer).unwrap();
+        assert_eq!(params.to_f32(), vec![1.0]);
+        assert_eq!(params.to_le_bytes().len(), 2);
+    }
+
+    #[test]
+    fn test_optimizer_step_execution() {
+        for optimizer in [sgd(), adamw(3)] {
+            let f32_step = execute_optimizer_step(256, &optimizer, MasterWeights::F32, 42).unwrap();
+            let again = execute_optimizer_step(256, &optimizer, MasterWeights::F32, 42).unwrap();
+            assert_eq!(f32_step.result_hash, again.result_hash);
+            assert_eq!(
+                f32_step.flops,
+                optimizer_step_flops(256, &optimizer).unwrap()
+            );
+
+            let bf16_step =
+                execute_optimizer_step(256, &optimizer, MasterWeights::BF16, 42).unwrap();
+            assert_ne!(f32_step.result_hash, bf16_step.result_hash);
+        }
+
+        let adam = execute_optimizer_step(16, &adamw(1), MasterWeights::F32, 1).unwrap();
+        let names: Vec<&str> = adam
+            .intermediate_hashes
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["master_weights", "exp_avg", "exp_avg_sq", "fp8_weights"]
+        );
+    }
+}
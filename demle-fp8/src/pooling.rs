@@ -0,0 +1,267 @@
+use crate::convolution::window_output_size;
+use crate::fp8::FP8;
+use crate::operations::{flops_product, flops_sum, generate_random_tensor, OperationTensors};
+use demle_core::{DemleError, PoolType, Result};
+
+/// Execute 2D max or average pooling
+pub fn execute_pooling2d(
+    input_shape: (usize, usize, usize, usize), // (batch, channels, height, width)
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<(String, u64)> {
+    let tensors = pooling2d_tensors(input_shape, pool_type, kernel_size, stride, padding, seed)?;
+
+    let flops = pooling2d_flops(input_shape, pool_type, kernel_size, stride, padding)?;
+
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded input and output of a 2D pooling operation
+pub fn pooling2d_tensors(
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    seed: u64,
+) -> Result<OperationTensors> {
+    let (batch, channels, height, width) = input_shape;
+    let input = generate_random_tensor(&[batch, channels, height, width], seed)?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&input);
+
+    let output = pool2d(&input, input_shape, pool_type, kernel_size, stride, padding)?;
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// Resolve the effective window, stride and padding of a pooling operation.
+/// Global pooling covers the whole spatial extent with a single window.
+fn pooling_window(
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> ((usize, usize), (usize, usize), (usize, usize)) {
+    let (_, _, height, width) = input_shape;
+    match pool_type {
+        PoolType::GlobalMax | PoolType::GlobalAverage => ((height, width), (1, 1), (0, 0)),
+        PoolType::Max | PoolType::Average => (kernel_size, stride, padding),
+    }
+}
+
+/// Output spatial dimensions of a pooling operation, rejecting windows that do
+/// not fit in the padded input
+pub fn pooling2d_output_size(
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<(usize, usize)> {
+    let (_, _, height, width) = input_shape;
+    let ((kh, kw), (sh, sw), (ph, pw)) =
+        pooling_window(input_shape, pool_type, kernel_size, stride, padding);
+
+    window_output_size(height, kh, sh, ph)
+        .zip(window_output_size(width, kw, sw, pw))
+        .ok_or_else(|| {
+            DemleError::ValidationError(format!(
+                "Pooling window {}x{} exceeds input {}x{} padded by {}x{}",
+                kh, kw, height, width, ph, pw
+            ))
+        })
+}
+
+/// Check that a pooling window and stride are non-zero and that the window
+/// fits in the padded input
+pub fn validate_pooling2d(
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<()> {
+    let ((kh, kw), (sh, sw), _) =
+        pooling_window(input_shape, pool_type, kernel_size, stride, padding);
+
+    if kh == 0 || kw == 0 || sh == 0 || sw == 0 {
+        return Err(DemleError::ValidationError(
+            "Pooling kernel size and stride must be non-zero".to_string(),
+        ));
+    }
+    pooling2d_output_size(input_shape, pool_type, kernel_size, stride, padding).map(|_| ())
+}
+
+/// 2D pooling over an NCHW tensor.
+///
+/// Padded positions never win a max and count as zeros for averages, which are
+/// always divided by the full window size.
+pub fn pool2d(
+    input: &[FP8],
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<Vec<FP8>> {
+    validate_pooling2d(input_shape, pool_type, kernel_size, stride, padding)?;
+    let (batch, channels, height, width) = input_shape;
+    let ((kh, kw), (sh, sw), (ph, pw)) =
+        pooling_window(input_shape, pool_type, kernel_size, stride, padding);
+
+    let (oh, ow) = pooling2d_output_size(input_shape, pool_type, kernel_size, stride, padding)?;
+    let is_max = matches!(pool_type, PoolType::Max | PoolType::GlobalMax);
+    let window_size = (kh * kw) as f32;
+
+    let mut output = vec![FP8::zero(); batch * channels * oh * ow];
+
+    for b in 0..batch {
+        for c in 0..channels {
+            let plane = &input[(b * channels + c) * height * width..][..height * width];
+
+            for y in 0..oh {
+                for x in 0..ow {
+                    let mut max_val = f32::NEG_INFINITY;
+                    let mut sum = 0.0f32;
+
+                    for ky in 0..kh {
+                        for kx in 0..kw {
+                            let input_y = y * sh + ky;
+                            let input_x = x * sw + kx;
+
+                            // Skip padding
+                            if input_y < ph || input_x < pw {
+                                continue;
+                            }
+                            let (input_y, input_x) = (input_y - ph, input_x - pw);
+                            if input_y >= height || input_x >= width {
+                                continue;
+                            }
+
+                            let value = plane[input_y * width + input_x].to_f32();
+                            max_val = max_val.max(value);
+                            sum += value;
+                        }
+                    }
+
+                    let value = if is_max {
+                        // A window made only of padding pools to zero
+                        if max_val.is_finite() {
+                            max_val
+                        } else {
+                            0.0
+                        }
+                    } else {
+                        sum / window_size
+                    };
+
+                    let output_idx = ((b * channels + c) * oh + y) * ow + x;
+                    output[output_idx] = FP8::from_f32(value);
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// FLOP count of pooling: one compare or add per window element, plus the
+/// division for averages
+pub fn pooling2d_flops(
+    input_shape: (usize, usize, usize, usize),
+    pool_type: PoolType,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Result<u64> {
+    let (batch, channels, _, _) = input_shape;
+    let ((kh, kw), _, _) = pooling_window(input_shape, pool_type, kernel_size, stride, padding);
+    let (oh, ow) = pooling2d_output_size(input_shape, pool_type, kernel_size, stride, padding)?;
+
+    let window = flops_product(&[batch, channels, oh, ow, kh, kw])?;
+    match pool_type {
+        PoolType::Max | PoolType::GlobalMax => Ok(window),
+        PoolType::Average | PoolType::GlobalAverage => {
+            flops_sum(&[window, flops_product(&[batch, channels, oh, ow])?])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_fp8(values: &[f32]) -> Vec<FP8> {
+        values.iter().map(|&v| FP8::from_f32(v)).collect()
+    }
+
+    #[test]
+    fn test_max_and_average_pooling() {
+        let input = to_fp8(&[
+            1.0, 2.0, 3.0, 4.0, //
+            5.0, 6.0, 7.0, 8.0, //
+            -1.0, -2.0, -3.0, -4.0, //
+            0.5, 0.5, 1.0, 1.0,
+        ]);
+        let shape = (1, 1, 4, 4);
+
+        let max = pool2d(&input, shape, PoolType::Max, (2, 2), (2, 2), (0, 0)).unwrap();
+        assert_eq!(max, to_fp8(&[6.0, 8.0, 0.5, 1.0]));
+
+        let avg = pool2d(&input, shape, PoolType::Average, (2, 2), (2, 2), (0, 0)).unwrap();
+        assert_eq!(avg, to_fp8(&[3.5, 5.5, -0.5, -1.25]));
+
+        // Padding: every window of a 3x3 stride-1 pool sees the centre of a 2x2 input
+        let small = to_fp8(&[1.0, 2.0, 3.0, 4.0]);
+        let padded = pool2d(&small, (1, 1, 2, 2), PoolType::Max, (3, 3), (1, 1), (1, 1)).unwrap();
+        assert_eq!(padded, to_fp8(&[4.0, 4.0, 4.0, 4.0]));
+    }
+
+    #[test]
+    fn test_global_pooling() {
+        let input = to_fp8(&[1.0, 2.0, 3.0, 4.0, -1.0, -2.0, -3.0, -4.0]);
+        let shape = (1, 2, 2, 2);
+
+        // Kernel, stride and padding are ignored for global pooling
+        let max = pool2d(&input, shape, PoolType::GlobalMax, (0, 0), (0, 0), (9, 9)).unwrap();
+        assert_eq!(max, to_fp8(&[4.0, -1.0]));
+
+        let avg = pool2d(
+            &input,
+            shape,
+            PoolType::GlobalAverage,
+            (0, 0),
+            (0, 0),
+            (0, 0),
+        )
+        .unwrap();
+        assert_eq!(avg, to_fp8(&[2.5, -2.5]));
+    }
+
+    #[test]
+    fn test_pooling_execution() {
+        let shape = (2, 4, 8, 8);
+
+        let (hash, flops) =
+            execute_pooling2d(shape, PoolType::Max, (2, 2), (2, 2), (0, 0), 42).unwrap();
+        assert!(!hash.is_empty());
+        assert_eq!(flops, 2 * 4 * 4 * 4 * 4);
+
+        let again = execute_pooling2d(shape, PoolType::Max, (2, 2), (2, 2), (0, 0), 42).unwrap();
+        assert_eq!(hash, again.0);
+
+        let avg = execute_pooling2d(shape, PoolType::Average, (2, 2), (2, 2), (0, 0), 42).unwrap();
+        assert_ne!(hash, avg.0);
+    }
+
+    #[test]
+    fn test_pooling_invalid_window() {
+        let result = execute_pooling2d((1, 1, 2, 2), PoolType::Max, (3, 3), (1, 1), (0, 0), 1);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,458 @@
+use crate::fp8::{FP8, FP8E5M2};
+use crate::operations::{flops_product, generate_random_f32, OperationOutput, OperationTensors};
+use demle_core::{proof::Proof, DemleError, NumericFormat, Result};
+use half::slice::HalfFloatSliceExt;
+use half::{bf16, f16};
+use rayon::prelude::*;
+
+#[cfg(feature = "cuda")]
+use candle_core::{DType, Device, Tensor};
+
+/// Elements handled per parallel task by the bulk conversion kernels
+const CHUNK_SIZE: usize = 4096;
+
+/// Tensor elements in their storage format
+#[derive(Debug, Clone, PartialEq)]
+pub enum TensorData {
+    F32(Vec<f32>),
+    BF16(Vec<bf16>),
+    F16(Vec<f16>),
+    FP8E4M3(Vec<FP8>),
+    FP8E5M2(Vec<FP8E5M2>),
+    INT8(Vec<i8>),
+}
+
+impl TensorData {
+    pub fn format(&self) -> NumericFormat {
+        match self {
+            TensorData::F32(_) => NumericFormat::F32,
+            TensorData::BF16(_) => NumericFormat::BF16,
+            TensorData::F16(_) => NumericFormat::F16,
+            TensorData::FP8E4M3(_) => NumericFormat::FP8E4M3,
+            TensorData::FP8E5M2(_) => NumericFormat::FP8E5M2,
+            TensorData::INT8(_) => NumericFormat::INT8,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            TensorData::F32(v) => v.len(),
+            TensorData::BF16(v) => v.len(),
+            TensorData::F16(v) => v.len(),
+            TensorData::FP8E4M3(v) => v.len(),
+            TensorData::FP8E5M2(v) => v.len(),
+            TensorData::INT8(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Little-endian storage bytes of the elements
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        match self {
+            TensorData::F32(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            TensorData::BF16(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            TensorData::F16(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            TensorData::FP8E4M3(v) => v.iter().map(|x| x.to_bits()).collect(),
+            TensorData::FP8E5M2(v) => v.iter().map(|x| x.to_bits()).collect(),
+            TensorData::INT8(v) => v.iter().map(|&x| x as u8).collect(),
+        }
+    }
+
+    /// Parse little-endian storage bytes of elements in a format
+    pub fn from_le_bytes(format: NumericFormat, bytes: &[u8]) -> Result<Self> {
+        let size = format_size(format);
+        if !bytes.len().is_multiple_of(size) {
+            return Err(DemleError::SerializationError(format!(
+                "{} bytes is not a whole number of {:?} elements",
+                bytes.len(),
+                format
+            )));
+        }
+
+        Ok(match format {
+            NumericFormat::F32 => TensorData::F32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            ),
+            NumericFormat::BF16 => TensorData::BF16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| bf16::from_le_bytes([b[0], b[1]]))
+                    .collect(),
+            ),
+            NumericFormat::F16 => TensorData::F16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| f16::from_le_bytes([b[0], b[1]]))
+                    .collect(),
+            ),
+            NumericFormat::FP8E4M3 => {
+                TensorData::FP8E4M3(bytes.iter().map(|&b| FP8::from_bits(b)).collect())
+            }
+            NumericFormat::FP8E5M2 => {
+                TensorData::FP8E5M2(bytes.iter().map(|&b| FP8E5M2::from_bits(b)).collect())
+            }
+            NumericFormat::INT8 => TensorData::INT8(bytes.iter().map(|&b| b as i8).collect()),
+        })
+    }
+}
+
+/// A tensor in some numeric format; element i represents `data[i] * scale`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedTensor {
+    pub data: TensorData,
+    pub scale: f32,
+}
+
+impl QuantizedTensor {
+    pub fn format(&self) -> NumericFormat {
+        self.data.format()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Little-endian storage bytes of the elements, followed by the scale
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.data.to_le_bytes();
+        bytes.extend(self.scale.to_le_bytes());
+        bytes
+    }
+}
+
+/// Bytes per element of a format
+pub fn format_size(format: NumericFormat) -> usize {
+    match format {
+        NumericFormat::F32 => 4,
+        NumericFormat::BF16 | NumericFormat::F16 => 2,
+        NumericFormat::FP8E4M3 | NumericFormat::FP8E5M2 | NumericFormat::INT8 => 1,
+    }
+}
+
+/// Largest stored magnitude of a scaled format, or None for formats that are
+/// stored unscaled
+pub fn format_max(format: NumericFormat) -> Option<f32> {
+    match format {
+        NumericFormat::FP8E4M3 => Some(FP8::MAX),
+        NumericFormat::FP8E5M2 => Some(FP8E5M2::MAX),
+        NumericFormat::INT8 => Some(127.0),
+        NumericFormat::F32 | NumericFormat::BF16 | NumericFormat::F16 => None,
+    }
+}
+
+/// Largest absolute value, ignoring NaNs
+pub fn absmax(values: &[f32]) -> f32 {
+    values
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| chunk.iter().fold(0.0f32, |acc, v| acc.max(v.abs())))
+        .reduce(|| 0.0, f32::max)
+}
+
+/// Convert f32 values to a format. FP8 and INT8 use a per-tensor scale that
+/// maps the largest magnitude to the format maximum; INT8 is symmetric and
+/// rounds half to even. Float formats round to nearest even with scale 1.
+pub fn quantize(values: &[f32], format: NumericFormat) -> QuantizedTensor {
+    let scale = match format_max(format) {
+        Some(max) => {
+            let amax = absmax(values);
+            if amax > 0.0 {
+                amax / max
+            } else {
+                1.0
+            }
+        }
+        None => 1.0,
+    };
+
+    let data = match format {
+        NumericFormat::F32 => TensorData::F32(values.to_vec()),
+        NumericFormat::BF16 => {
+            let mut out = vec![bf16::ZERO; values.len()];
+            convert_chunks(&mut out, values, |dst, src| dst.convert_from_f32_slice(src));
+            TensorData::BF16(out)
+        }
+        NumericFormat::F16 => {
+            let mut out = vec![f16::ZERO; values.len()];
+            convert_chunks(&mut out, values, |dst, src| dst.convert_from_f32_slice(src));
+            TensorData::F16(out)
+        }
+        NumericFormat::FP8E4M3 => TensorData::FP8E4M3(
+            values
+                .par_iter()
+                .map(|&v| FP8::from_f32(v / scale))
+                .collect(),
+        ),
+        NumericFormat::FP8E5M2 => TensorData::FP8E5M2(
+            values
+                .par_iter()
+                .map(|&v| FP8E5M2::from_f32(v / scale))
+                .collect(),
+        ),
+        NumericFormat::INT8 => TensorData::INT8(
+            values
+                .par_iter()
+                .map(|&v| (v / scale).round_ties_even().clamp(-127.0, 127.0) as i8)
+                .collect(),
+        ),
+    };
+
+    QuantizedTensor { data, scale }
+}
+
+/// Convert a tensor back to f32, applying its scale
+pub fn dequantize(tensor: &QuantizedTensor) -> Vec<f32> {
+    let scale = tensor.scale;
+    match &tensor.data {
+        TensorData::F32(v) => v.clone(),
+        TensorData::BF16(v) => {
+            let mut out = vec![0.0f32; v.len()];
+            convert_chunks(&mut out, v, |dst, src| src.convert_to_f32_slice(dst));
+            out
+        }
+        TensorData::F16(v) => {
+            let mut out = vec![0.0f32; v.len()];
+            convert_chunks(&mut out, v, |dst, src| src.convert_to_f32_slice(dst));
+            out
+        }
+        TensorData::FP8E4M3(v) => v.par_iter().map(|x| x.to_f32() * scale).collect(),
+        TensorData::FP8E5M2(v) => v.par_iter().map(|x| x.to_f32() * scale).collect(),
+        TensorData::INT8(v) => v.par_iter().map(|&x| x as f32 * scale).collect(),
+    }
+}
+
+/// Convert a tensor to another format, rescaling for the target range
+pub fn requantize(tensor: &QuantizedTensor, format: NumericFormat) -> QuantizedTensor {
+    quantize(&dequantize(tensor), format)
+}
+
+/// Run a slice conversion over matching chunks of the output and input in parallel
+fn convert_chunks<D: Send, S: Sync>(
+    dst: &mut [D],
+    src: &[S],
+    convert: impl Fn(&mut [D], &[S]) + Sync,
+) {
+    dst.par_chunks_mut(CHUNK_SIZE)
+        .zip(src.par_chunks(CHUNK_SIZE))
+        .for_each(|(d, s)| convert(d, s));
+}
+
+/// Upload a tensor to a candle device. BF16, F16 and F32 are uploaded as
+/// stored; FP8 and INT8 have no candle dtype and are widened to BF16.
+#[cfg(feature = "cuda")]
+pub fn to_candle(tensor: &QuantizedTensor, shape: &[usize], device: &Device) -> Result<Tensor> {
+    let shape = shape.to_vec();
+    let tensor = match &tensor.data {
+        TensorData::F32(v) => Tensor::from_slice(v, shape, device)?,
+        TensorData::BF16(v) => Tensor::from_slice(v, shape, device)?,
+        TensorData::F16(v) => Tensor::from_slice(v, shape, device)?,
+        _ => match quantize(&dequantize(tensor), NumericFormat::BF16).data {
+            TensorData::BF16(v) => Tensor::from_vec(v, shape, device)?,
+            _ => unreachable!("BF16 quantization produces BF16 data"),
+        },
+    };
+    Ok(tensor)
+}
+
+/// Download a candle tensor and convert it to a format
+#[cfg(feature = "cuda")]
+pub fn from_candle(tensor: &Tensor, format: NumericFormat) -> Result<QuantizedTensor> {
+    let values: Vec<f32> = tensor.to_dtype(DType::F32)?.flatten_all()?.to_vec1()?;
+    Ok(quantize(&values, format))
+}
+
+/// Execute a calibration-style conversion: quantize seeded f32 values to the
+/// source format, requantize to the target format and measure the error
+/// against the original values
+pub fn execute_quantize(
+    num_elements: usize,
+    source: NumericFormat,
+    target: NumericFormat,
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_quantize(num_elements, source, target, seed)?.0)
+}
+
+/// Seeded f32 values and the target-format tensor of a quantize operation
+pub fn quantize_tensors(
+    num_elements: usize,
+    source: NumericFormat,
+    target: NumericFormat,
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_quantize(num_elements, source, target, seed)?.1)
+}
+
+fn run_quantize(
+    num_elements: usize,
+    source: NumericFormat,
+    target: NumericFormat,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    let values = generate_random_f32(&[num_elements], 1.0, seed)?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_f32(&values);
+
+    let source_tensor = quantize(&values, source);
+    let target_tensor = requantize(&source_tensor, target);
+    let restored = dequantize(&target_tensor);
+
+    let (squared_error, max_error) =
+        values
+            .iter()
+            .zip(&restored)
+            .fold((0.0f64, 0.0f32), |(sum, max), (&x, &y)| {
+                let error = (x - y).abs();
+                (sum + (error as f64) * (error as f64), max.max(error))
+            });
+    let mse = if num_elements > 0 {
+        squared_error / num_elements as f64
+    } else {
+        0.0
+    };
+
+    let source_bytes = source_tensor.to_bytes();
+    let target_bytes = target_tensor.to_bytes();
+
+    let output = OperationOutput {
+        result_hash: Proof::hash_operation_result(&target_bytes),
+        flops: quantize_flops(num_elements, source, target)?,
+        intermediate_hashes: vec![(
+            "source".to_string(),
+            Proof::hash_operation_result(&source_bytes),
+        )],
+        metrics: vec![
+            ("source_scale".to_string(), source_tensor.scale as f64),
+            ("target_scale".to_string(), target_tensor.scale as f64),
+            ("mse".to_string(), mse),
+            ("max_abs_error".to_string(), max_error as f64),
+        ],
+    };
+    Ok((
+        output,
+        OperationTensors {
+            output: target_bytes,
+            ..tensors
+        },
+    ))
+}
+
+/// FLOP count of a quantize operation: per element, the source encode, its
+/// decode, the target encode and decode, and the error measurement
+pub fn quantize_flops(
+    num_elements: usize,
+    source: NumericFormat,
+    target: NumericFormat,
+) -> Result<u64> {
+    // A scaled encode is an absmax compare and a divide; a float cast rounds once
+    let encode = |format| match format {
+        NumericFormat::F32 => 0,
+        NumericFormat::BF16 | NumericFormat::F16 => 1,
+        NumericFormat::FP8E4M3 | NumericFormat::FP8E5M2 | NumericFormat::INT8 => 2,
+    };
+    // Widening back to f32 is exact; only scaled formats multiply
+    let decode = |format| match format_max(format) {
+        Some(_) => 1,
+        None => 0,
+    };
+    let error = 3;
+
+    flops_product(&[
+        num_elements,
+        encode(source) + decode(source) + encode(target) + decode(target) + error,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [NumericFormat; 6] = [
+        NumericFormat::F32,
+        NumericFormat::BF16,
+        NumericFormat::F16,
+        NumericFormat::FP8E4M3,
+        NumericFormat::FP8E5M2,
+        NumericFormat::INT8,
+    ];
+
+    #[test]
+    fn test_round_trip_error_bounds() {
+        let values = generate_random_f32(&[10_000], 2.0, 7).unwrap();
+        let amax = absmax(&values);
+
+        for format in FORMATS {
+            let tensor = quantize(&values, format);
+            assert_eq!(tensor.format(), format);
+            assert_eq!(tensor.len(), values.len());
+
+            let restored = dequantize(&tensor);
+            for (&x, &y) in values.iter().zip(&restored) {
+                let error = (x - y).abs();
+                // Relative precision of the format, the smallest FP8 normal, or
+                // half an INT8 step
+                let bound = match format {
+                    NumericFormat::F32 => 0.0,
+                    NumericFormat::BF16 => x.abs() / 256.0,
+                    NumericFormat::F16 => x.abs() / 2048.0,
+                    NumericFormat::FP8E4M3 => x.abs() / 8.0 + tensor.scale / 64.0,
+                    NumericFormat::FP8E5M2 => x.abs() / 8.0 + tensor.scale / 65536.0,
+                    NumericFormat::INT8 => amax / 127.0 / 2.0,
+                };
+                assert!(error <= bound * 1.0001, "{:?}: {} -> {}", format, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_int8_quantization() {
+        let tensor = quantize(&[-2.54, 0.0, 1.0, 0.01], NumericFormat::INT8);
+        assert!((tensor.scale - 0.02).abs() < 1e-7);
+        assert_eq!(tensor.data, TensorData::INT8(vec![-127, 0, 50, 0]));
+
+        // An all-zero tensor keeps a unit scale
+        assert_eq!(quantize(&[0.0; 4], NumericFormat::INT8).scale, 1.0);
+    }
+
+    #[test]
+    fn test_requantize_between_formats() {
+        let values = generate_random_f32(&[256], 1.0, 3).unwrap();
+
+        // Widening F16 to F32 is exact, so narrowing back reproduces the F16 bits
+        let half = quantize(&values, NumericFormat::F16);
+        let wide = requantize(&half, NumericFormat::F32);
+        assert_eq!(requantize(&wide, NumericFormat::F16), half);
+
+        // Requantizing INT8 to INT8 keeps every code
+        let int8 = quantize(&values, NumericFormat::INT8);
+        assert_eq!(requantize(&int8, NumericFormat::INT8).data, int8.data);
+
+        let fp8 = requantize(&int8, NumericFormat::FP8E5M2);
+        assert_eq!(fp8.format(), NumericFormat::FP8E5M2);
+        assert!((fp8.scale * FP8E5M2::MAX - int8.scale * 127.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_quantize_execution() {
+        let result = execute_quantize(1024, NumericFormat::BF16, NumericFormat::INT8, 42).unwrap();
+        let again = execute_quantize(1024, NumericFormat::BF16, NumericFormat::INT8, 42).unwrap();
+        assert_eq!(result.result_hash, again.result_hash);
+        assert_eq!(
+            result.flops,
+            quantize_flops(1024, NumericFormat::BF16, NumericFormat::INT8).unwrap()
+        );
+
+        let lossless = execute_quantize(1024, NumericFormat::F32, NumericFormat::F32, 42).unwrap();
+        assert_eq!(lossless.metrics[2], ("mse".to_string(), 0.0));
+        assert_ne!(lossless.result_hash, result.result_hash);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::operations::{
+    flops_product, generate_random_tensor, log_softmax, softmax, OperationTensors,
+};
+use demle_core::Result;
+
+/// Execute a row-wise softmax (or log-softmax) over a (rows, cols) tensor
+pub fn execute_softmax(rows: usize, cols: usize, log: bool, seed: u64) -> Result<(String, u64)> {
+    let flops = softmax_flops(rows, cols)?;
+    let tensors = softmax_tensors(rows, cols, log, seed)?;
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded input and output of a row-wise softmax
+pub fn softmax_tensors(rows: usize, cols: usize, log: bool, seed: u64) -> Result<OperationTensors> {
+    let input = generate_random_tensor(&[rows, cols], seed)?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&input);
+
+    let output: Vec<_> = input
+        .chunks(cols.max(1))
+        .flat_map(|row| if log { log_softmax(row) } else { softmax(row) })
+        .collect();
+
+    Ok(tensors.with_fp8_output(&output))
+}
+
+/// FLOP count of a row softmax: max, subtract, exp, sum and normalize per element
+pub fn softmax_flops(rows: usize, cols: usize) -> Result<u64> {
+    flops_product(&[5, rows, cols])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_softmax_execution() {
+        let (hash, flops) = execute_softmax(16, 32, false, 42).unwrap();
+        assert!(!hash.is_empty());
+        assert_eq!(flops, 5 * 16 * 32);
+
+        let again = execute_softmax(16, 32, false, 42).unwrap();
+        assert_eq!(hash, again.0);
+
+        let log = execute_softmax(16, 32, true, 42).unwrap();
+        assert_ne!(hash, log.0);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::fp8::FP8;
+use crate::gemm::gemm_flops;
+use crate::operations::{generate_random_tensor, OperationTensors};
+use demle_core::{DemleError, Result};
+use rayon::prelude::*;
+
+/// A (rows × cols) matrix with 2:4 structured sparsity along its rows: every
+/// group of four consecutive elements holds at most two non-zeros.
+///
+/// Only the two kept values of each group are stored, in column order, with
+/// their positions within the group packed into a 4-bit metadata nibble
+/// (two groups per byte, the first group in the low nibble).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sparse24Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    /// Kept values, two per group of four, (rows × cols / 2) in total
+    pub values: Vec<FP8>,
+    /// Packed 2-bit in-group indices of the kept values
+    pub metadata: Vec<u8>,
+}
+
+impl Sparse24Matrix {
+    /// Prune a row-major dense matrix to 2:4 sparsity by keeping the two
+    /// largest magnitudes of every group. Ties keep the lower index, so
+    /// pruning is deterministic.
+    pub fn prune(dense: &[FP8], rows: usize, cols: usize) -> Result<Self> {
+        check_group_columns(cols)?;
+        if dense.len() != rows * cols {
+            return Err(DemleError::ValidationError(format!(
+                "Expected {} elements for a {}x{} matrix, got {}",
+                rows * cols,
+                rows,
+                cols,
+                dense.len()
+            )));
+        }
+
+        let groups = rows * cols / 4;
+        let mut values = Vec::with_capacity(groups * 2);
+        let mut metadata = vec![0u8; groups.div_ceil(2)];
+
+        for (group, chunk) in dense.chunks(4).enumerate() {
+            let mut order = [0usize, 1, 2, 3];
+            // Stable sort by descending magnitude keeps lower indices on ties
+            order.sort_by(|&x, &y| chunk[y].to_f32().abs().total_cmp(&chunk[x].to_f32().abs()));
+            let (first, second) = (order[0].min(order[1]), order[0].max(order[1]));
+
+            values.push(chunk[first]);
+            values.push(chunk[second]);
+            let nibble = (first | (second << 2)) as u8;
+            metadata[group / 2] |= nibble << (4 * (group % 2));
+        }
+
+        Ok(Self {
+            rows,
+            cols,
+            values,
+            metadata,
+        })
+    }
+
+    /// In-group column indices of the two values kept in a group
+    fn group_indices(&self, group: usize) -> (usize, usize) {
+        let nibble = (self.metadata[group / 2] >> (4 * (group % 2))) & 0xF;
+        ((nibble & 0x3) as usize, (nibble >> 2) as usize)
+    }
+
+    /// Expand back to a row-major dense matrix with explicit zeros
+    pub fn to_dense(&self) -> Vec<FP8> {
+        let mut dense = vec![FP8::zero(); self.rows * self.cols];
+        for group in 0..self.rows * self.cols / 4 {
+            let (first, second) = self.group_indices(group);
+            dense[group * 4 + first] = self.values[group * 2];
+            dense[group * 4 + second] = self.values[group * 2 + 1];
+        }
+        dense
+    }
+}
+
+fn check_group_columns(cols: usize) -> Result<()> {
+    if !cols.is_multiple_of(4) {
+        return Err(DemleError::ValidationError(format!(
+            "2:4 sparsity needs a column count divisible by 4, got {}",
+            cols
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the inner dimension of a sparse GEMM splits into groups of four
+pub fn validate_sparse_gemm(dimensions: (usize, usize, usize)) -> Result<()> {
+    check_group_columns(dimensions.1)
+}
+
+/// Execute a 2:4 sparse FP8 GEMM: C = prune(A) * B with seeded operands
+pub fn execute_sparse_gemm(dimensions: (usize, usize, usize), seed: u64) -> Result<(String, u64)> {
+    let flops = sparse_gemm_flops(dimensions)?;
+    let tensors = sparse_gemm_tensors(dimensions, seed)?;
+    Ok((tensors.result_hash(), flops))
+}
+
+/// Seeded operands, A before pruning, and output of a sparse GEMM
+pub fn sparse_gemm_tensors(
+    dimensions: (usize, usize, usize),
+    seed: u64,
+) -> Result<OperationTensors> {
+    validate_sparse_gemm(dimensions)?;
+    let (m, k, n) = dimensions;
+
+    let a = generate_random_tensor(&[m, k], seed)?;
+    let b = generate_random_tensor(&[k, n], seed.wrapping_add(1))?;
+    let mut tensors = OperationTensors::default();
+    tensors.add_fp8(&a);
+    tensors.add_fp8(&b);
+
+    let sparse_a = Sparse24Matrix::prune(&a, m, k)?;
+    let c = sparse_gemm_fp8(&sparse_a, &b, n)?;
+
+    Ok(tensors.with_fp8_output(&c))
+}
+
+/// Sparse FP8 GEMM kernel: C(m×n) = A(m×k, 2:4 sparse) * B(k×n), skipping the
+/// pruned half of A. Matches the dense kernel on the expanded matrix.
+pub fn sparse_gemm_fp8(a: &Sparse24Matrix, b: &[FP8], n: usize) -> Result<Vec<FP8>> {
+    let (m, k) = (a.rows, a.cols);
+    if b.len() != k * n {
+        return Err(DemleError::ValidationError(format!(
+            "Expected {} elements for a {}x{} B matrix, got {}",
+            k * n,
+            k,
+            n,
+            b.len()
+        )));
+    }
+    let groups_per_row = k / 4;
+
+    Ok((0..m * n)
+        .into_par_iter()
+        .map(|idx| {
+            let i = idx / n;
+            let j = idx % n;
+
+            let mut sum = FP8::zero();
+            for group in i * groups_per_row..(i + 1) * groups_per_row {
+                let base = (group % groups_per_row) * 4;
+                let (first, second) = a.group_indices(group);
+                sum = sum + (a.values[group * 2] * b[(base + first) * n + j]);
+                sum = sum + (a.values[group * 2 + 1] * b[(base + second) * n + j]);
+            }
+            sum
+        })
+        .collect())
+}
+
+/// FLOP count credited for a 2:4 sparse GEMM: the multiply-adds actually
+/// performed, half of the dense equivalent
+pub fn sparse_gemm_flops(dimensions: (usize, usize, usize)) -> Result<u64> {
+    Ok(sparse_gemm_dense_equivalent_flops(dimensions)? / 2)
+}
+
+/// FLOP count of the dense GEMM a 2:4 sparse GEMM stands in for, which
+/// sparse throughput is usually quoted against
+pub fn sparse_gemm_dense_equivalent_flops(dimensions: (usize, usize, usize)) -> Result<u64> {
+    gemm_flops(dimensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemm::gemm_fp8;
+
+    fn to_fp8(values: &[f32]) -> Vec<FP8> {
+        values.iter().map(|&v| FP8::from_f32(v)).collect()
+    }
+
+    #[test]
+    fn test_prune_2_4() {
+        let dense = to_fp8(&[
+            1.0, -4.0, 2.0, 0.5, //
+            3.0, 3.0, 3.0, -3.0,
+        ]);
+        let sparse = Sparse24Matrix::prune(&dense, 1, 8).unwrap();
+
+        assert_eq!(sparse.values, to_fp8(&[-4.0, 2.0, 3.0, 3.0]));
+        // Group 0 keeps columns 1 and 2, group 1 keeps columns 0 and 1
+        assert_eq!(sparse.metadata, vec![0b0100_1001]);
+        assert_eq!(
+            sparse.to_dense(),
+            to_fp8(&[0.0, -4.0, 2.0, 0.0, 3.0, 3.0, 0.0, 0.0])
+        );
+
+        assert!(Sparse24Matrix::prune(&dense, 2, 4).is_ok());
+        assert!(Sparse24Matrix::prune(&dense, 4, 2).is_err());
+    }
+
+    #[test]
+    fn test_sparse_gemm_matches_dense() {
+        let (m, k, n) = (6, 16, 5);
+        let a = generate_random_tensor(&[m, k], 1).unwrap();
+        let b = generate_random_tensor(&[k, n], 2).unwrap();
+
+        let sparse = Sparse24Matrix::prune(&a, m, k).unwrap();
+        assert_eq!(sparse.values.len(), m * k / 2);
+        assert_eq!(sparse.metadata.len(), m * k / 8);
+
+        let expected = gemm_fp8(&sparse.to_dense(), &b, m, k, n);
+        assert_eq!(sparse_gemm_fp8(&sparse, &b, n).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_sparse_gemm_execution() {
+        let dimensions = (32, 64, 16);
+
+        let (hash, flops) = execute_sparse_gemm(dimensions, 42).unwrap();
+        assert_eq!(flops, 32 * 64 * 16);
+        assert_eq!(
+            2 * flops,
+            sparse_gemm_dense_equivalent_flops(dimensions).unwrap()
+        );
+        assert_eq!(hash, execute_sparse_gemm(dimensions, 42).unwrap().0);
+
+        assert!(execute_sparse_gemm((4, 6, 4), 42).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::fp8::FP8;
+use crate::npy::{fp8_from_npy, fp8_to_npy, read_npy, write_npy};
+use demle_core::{proof::Proof, DemleError, Result, TensorRef};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// FP8 tensor with its shape, as kept in a [`TensorStore`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredTensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<FP8>,
+}
+
+impl StoredTensor {
+    pub fn new(shape: Vec<usize>, data: Vec<FP8>) -> Result<Self> {
+        let expected: usize = shape.iter().product();
+        if data.len() != expected {
+            return Err(DemleError::ValidationError(format!(
+                "Expected {} elements for shape {:?}, got {}",
+                expected,
+                shape,
+                data.len()
+            )));
+        }
+        Ok(Self { shape, data })
+    }
+
+    /// Bytes the content address covers: the rank (u32), dimensions (u64
+    /// each) and FP8 bits, all little-endian
+    pub fn content_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + 8 * self.shape.len() + self.data.len());
+        bytes.extend_from_slice(&(self.shape.len() as u32).to_le_bytes());
+        for &dim in &self.shape {
+            bytes.extend_from_slice(&(dim as u64).to_le_bytes());
+        }
+        bytes.extend(self.data.iter().map(|x| x.to_bits()));
+        bytes
+    }
+
+    /// Content address: the result hash of [`StoredTensor::content_bytes`]
+    pub fn content_hash(&self) -> String {
+        Proof::hash_operation_result(&self.content_bytes())
+    }
+
+    /// Reference to this tensor by content hash
+    pub fn tensor_ref(&self) -> TensorRef {
+        TensorRef {
+            hash: self.content_hash(),
+            shape: self.shape.clone(),
+        }
+    }
+}
+
+/// Directory of content-addressed tensors, one `<hash>.npy` file each
+#[derive(Debug, Clone)]
+pub struct TensorStore {
+    root: PathBuf,
+}
+
+impl TensorStore {
+    /// Open a store rooted at a directory, creating it if needed
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|e| {
+            DemleError::ComputationError(format!(
+                "Failed to create tensor store {}: {}",
+                root.display(),
+                e
+            ))
+        })?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the file holding a tensor. Hashes are validated so a reference
+    /// cannot point outside the store.
+    pub fn path(&self, hash: &str) -> Result<PathBuf> {
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(DemleError::ValidationError(format!(
+                "Invalid tensor hash '{}'",
+                hash
+            )));
+        }
+        Ok(self.root.join(format!("{}.npy", hash.to_ascii_lowercase())))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.path(hash).is_ok_and(|path| path.is_file())
+    }
+
+    /// Write a tensor under its content hash and return a reference to it.
+    /// Writing a tensor that is already stored is a no-op.
+    pub fn put(&self, tensor: &StoredTensor) -> Result<TensorRef> {
+        let tensor_ref = tensor.tensor_ref();
+        let path = self.path(&tensor_ref.hash)?;
+        if path.is_file() {
+            return Ok(tensor_ref);
+        }
+
+        // Write to a temporary file first so readers never see a partial tensor
+        let tmp = path.with_extension(format!("npy.{}.tmp", std::process::id()));
+        let bytes = write_npy(&fp8_to_npy(&tensor.shape, &tensor.data));
+        fs::write(&tmp, bytes)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| {
+                DemleError::ComputationError(format!(
+                    "Failed to write tensor {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        Ok(tensor_ref)
+    }
+
+    /// Load a referenced tensor, checking its shape and content hash
+    pub fn get(&self, tensor_ref: &TensorRef) -> Result<StoredTensor> {
+        let path = self.path(&tensor_ref.hash)?;
+        let bytes = fs::read(&path).map_err(|e| {
+            DemleError::ComputationError(format!("Failed to read tensor {}: {}", path.display(), e))
+        })?;
+
+        let array = read_npy(&bytes)?;
+        let tensor = StoredTensor::new(array.shape.clone(), fp8_from_npy(&array)?)?;
+        if tensor.shape != tensor_ref.shape {
+            return Err(DemleError::ValidationError(format!(
+                "Tensor {} has shape {:?}, expected {:?}",
+                tensor_ref.hash, tensor.shape, tensor_ref.shape
+            )));
+        }
+        if !tensor.content_hash().eq_ignore_ascii_case(&tensor_ref.hash) {
+            return Err(DemleError::ValidationError(format!(
+                "Tensor file {} does not match its hash",
+                path.display()
+            )));
+        }
+
+        Ok(tensor)
+    }
+}
+
+/// Empty store in a fresh temporary directory, for tests
+#[cfg(test)]
+pub(crate) fn temp_store(name: &str) -> TensorStore {
+    let root = std::env::temp_dir().join(format!("demle-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    TensorStore::open(root).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::generate_random_tensor;
+
+    #[test]
+    fn test_store_round_trip() {
+        let store = temp_store("store-round-trip");
+        let tensor =
+            StoredTensor::new(vec![3, 5], generate_random_tensor(&[3, 5], 1).unwrap()).unwrap();
+
+        let tensor_ref = store.put(&tensor).unwrap();
+        assert!(store.contains(&tensor_ref.hash));
+        assert_eq!(store.get(&tensor_ref).unwrap(), tensor);
+        assert_eq!(store.put(&tensor).unwrap(), tensor_ref);
+
+        // Same data with a different shape is a different tensor
+        let reshaped = StoredTensor::new(vec![5, 3], tensor.data.clone()).unwrap();
+        assert_ne!(reshaped.content_hash(), tensor_ref.hash);
+
+        let wrong_shape = TensorRef {
+            shape: vec![15],
+            ..tensor_ref.clone()
+        };
+        assert!(store.get(&wrong_shape).is_err());
+
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_store_rejects_bad_references() {
+        let store = temp_store("store-bad-refs");
+        let tensor = StoredTensor::new(vec![4], generate_random_tensor(&[4], 2).unwrap()).unwrap();
+        let tensor_ref = store.put(&tensor).unwrap();
+
+        // A tampered file no longer matches its hash
+        let other = StoredTensor::new(vec![4], generate_random_tensor(&[4], 3).unwrap()).unwrap();
+        let bytes = write_npy(&fp8_to_npy(&other.shape, &other.data));
+        fs::write(store.path(&tensor_ref.hash).unwrap(), bytes).unwrap();
+        assert!(store.get(&tensor_ref).is_err());
+
+        for hash in ["../etc/passwd", "abc", ""] {
+            let bad = TensorRef {
+                hash: hash.to_string(),
+                shape: vec![4],
+            };
+            assert!(store.get(&bad).is_err());
+            assert!(!store.contains(hash));
+        }
+
+        assert!(StoredTensor::new(vec![2, 2], tensor.data[..3].to_vec()).is_err());
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+}
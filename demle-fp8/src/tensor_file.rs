@@ -0,0 +1,267 @@
+use crate::fp8::FP8;
+use crate::npy::{read_npy, tensor_from_npy, tensor_to_npy, write_npy};
+use crate::quantize::TensorData;
+use crate::store::StoredTensor;
+use demle_core::{DemleError, NumericFormat, Result};
+use safetensors::tensor::{Dtype, SafeTensors, TensorView};
+use std::fs;
+use std::path::Path;
+
+/// Tensor with a name, as held in safetensors and .npy files
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedTensor {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub data: TensorData,
+}
+
+impl NamedTensor {
+    pub fn new(name: impl Into<String>, shape: Vec<usize>, data: TensorData) -> Result<Self> {
+        let name = name.into();
+        let expected: usize = shape.iter().product();
+        if data.len() != expected {
+            return Err(DemleError::ValidationError(format!(
+                "Tensor '{}' has {} elements, expected {} for shape {:?}",
+                name,
+                data.len(),
+                expected,
+                shape
+            )));
+        }
+        Ok(Self { name, shape, data })
+    }
+
+    /// Name a stored FP8 tensor
+    pub fn from_stored(name: impl Into<String>, tensor: &StoredTensor) -> Self {
+        Self {
+            name: name.into(),
+            shape: tensor.shape.clone(),
+            data: TensorData::FP8E4M3(tensor.data.clone()),
+        }
+    }
+
+    /// Convert to an FP8 E4M3 tensor for the tensor store. E4M3 data keeps its
+    /// bits; other formats are converted element-wise without scaling, so a
+    /// value that would saturate or flush to zero is rejected rather than
+    /// silently changed.
+    pub fn to_stored(&self) -> Result<StoredTensor> {
+        let values: Vec<f32> = match &self.data {
+            TensorData::FP8E4M3(v) => return StoredTensor::new(self.shape.clone(), v.clone()),
+            TensorData::F32(v) => v.clone(),
+            TensorData::BF16(v) => v.iter().map(|x| x.to_f32()).collect(),
+            TensorData::F16(v) => v.iter().map(|x| x.to_f32()).collect(),
+            TensorData::FP8E5M2(v) => v.iter().map(|x| x.to_f32()).collect(),
+            TensorData::INT8(v) => v.iter().map(|&x| x as f32).collect(),
+        };
+        if let Some(&x) = values
+            .iter()
+            .find(|&&x| x != 0.0 && !(FP8::MIN_NORMAL..=FP8::MAX).contains(&x.abs()))
+        {
+            return Err(DemleError::ValidationError(format!(
+                "Tensor '{}' has value {} outside the FP8 E4M3 range; non-zero \
+                 magnitudes must lie in [{}, {}]",
+                self.name,
+                x,
+                FP8::MIN_NORMAL,
+                FP8::MAX
+            )));
+        }
+        let data = values.into_iter().map(FP8::from_f32).collect();
+        StoredTensor::new(self.shape.clone(), data)
+    }
+}
+
+fn to_dtype(format: NumericFormat) -> Dtype {
+    match format {
+        NumericFormat::F32 => Dtype::F32,
+        NumericFormat::BF16 => Dtype::BF16,
+        NumericFormat::F16 => Dtype::F16,
+        NumericFormat::FP8E4M3 => Dtype::F8_E4M3,
+        NumericFormat::FP8E5M2 => Dtype::F8_E5M2,
+        NumericFormat::INT8 => Dtype::I8,
+    }
+}
+
+fn from_dtype(dtype: Dtype) -> Result<NumericFormat> {
+    match dtype {
+        Dtype::F32 => Ok(NumericFormat::F32),
+        Dtype::BF16 => Ok(NumericFormat::BF16),
+        Dtype::F16 => Ok(NumericFormat::F16),
+        Dtype::F8_E4M3 => Ok(NumericFormat::FP8E4M3),
+        Dtype::F8_E5M2 => Ok(NumericFormat::FP8E5M2),
+        Dtype::I8 => Ok(NumericFormat::INT8),
+        other => Err(DemleError::SerializationError(format!(
+            "Unsupported safetensors dtype {:?}",
+            other
+        ))),
+    }
+}
+
+fn safetensors_error(e: safetensors::SafeTensorError) -> DemleError {
+    DemleError::SerializationError(format!("Invalid safetensors data: {}", e))
+}
+
+/// Parse a safetensors file, returning its tensors sorted by name
+pub fn read_safetensors(bytes: &[u8]) -> Result<Vec<NamedTensor>> {
+    let file = SafeTensors::deserialize(bytes).map_err(safetensors_error)?;
+
+    let mut tensors = file
+        .tensors()
+        .into_iter()
+        .map(|(name, view)| {
+            let format = from_dtype(view.dtype())?;
+            let data = TensorData::from_le_bytes(format, view.data())?;
+            NamedTensor::new(name, view.shape().to_vec(), data)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    tensors.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tensors)
+}
+
+/// Serialize tensors as a safetensors file, with FP8 data in the F8_E4M3 and
+/// F8_E5M2 dtypes PyTorch loads as `float8_e4m3fn` and `float8_e5m2`
+pub fn write_safetensors(tensors: &[NamedTensor]) -> Result<Vec<u8>> {
+    let bytes: Vec<Vec<u8>> = tensors.iter().map(|t| t.data.to_le_bytes()).collect();
+    let views = tensors
+        .iter()
+        .zip(&bytes)
+        .map(|(tensor, data)| {
+            let dtype = to_dtype(tensor.data.format());
+            let view =
+                TensorView::new(dtype, tensor.shape.clone(), data).map_err(safetensors_error)?;
+            Ok((tensor.name.as_str(), view))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    safetensors::serialize(views, &None).map_err(safetensors_error)
+}
+
+/// Read the tensors of a `.safetensors` file, or the single tensor of a `.npy`
+/// file named after the file stem. `.npy` files carry no FP8 dtype, so raw-bit
+/// arrays are read as `npy_format`.
+pub fn load_tensors(path: &Path, npy_format: NumericFormat) -> Result<Vec<NamedTensor>> {
+    let bytes = fs::read(path).map_err(|e| {
+        DemleError::ComputationError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("safetensors") => read_safetensors(&bytes),
+        Some("npy") => {
+            let array = read_npy(&bytes)?;
+            let format = match array.descr.as_str() {
+                "<f4" => NumericFormat::F32,
+                "<f2" => NumericFormat::F16,
+                "|i1" => NumericFormat::INT8,
+                _ => npy_format,
+            };
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let data = tensor_from_npy(&array, format)?;
+            Ok(vec![NamedTensor::new(name, array.shape, data)?])
+        }
+        _ => Err(DemleError::ValidationError(format!(
+            "Unsupported tensor file {}; expected .safetensors or .npy",
+            path.display()
+        ))),
+    }
+}
+
+/// Write tensors to a `.safetensors` file, or a single tensor to a `.npy` file
+pub fn save_tensors(path: &Path, tensors: &[NamedTensor]) -> Result<()> {
+    let bytes = match (path.extension().and_then(|ext| ext.to_str()), tensors) {
+        (Some("safetensors"), _) => write_safetensors(tensors)?,
+        (Some("npy"), [tensor]) => write_npy(&tensor_to_npy(&tensor.shape, &tensor.data)),
+        (Some("npy"), _) => {
+            return Err(DemleError::ValidationError(format!(
+                "A .npy file holds one tensor, got {}",
+                tensors.len()
+            )))
+        }
+        _ => {
+            return Err(DemleError::ValidationError(format!(
+                "Unsupported tensor file {}; expected .safetensors or .npy",
+                path.display()
+            )))
+        }
+    };
+
+    fs::write(path, bytes).map_err(|e| {
+        DemleError::ComputationError(format!("Failed to write {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fp8::FP8E5M2;
+
+    fn all_bits(format: NumericFormat) -> TensorData {
+        let bits: Vec<u8> = (0..=255).collect();
+        TensorData::from_le_bytes(format, &bits).unwrap()
+    }
+
+    #[test]
+    fn test_safetensors_round_trip() {
+        let tensors = vec![
+            NamedTensor::new("a.e4m3", vec![16, 16], all_bits(NumericFormat::FP8E4M3)).unwrap(),
+            NamedTensor::new("b.e5m2", vec![256], all_bits(NumericFormat::FP8E5M2)).unwrap(),
+            NamedTensor::new(
+                "c.f32",
+                vec![2, 2],
+                TensorData::F32(vec![1.0, -2.5, 0.0, 3.25]),
+            )
+            .unwrap(),
+            NamedTensor::new("d.i8", vec![4, 64], all_bits(NumericFormat::INT8)).unwrap(),
+        ];
+
+        let bytes = write_safetensors(&tensors).unwrap();
+        let read = read_safetensors(&bytes).unwrap();
+        assert_eq!(read, tensors);
+
+        // The header names the dtypes PyTorch maps to its float8 types
+        let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+        let header = std::str::from_utf8(&bytes[8..8 + header_len]).unwrap();
+        assert!(header.contains("\"F8_E4M3\"") && header.contains("\"F8_E5M2\""));
+
+        assert!(read_safetensors(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_tensor_files() {
+        let dir = std::env::temp_dir().join(format!("demle-tensor-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let grad = NamedTensor::new(
+            "grad",
+            vec![3],
+            TensorData::FP8E5M2(vec![
+                FP8E5M2::from_f32(-0.75),
+                FP8E5M2::from_f32(57344.0),
+                FP8E5M2::from_bits(0x7F),
+            ]),
+        )
+        .unwrap();
+        let npy_path = dir.join("grad.npy");
+        save_tensors(&npy_path, std::slice::from_ref(&grad)).unwrap();
+        assert_eq!(
+            load_tensors(&npy_path, NumericFormat::FP8E5M2).unwrap(),
+            vec![grad.clone()]
+        );
+
+        let stored =
+            StoredTensor::new(vec![2, 2], (0x3C..0x40).map(FP8::from_bits).collect()).unwrap();
+        let weights = NamedTensor::from_stored("w", &stored);
+        let st_path = dir.join("model.safetensors");
+        save_tensors(&st_path, &[weights, grad.clone()]).unwrap();
+
+        let loaded = load_tensors(&st_path, NumericFormat::FP8E4M3).unwrap();
+        assert_eq!(loaded[1].to_stored().unwrap(), stored);
+        assert_eq!(loaded[0].data, grad.data);
+
+        assert!(save_tensors(&dir.join("two.npy"), &loaded).is_err());
+        assert!(load_tensors(&dir.join("model.bin"), NumericFormat::F32).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
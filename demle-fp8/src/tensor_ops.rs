@@ -0,0 +1,439 @@
+use crate::attention::{attention_flops, multi_head_attention};
+use crate::convolution::{conv2d_flops, conv2d_fp8, window_output_size};
+use crate::fp8::FP8;
+use crate::gemm::gemm_fp8;
+use crate::operations::{
+    apply_activation, flops_product, layer_norm, log_softmax, softmax, OperationOutput,
+    OperationTensors,
+};
+use crate::pooling::{pool2d, pooling2d_flops, pooling2d_output_size, validate_pooling2d};
+use crate::store::{StoredTensor, TensorStore};
+use demle_core::{DemleError, Result, TensorInput, TensorOp, TensorRef};
+use std::collections::HashMap;
+
+/// Load the inputs of a tensor operation from the store, apply the kernel and
+/// write the output back. The result hash is the output's content hash, so the
+/// submitter can fetch it from the miner's store.
+pub fn execute_tensor_operation(
+    op: &TensorOp,
+    inputs: &[TensorRef],
+    store: &TensorStore,
+) -> Result<OperationOutput> {
+    let tensors = inputs
+        .iter()
+        .map(|input| store.get(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = apply_tensor_op(op, &tensors)?;
+    let output_ref = store.put(&output)?;
+
+    let intermediate_hashes = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| (format!("input_{}", i), input.hash.clone()))
+        .collect();
+
+    Ok(OperationOutput {
+        result_hash: output_ref.hash,
+        flops: tensor_op_flops(op, &shapes(inputs))?,
+        intermediate_hashes,
+        metrics: Vec::new(),
+    })
+}
+
+/// Loaded inputs and output of a tensor operation. The output bytes are those
+/// its content hash, and so its result hash, covers.
+pub fn tensor_operation_tensors(
+    op: &TensorOp,
+    inputs: &[TensorRef],
+    store: &TensorStore,
+) -> Result<OperationTensors> {
+    let tensors = inputs
+        .iter()
+        .map(|input| store.get(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut recorded = OperationTensors::default();
+    for tensor in &tensors {
+        recorded.add_fp8(&tensor.data);
+    }
+    recorded.output = apply_tensor_op(op, &tensors)?.content_bytes();
+    Ok(recorded)
+}
+
+fn shapes(inputs: &[TensorRef]) -> Vec<&[usize]> {
+    inputs.iter().map(|input| input.shape.as_slice()).collect()
+}
+
+/// Replace edge inputs with the stored tensors produced for them so far
+pub fn resolve_inputs(
+    inputs: &[TensorInput],
+    edges: &HashMap<String, TensorRef>,
+) -> Result<Vec<TensorRef>> {
+    inputs
+        .iter()
+        .map(|input| match input {
+            TensorInput::Stored(tensor) => Ok(tensor.clone()),
+            TensorInput::Edge { name, shape } => match edges.get(name) {
+                Some(tensor) if tensor.shape == *shape => Ok(tensor.clone()),
+                Some(tensor) => Err(DemleError::ValidationError(format!(
+                    "Edge '{}' has shape {:?}, expected {:?}",
+                    name, tensor.shape, shape
+                ))),
+                None => Err(DemleError::ValidationError(format!(
+                    "Edge '{}' is read before any operation produces it",
+                    name
+                ))),
+            },
+        })
+        .collect()
+}
+
+fn arity(op: &TensorOp) -> usize {
+    match op {
+        TensorOp::MatMul | TensorOp::Add => 2,
+        TensorOp::Conv2D { .. } => 2,
+        TensorOp::MultiHeadAttention { .. } => 4,
+        TensorOp::LayerNorm { .. } => 3,
+        TensorOp::Softmax { .. }
+        | TensorOp::Activation(_)
+        | TensorOp::Pooling2D { .. }
+        | TensorOp::Reshape { .. } => 1,
+    }
+}
+
+fn shape_error(op: &TensorOp, shapes: &[&[usize]], reason: &str) -> DemleError {
+    DemleError::ValidationError(format!(
+        "{:?} cannot take inputs of shape {:?}: {}",
+        op, shapes, reason
+    ))
+}
+
+fn nchw(shape: &[usize]) -> Option<(usize, usize, usize, usize)> {
+    match *shape {
+        [n, c, h, w] => Some((n, c, h, w)),
+        _ => None,
+    }
+}
+
+/// Shape of the output of a tensor operation, validating the input shapes
+pub fn output_shape(op: &TensorOp, shapes: &[&[usize]]) -> Result<Vec<usize>> {
+    if shapes.len() != arity(op) {
+        return Err(shape_error(
+            op,
+            shapes,
+            &format!("expected {} inputs", arity(op)),
+        ));
+    }
+    let err = |reason: &str| shape_error(op, shapes, reason);
+
+    match op {
+        TensorOp::MatMul => {
+            let (a, b) = (shapes[0], shapes[1]);
+            match (a.split_last(), b) {
+                (Some((&k, leading)), &[bk, n]) if k == bk => {
+                    Ok(leading.iter().copied().chain([n]).collect())
+                }
+                _ => Err(err("expected (..., k) x (k, n)")),
+            }
+        }
+        TensorOp::Conv2D { stride, padding } => {
+            let (input, kernel) = nchw(shapes[0])
+                .zip(nchw(shapes[1]))
+                .ok_or_else(|| err("expected 4D input and kernel"))?;
+            let (batch, in_ch, ih, iw) = input;
+            let (out_ch, kernel_in_ch, kh, kw) = kernel;
+            if in_ch != kernel_in_ch {
+                return Err(err("input and kernel channels differ"));
+            }
+            if stride.0 == 0 || stride.1 == 0 || kh == 0 || kw == 0 {
+                return Err(err("stride and kernel size must be non-zero"));
+            }
+            window_output_size(ih, kh, stride.0, padding.0)
+                .zip(window_output_size(iw, kw, stride.1, padding.1))
+                .map(|(oh, ow)| vec![batch, out_ch, oh, ow])
+                .ok_or_else(|| err("kernel exceeds padded input"))
+        }
+        TensorOp::MultiHeadAttention { num_heads } => match shapes[0] {
+            &[_, _, d_model]
+                if *num_heads > 0
+                    && d_model.is_multiple_of(*num_heads)
+                    && shapes[1..].iter().all(|w| *w == [d_model, d_model]) =>
+            {
+                Ok(shapes[0].to_vec())
+            }
+            _ => Err(err(
+                "expected (batch, seq, d_model) input, (d_model, d_model) projections and heads dividing d_model",
+            )),
+        },
+        TensorOp::LayerNorm { .. } => match shapes[0].last() {
+            Some(&d) if d > 0 && shapes[1] == [d] && shapes[2] == [d] => Ok(shapes[0].to_vec()),
+            _ => Err(err("expected (..., d) input with non-zero d and (d) gamma and beta")),
+        },
+        TensorOp::Softmax { .. } => {
+            if shapes[0].is_empty() {
+                return Err(err("expected at least one dimension"));
+            }
+            Ok(shapes[0].to_vec())
+        }
+        TensorOp::Activation(_) => Ok(shapes[0].to_vec()),
+        TensorOp::Add => {
+            let (a, b) = (shapes[0], shapes[1]);
+            if b.len() > a.len() || !a.ends_with(b) {
+                return Err(err("second input must match trailing dimensions of the first"));
+            }
+            Ok(a.to_vec())
+        }
+        TensorOp::Pooling2D {
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+        } => {
+            let input_shape = nchw(shapes[0]).ok_or_else(|| err("expected 4D input"))?;
+            let (batch, channels, _, _) = input_shape;
+            let (oh, ow) =
+                validate_pooling2d(input_shape, *pool_type, *kernel_size, *stride, *padding)
+                    .and_then(|()| {
+                        pooling2d_output_size(
+                            input_shape,
+                            *pool_type,
+                            *kernel_size,
+                            *stride,
+                            *padding,
+                        )
+                    })
+                    .map_err(|_| err("pooling window must be non-empty and fit the padded input"))?;
+            Ok(vec![batch, channels, oh, ow])
+        }
+        TensorOp::Reshape { shape } => {
+            if flops_product(shape)? != flops_product(shapes[0])? {
+                return Err(err("reshape must keep the number of elements"));
+            }
+            Ok(shape.clone())
+        }
+    }
+}
+
+/// FLOPs credited for a tensor operation on inputs of the given shapes
+pub fn tensor_op_flops(op: &TensorOp, shapes: &[&[usize]]) -> Result<u64> {
+    let output = output_shape(op, shapes)?;
+    let elements = |factors: &[usize], shape: &[usize]| flops_product(&[factors, shape].concat());
+
+    match op {
+        // Each output element is a k-term inner product
+        TensorOp::MatMul => elements(&[2, shapes[0].last().copied().unwrap_or(0)], &output),
+        TensorOp::Conv2D { stride, padding } => conv2d_flops(
+            nchw(shapes[0]).unwrap_or_default(),
+            nchw(shapes[1]).unwrap_or_default(),
+            *stride,
+            *padding,
+        ),
+        TensorOp::MultiHeadAttention { num_heads } => {
+            let (b, s, d) = (shapes[0][0], shapes[0][1], shapes[0][2]);
+            attention_flops(b, s, d, *num_heads)
+        }
+        TensorOp::Softmax { .. } => elements(&[5], &output),
+        // Mean, variance, normalize, scale and shift, as in the transformer block
+        TensorOp::LayerNorm { .. } => elements(&[8], &output),
+        TensorOp::Activation(_) | TensorOp::Add => elements(&[], &output),
+        TensorOp::Pooling2D {
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+        } => pooling2d_flops(
+            nchw(shapes[0]).unwrap_or_default(),
+            *pool_type,
+            *kernel_size,
+            *stride,
+            *padding,
+        ),
+        // A reshape only relabels the elements
+        TensorOp::Reshape { .. } => Ok(0),
+    }
+}
+
+/// Apply a tensor operation to loaded inputs
+pub fn apply_tensor_op(op: &TensorOp, inputs: &[StoredTensor]) -> Result<StoredTensor> {
+    let shapes: Vec<&[usize]> = inputs.iter().map(|t| t.shape.as_slice()).collect();
+    let shape = output_shape(op, &shapes)?;
+    let data = |i: usize| inputs[i].data.as_slice();
+
+    let output: Vec<FP8> = match op {
+        TensorOp::MatMul => {
+            let k = shapes[1][0];
+            let n = shapes[1][1];
+            gemm_fp8(data(0), data(1), data(0).len() / k.max(1), k, n)
+        }
+        TensorOp::Conv2D { stride, padding } => conv2d_fp8(
+            data(0),
+            data(1),
+            nchw(shapes[0]).unwrap_or_default(),
+            nchw(shapes[1]).unwrap_or_default(),
+            *stride,
+            *padding,
+        ),
+        TensorOp::MultiHeadAttention { num_heads } => multi_head_attention(
+            data(0),
+            data(1),
+            data(2),
+            data(3),
+            shape[0],
+            shape[1],
+            shape[2],
+            *num_heads,
+        ),
+        TensorOp::Softmax { log } => {
+            let cols = shape[shape.len() - 1].max(1);
+            data(0)
+                .chunks(cols)
+                .flat_map(|row| if *log { log_softmax(row) } else { softmax(row) })
+                .collect()
+        }
+        TensorOp::LayerNorm { epsilon } => {
+            let dim = shape[shape.len() - 1];
+            layer_norm(data(0), data(1), data(2), dim, *epsilon)
+        }
+        TensorOp::Activation(activation) => apply_activation(data(0), (*activation).into()),
+        TensorOp::Add => {
+            let bias = data(1);
+            data(0)
+                .iter()
+                .zip(bias.iter().cycle())
+                .map(|(&x, &y)| x + y)
+                .collect()
+        }
+        TensorOp::Pooling2D {
+            pool_type,
+            kernel_size,
+            stride,
+            padding,
+        } => pool2d(
+            data(0),
+            nchw(shapes[0]).unwrap_or_default(),
+            *pool_type,
+            *kernel_size,
+            *stride,
+            *padding,
+        )?,
+        TensorOp::Reshape { .. } => data(0).to_vec(),
+    };
+
+    StoredTensor::new(shape, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::estimate_flops;
+    use crate::operations::generate_random_tensor;
+    use crate::store::temp_store;
+    use crate::{execute_ml_operation, execute_ml_operation_with_store};
+    use demle_core::{Activation, MLOperation, PoolType};
+
+    fn random(shape: &[usize], seed: u64) -> StoredTensor {
+        StoredTensor::new(shape.to_vec(), generate_random_tensor(shape, seed).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_output_shapes() {
+        let conv = TensorOp::Conv2D {
+            stride: (2, 1),
+            padding: (1, 0),
+        };
+        assert_eq!(
+            output_shape(&conv, &[&[1, 2, 8, 8], &[4, 2, 3, 3]]).unwrap(),
+            vec![1, 4, 4, 6]
+        );
+        assert!(output_shape(&conv, &[&[1, 3, 8, 8], &[4, 2, 3, 3]]).is_err());
+
+        assert_eq!(
+            output_shape(&TensorOp::MatMul, &[&[2, 3, 8], &[8, 5]]).unwrap(),
+            vec![2, 3, 5]
+        );
+        assert!(output_shape(&TensorOp::MatMul, &[&[3, 8], &[7, 5]]).is_err());
+        assert!(output_shape(&TensorOp::Add, &[&[3, 8], &[3]]).is_err());
+        assert!(output_shape(&TensorOp::Softmax { log: false }, &[&[3], &[3]]).is_err());
+        let layer_norm = TensorOp::LayerNorm { epsilon: 1e-5 };
+        assert!(output_shape(&layer_norm, &[&[2, 0], &[0], &[0]]).is_err());
+        assert!(tensor_op_flops(&TensorOp::MatMul, &[&[3, 8]]).is_err());
+    }
+
+    #[test]
+    fn test_tensor_ops_match_seeded_kernels() {
+        let a = random(&[2, 3, 8], 1);
+        let b = random(&[8, 5], 2);
+        let out = apply_tensor_op(&TensorOp::MatMul, &[a.clone(), b.clone()]).unwrap();
+        assert_eq!(out.shape, vec![2, 3, 5]);
+        assert_eq!(out.data, gemm_fp8(&a.data, &b.data, 6, 8, 5));
+
+        let bias = random(&[8], 3);
+        let out = apply_tensor_op(&TensorOp::Add, &[a.clone(), bias.clone()]).unwrap();
+        assert_eq!(out.data[8 + 2], a.data[8 + 2] + bias.data[2]);
+
+        let input = random(&[1, 2, 6, 6], 4);
+        let pooled = apply_tensor_op(
+            &TensorOp::Pooling2D {
+                pool_type: PoolType::Max,
+                kernel_size: (2, 2),
+                stride: (2, 2),
+                padding: (0, 0),
+            },
+            &[input],
+        )
+        .unwrap();
+        assert_eq!(pooled.shape, vec![1, 2, 3, 3]);
+
+        let relu = apply_tensor_op(&TensorOp::Activation(Activation::ReLU), &[a]).unwrap();
+        assert!(relu.data.iter().all(|x| x.to_f32() >= 0.0));
+    }
+
+    #[test]
+    fn test_execute_tensor_operation_writes_output() {
+        let store = temp_store("tensor-ops");
+
+        let x = random(&[4, 8], 5);
+        let gamma = random(&[8], 6);
+        let beta = random(&[8], 7);
+        let inputs: Vec<TensorRef> = [&x, &gamma, &beta]
+            .iter()
+            .map(|t| store.put(t).unwrap())
+            .collect();
+
+        let op = TensorOp::LayerNorm { epsilon: 1e-5 };
+        let operation = MLOperation::TensorOperation {
+            op: op.clone(),
+            inputs: inputs.iter().cloned().map(TensorInput::Stored).collect(),
+            output: None,
+        };
+        assert!(execute_ml_operation(&operation).is_err());
+
+        let result = execute_ml_operation_with_store(&operation, Some(&store)).unwrap();
+        assert_eq!(result.flops, 8 * 4 * 8);
+        assert_eq!(result.flops, estimate_flops(&operation).unwrap());
+
+        let output = execute_tensor_operation(&op, &inputs, &store).unwrap();
+        assert_eq!(output.result_hash, result.result_hash);
+        assert_eq!(output.intermediate_hashes.len(), 3);
+
+        let stored = store
+            .get(&TensorRef {
+                hash: output.result_hash.clone(),
+                shape: vec![4, 8],
+            })
+            .unwrap();
+        assert_eq!(stored, apply_tensor_op(&op, &[x, gamma, beta]).unwrap());
+
+        // Missing inputs are reported rather than replaced by random data
+        let missing = random(&[4, 8], 8).tensor_ref();
+        assert!(execute_tensor_operation(
+            &op,
+            &[missing, inputs[1].clone(), inputs[2].clone()],
+            &store
+        )
+        .is_err());
+
+        std::fs::remove_dir_all(store.root()).unwrap();
+    }
+}
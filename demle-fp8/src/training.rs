@@ -0,0 +1,527 @@
+use crate::backward::matmul;
+use crate::embedding::generate_token_ids;
+use crate::fp8::FP8E5M2;
+use crate::gemm::gemm_flops;
+use crate::operations::{
+    flops_product, flops_sum, generate_random_f32, generate_random_tensor, OperationOutput,
+    OperationTensors,
+};
+use crate::optimizer::{
+    optimizer_output, optimizer_step, optimizer_step_flops, MasterParams, OptimizerState,
+};
+use crate::quantize::{dequantize, quantize, QuantizedTensor};
+use crate::softmax::softmax_flops;
+use demle_core::{proof::Proof, DemleError, MasterWeights, NumericFormat, Optimizer, Result};
+
+/// Layer sizes of the two-layer MLP trained by a training step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MlpShape {
+    pub batch_size: usize,
+    pub input_dim: usize,
+    pub hidden_dim: usize,
+    pub num_classes: usize,
+}
+
+impl MlpShape {
+    /// Check that every dimension is non-zero and the parameter count fits
+    pub fn validate(&self) -> Result<()> {
+        if self.batch_size == 0
+            || self.input_dim == 0
+            || self.hidden_dim == 0
+            || self.num_classes == 0
+        {
+            return Err(DemleError::ValidationError(
+                "Training step dimensions must be non-zero".to_string(),
+            ));
+        }
+        self.checked_num_params()?;
+        Ok(())
+    }
+
+    /// Number of parameters, laid out as [w1, b1, w2, b2]
+    pub fn num_params(&self) -> usize {
+        self.input_dim * self.hidden_dim
+            + self.hidden_dim
+            + self.hidden_dim * self.num_classes
+            + self.num_classes
+    }
+
+    fn checked_num_params(&self) -> Result<usize> {
+        let count = flops_sum(&[
+            flops_product(&[self.input_dim, self.hidden_dim])?,
+            self.hidden_dim as u64,
+            flops_product(&[self.hidden_dim, self.num_classes])?,
+            self.num_classes as u64,
+        ])?;
+        usize::try_from(count).map_err(|_| {
+            DemleError::ValidationError("Training step parameter count overflows".to_string())
+        })
+    }
+
+    /// Split a flat parameter vector into (w1, b1, w2, b2)
+    fn split<'a>(&self, params: &'a [f32]) -> (&'a [f32], &'a [f32], &'a [f32], &'a [f32]) {
+        let (w1, rest) = params.split_at(self.input_dim * self.hidden_dim);
+        let (b1, rest) = rest.split_at(self.hidden_dim);
+        let (w2, b2) = rest.split_at(self.hidden_dim * self.num_classes);
+        (w1, b1, w2, b2)
+    }
+}
+
+/// Activations, loss and gradients of one forward and backward pass
+#[derive(Debug, Clone)]
+pub struct ForwardBackward {
+    /// ReLU activations as fed to the second GEMM, in scaled FP8
+    pub hidden: QuantizedTensor,
+    pub logits: Vec<f32>,
+    pub loss: f32,
+    /// Number of samples whose largest logit is the label
+    pub correct: usize,
+    /// Loss gradient with respect to the logits, in E5M2
+    pub grad_logits: Vec<FP8E5M2>,
+    /// Loss gradient with respect to the parameters, laid out as [w1, b1, w2, b2]
+    pub grad_params: Vec<f32>,
+}
+
+/// Execute one training step of a two-layer ReLU MLP on seeded inputs and
+/// labels: forward, cross-entropy loss, backward and an optimizer update.
+///
+/// GEMM operands are FP8: E4M3 inputs, activations and weights (with a
+/// per-tensor scale) and E5M2 activation gradients. Accumulation, weight
+/// gradients and the optimizer update are f32.
+pub fn execute_training_step(
+    shape: MlpShape,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<OperationOutput> {
+    Ok(run_training_step(shape, optimizer, master_weights, seed)?.0)
+}
+
+/// Seeded inputs, labels and initial parameters and the output of a training
+/// step
+pub fn training_step_tensors(
+    shape: MlpShape,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<OperationTensors> {
+    Ok(run_training_step(shape, optimizer, master_weights, seed)?.1)
+}
+
+fn run_training_step(
+    shape: MlpShape,
+    optimizer: &Optimizer,
+    master_weights: MasterWeights,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    shape.validate()?;
+    let MlpShape {
+        batch_size,
+        input_dim,
+        hidden_dim,
+        num_classes,
+    } = shape;
+
+    let mut tensors = OperationTensors::default();
+    let input = generate_random_tensor(&[batch_size, input_dim], seed)?;
+    tensors.add_fp8(&input);
+    let input: Vec<f32> = input.iter().map(|x| x.to_f32()).collect();
+    let labels = generate_token_ids(num_classes, batch_size, seed.wrapping_add(1));
+    tensors.add_ids(&labels);
+
+    // He initialization for the ReLU layer, zero biases
+    let mut initial = generate_random_f32(
+        &[input_dim, hidden_dim],
+        (2.0 / input_dim as f32).sqrt(),
+        seed.wrapping_add(2),
+    )?;
+    initial.extend(vec![0.0; hidden_dim]);
+    initial.extend(generate_random_f32(
+        &[hidden_dim, num_classes],
+        (1.0 / hidden_dim as f32).sqrt(),
+        seed.wrapping_add(3),
+    )?);
+    initial.extend(vec![0.0; num_classes]);
+    tensors.add_f32(&initial);
+    let mut params = MasterParams::from_f32(initial, master_weights);
+
+    let pass = forward_backward(&shape, &input, &labels, &params.to_f32())?;
+
+    let mut state = match optimizer {
+        Optimizer::SgdMomentum { .. } => OptimizerState::SgdMomentum {
+            momentum_buffer: vec![0.0; shape.num_params()],
+        },
+        Optimizer::AdamW { .. } => OptimizerState::AdamW {
+            exp_avg: vec![0.0; shape.num_params()],
+            exp_avg_sq: vec![0.0; shape.num_params()],
+        },
+    };
+    optimizer_step(&mut params, &pass.grad_params, &mut state, optimizer)?;
+
+    let (step, tensors) = optimizer_output(
+        tensors,
+        &params,
+        &state,
+        training_step_flops(shape, optimizer)?,
+    );
+
+    let gradient_bytes: Vec<u8> = pass
+        .grad_params
+        .iter()
+        .flat_map(|g| g.to_le_bytes())
+        .collect();
+    let mut intermediate_hashes = vec![
+        (
+            "hidden".to_string(),
+            Proof::hash_operation_result(&pass.hidden.data.to_le_bytes()),
+        ),
+        (
+            "logits".to_string(),
+            Proof::hash_operation_result(
+                &pass
+                    .logits
+                    .iter()
+                    .flat_map(|l| l.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+        ),
+        (
+            "grad_logits".to_string(),
+            Proof::hash_operation_result(
+                &pass
+                    .grad_logits
+                    .iter()
+                    .map(|g| g.to_bits())
+                    .collect::<Vec<u8>>(),
+            ),
+        ),
+        (
+            "grad_params".to_string(),
+            Proof::hash_operation_result(&gradient_bytes),
+        ),
+    ];
+    intermediate_hashes.extend(step.intermediate_hashes);
+
+    let output = OperationOutput {
+        result_hash: step.result_hash,
+        flops: step.flops,
+        intermediate_hashes,
+        metrics: vec![
+            ("loss".to_string(), pass.loss as f64),
+            (
+                "accuracy".to_string(),
+                pass.correct as f64 / batch_size as f64,
+            ),
+        ],
+    };
+    Ok((output, tensors))
+}
+
+/// Forward and backward pass of the MLP for a batch of inputs and labels
+pub fn forward_backward(
+    shape: &MlpShape,
+    input: &[f32],
+    labels: &[usize],
+    params: &[f32],
+) -> Result<ForwardBackward> {
+    let MlpShape {
+        batch_size,
+        input_dim,
+        hidden_dim,
+        num_classes,
+    } = *shape;
+    if input.len() != batch_size * input_dim
+        || labels.len() != batch_size
+        || params.len() != shape.num_params()
+    {
+        return Err(DemleError::ValidationError(
+            "Training step tensors do not match the MLP shape".to_string(),
+        ));
+    }
+    let (w1, b1, w2, b2) = shape.split(params);
+    let w1 = dequantize_scaled(w1);
+    let w2 = dequantize_scaled(w2);
+
+    // Forward: hidden = relu(input * w1 + b1), logits = hidden * w2 + b2
+    let mut pre_activation = matmul(input, false, &w1, false, batch_size, input_dim, hidden_dim);
+    add_bias(&mut pre_activation, b1);
+    let relu: Vec<f32> = pre_activation.iter().map(|&x| x.max(0.0)).collect();
+    let hidden = quantize(&relu, NumericFormat::FP8E4M3);
+    let hidden_f32 = dequantize(&hidden);
+
+    let mut logits = matmul(
+        &hidden_f32,
+        false,
+        &w2,
+        false,
+        batch_size,
+        hidden_dim,
+        num_classes,
+    );
+    add_bias(&mut logits, b2);
+
+    let (loss, grad_logits_f32) = cross_entropy(&logits, labels, num_classes)?;
+    let correct = logits
+        .chunks(num_classes)
+        .zip(labels)
+        .filter(|(row, &label)| argmax(row) == label)
+        .count();
+
+    // Backward, with E5M2 activation gradients as GEMM operands
+    let grad_logits: Vec<FP8E5M2> = grad_logits_f32
+        .iter()
+        .map(|&g| FP8E5M2::from_f32(g))
+        .collect();
+    let grad_logits_f32: Vec<f32> = grad_logits.iter().map(|g| g.to_f32()).collect();
+
+    let grad_w2 = matmul(
+        &hidden_f32,
+        true,
+        &grad_logits_f32,
+        false,
+        hidden_dim,
+        batch_size,
+        num_classes,
+    );
+    let grad_b2 = column_sums(&grad_logits_f32, num_classes);
+
+    let grad_hidden = matmul(
+        &grad_logits_f32,
+        false,
+        &w2,
+        true,
+        batch_size,
+        num_classes,
+        hidden_dim,
+    );
+    let grad_pre_activation: Vec<f32> = grad_hidden
+        .iter()
+        .zip(&pre_activation)
+        .map(|(&g, &x)| FP8E5M2::from_f32(if x > 0.0 { g } else { 0.0 }).to_f32())
+        .collect();
+
+    let grad_w1 = matmul(
+        input,
+        true,
+        &grad_pre_activation,
+        false,
+        input_dim,
+        batch_size,
+        hidden_dim,
+    );
+    let grad_b1 = column_sums(&grad_pre_activation, hidden_dim);
+
+    let mut grad_params = grad_w1;
+    grad_params.extend(grad_b1);
+    grad_params.extend(grad_w2);
+    grad_params.extend(grad_b2);
+
+    Ok(ForwardBackward {
+        hidden,
+        logits,
+        loss,
+        correct,
+        grad_logits,
+        grad_params,
+    })
+}
+
+/// Mean cross-entropy of row-major (batch, num_classes) logits against class
+/// labels, and its gradient with respect to the logits
+pub fn cross_entropy(
+    logits: &[f32],
+    labels: &[usize],
+    num_classes: usize,
+) -> Result<(f32, Vec<f32>)> {
+    let batch_size = labels.len();
+    let mut loss = 0.0f32;
+    let mut grad = vec![0.0f32; logits.len()];
+
+    for (b, &label) in labels.iter().enumerate() {
+        if label >= num_classes {
+            return Err(DemleError::ValidationError(format!(
+                "Label {} out of range for {} classes",
+                label, num_classes
+            )));
+        }
+        let row = &logits[b * num_classes..(b + 1) * num_classes];
+        let max = row.iter().fold(f32::NEG_INFINITY, |acc, &x| acc.max(x));
+        let sum: f32 = row.iter().map(|&x| (x - max).exp()).sum();
+        let log_sum = max + sum.ln();
+
+        loss += log_sum - row[label];
+        for (c, (g, &x)) in grad[b * num_classes..(b + 1) * num_classes]
+            .iter_mut()
+            .zip(row)
+            .enumerate()
+        {
+            let target = if c == label { 1.0 } else { 0.0 };
+            *g = ((x - log_sum).exp() - target) / batch_size as f32;
+        }
+    }
+
+    Ok((loss / batch_size as f32, grad))
+}
+
+/// FLOP count of a training step: forward GEMMs, loss, backward GEMMs and the
+/// optimizer update
+pub fn training_step_flops(shape: MlpShape, optimizer: &Optimizer) -> Result<u64> {
+    let MlpShape {
+        batch_size,
+        input_dim,
+        hidden_dim,
+        num_classes,
+    } = shape;
+    let hidden = flops_product(&[batch_size, hidden_dim])?;
+    let logits = flops_product(&[batch_size, num_classes])?;
+
+    // Two GEMMs, bias adds and ReLU
+    let forward = flops_sum(&[
+        gemm_flops((batch_size, input_dim, hidden_dim))?,
+        gemm_flops((batch_size, hidden_dim, num_classes))?,
+        hidden,
+        hidden,
+        logits,
+    ])?;
+    // Softmax, loss and the logit gradient
+    let loss = flops_sum(&[softmax_flops(batch_size, num_classes)?, logits, logits])?;
+    // Weight and hidden gradients, bias reductions and the ReLU mask
+    let backward = flops_sum(&[
+        gemm_flops((hidden_dim, batch_size, num_classes))?,
+        gemm_flops((batch_size, num_classes, hidden_dim))?,
+        gemm_flops((input_dim, batch_size, hidden_dim))?,
+        logits,
+        hidden,
+        hidden,
+    ])?;
+    let update = optimizer_step_flops(shape.checked_num_params()?, optimizer)?;
+
+    flops_sum(&[forward, loss, backward, update])
+}
+
+/// Round-trip weights through scaled FP8, as seen by the forward GEMMs
+fn dequantize_scaled(values: &[f32]) -> Vec<f32> {
+    dequantize(&quantize(values, NumericFormat::FP8E4M3))
+}
+
+fn add_bias(data: &mut [f32], bias: &[f32]) {
+    for row in data.chunks_mut(bias.len()) {
+        for (x, &b) in row.iter_mut().zip(bias) {
+            *x += b;
+        }
+    }
+}
+
+fn column_sums(data: &[f32], cols: usize) -> Vec<f32> {
+    let mut sums = vec![0.0f32; cols];
+    for row in data.chunks(cols) {
+        for (sum, &x) in sums.iter_mut().zip(row) {
+            *sum += x;
+        }
+    }
+    sums
+}
+
+fn argmax(row: &[f32]) -> usize {
+    row.iter()
+        .enumerate()
+        .fold((0, f32::NEG_INFINITY), |best, (i, &x)| {
+            if x > best.1 {
+                (i, x)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPE: MlpShape = MlpShape {
+        batch_size: 16,
+        input_dim: 8,
+        hidden_dim: 32,
+        num_classes: 4,
+    };
+
+    fn sgd(learning_rate: f32) -> Optimizer {
+        Optimizer::SgdMomentum {
+            learning_rate,
+            momentum: 0.9,
+            weight_decay: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let (loss, grad) = cross_entropy(&[0.0, 0.0, 2.0, 0.0], &[0, 0], 2).unwrap();
+
+        // ln(2) for the uniform row, ln(1 + e^-2) for the confident one
+        let expected = (2.0f32.ln() + (1.0 + (-2.0f32).exp()).ln()) / 2.0;
+        assert!((loss - expected).abs() < 1e-6);
+        assert!((grad[0] + 0.25).abs() < 1e-6);
+        assert!((grad[1] - 0.25).abs() < 1e-6);
+        assert!((grad[2] + grad[3]).abs() < 1e-6);
+
+        assert!(cross_entropy(&[0.0, 0.0], &[2], 2).is_err());
+    }
+
+    #[test]
+    fn test_training_step_reduces_loss() {
+        let input: Vec<f32> = generate_random_tensor(&[16, 8], 7)
+            .unwrap()
+            .iter()
+            .map(|x| x.to_f32())
+            .collect();
+        let labels = generate_token_ids(4, 16, 8);
+        let mut params =
+            MasterParams::F32(generate_random_f32(&[SHAPE.num_params()], 0.3, 9).unwrap());
+        let mut state = OptimizerState::SgdMomentum {
+            momentum_buffer: vec![0.0; SHAPE.num_params()],
+        };
+
+        let before = forward_backward(&SHAPE, &input, &labels, &params.to_f32()).unwrap();
+        optimizer_step(&mut params, &before.grad_params, &mut state, &sgd(0.1)).unwrap();
+        let after = forward_backward(&SHAPE, &input, &labels, &params.to_f32()).unwrap();
+
+        assert!(
+            after.loss < before.loss,
+            "{} -> {}",
+            before.loss,
+            after.loss
+        );
+    }
+
+    #[test]
+    fn test_training_step_execution() {
+        let optimizer = sgd(0.05);
+        let result = execute_training_step(SHAPE, &optimizer, MasterWeights::F32, 42).unwrap();
+        let again = execute_training_step(SHAPE, &optimizer, MasterWeights::F32, 42).unwrap();
+        assert_eq!(result.result_hash, again.result_hash);
+        assert_eq!(
+            result.flops,
+            training_step_flops(SHAPE, &optimizer).unwrap()
+        );
+
+        let loss = result
+            .metrics
+            .iter()
+            .find(|(name, _)| name == "loss")
+            .unwrap()
+            .1;
+        // An untrained 4-class classifier starts near ln(4)
+        assert!(loss > 0.5 && loss < 5.0, "loss {}", loss);
+        assert_eq!(result.intermediate_hashes[0].0, "hidden");
+
+        let other = execute_training_step(SHAPE, &optimizer, MasterWeights::F32, 43).unwrap();
+        assert_ne!(result.result_hash, other.result_hash);
+
+        let empty = MlpShape {
+            hidden_dim: 0,
+            ..SHAPE
+        };
+        assert!(execute_training_step(empty, &optimizer, MasterWeights::F32, 42).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use crate::attention::{multi_head_attention, validate_attention_heads};
+use crate::gemm::{gemm_fp8, gemm_fp8_fused, Epilogue};
+use crate::operations::{
+    add_tensors, flops_product, flops_sum, generate_random_tensor, hash_tensor, layer_norm,
+    ActivationType, OperationOutput, OperationTensors,
+};
+use demle_core::{FeedForwardType, Result};
+
+/// Epsilon used by the pre-norm layer normalizations
+const LAYER_NORM_EPSILON: f32 = 1e-5;
+
+/// Execute a pre-norm transformer block:
+///
+/// ```text
+/// h   = x + Attention(LayerNorm(x)) * W_o
+/// out = h + FFN(LayerNorm(h))
+/// ```
+pub fn execute_transformer_block(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    d_ff: usize,
+    feed_forward: FeedForwardType,
+    seed: u64,
+) -> Result<OperationOutput> {
+    let (output, _) = run_transformer_block(
+        batch_size,
+        seq_length,
+        d_model,
+        num_heads,
+        d_ff,
+        feed_forward,
+        seed,
+    )?;
+    Ok(output)
+}
+
+/// Seeded input and parameters, in generation order, and output of a
+/// transformer block
+pub fn transformer_block_tensors(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    d_ff: usize,
+    feed_forward: FeedForwardType,
+    seed: u64,
+) -> Result<OperationTensors> {
+    let (_, tensors) = run_transformer_block(
+        batch_size,
+        seq_length,
+        d_model,
+        num_heads,
+        d_ff,
+        feed_forward,
+        seed,
+    )?;
+    Ok(tensors)
+}
+
+fn run_transformer_block(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    d_ff: usize,
+    feed_forward: FeedForwardType,
+    seed: u64,
+) -> Result<(OperationOutput, OperationTensors)> {
+    validate_attention_heads(d_model, num_heads)?;
+
+    let tokens = batch_size * seq_length;
+    let mut intermediate_hashes = Vec::new();
+    let mut tensors = OperationTensors::default();
+
+    // Block input (batch_size, seq_length, d_model)
+    let input = generate_random_tensor(&[batch_size, seq_length, d_model], seed)?;
+
+    // Attention sub-layer
+    let ln1_gamma = generate_random_tensor(&[d_model], seed.wrapping_add(1))?;
+    let ln1_beta = generate_random_tensor(&[d_model], seed.wrapping_add(2))?;
+    for operand in [&input, &ln1_gamma, &ln1_beta] {
+        tensors.add_fp8(operand);
+    }
+    let normed = layer_norm(&input, &ln1_gamma, &ln1_beta, d_model, LAYER_NORM_EPSILON);
+    intermediate_hashes.push(("ln1".to_string(), hash_tensor(&normed)));
+
+    let wq = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(3))?;
+    let wk = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(4))?;
+    let wv = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(5))?;
+    let wo = generate_random_tensor(&[d_model, d_model], seed.wrapping_add(6))?;
+    for operand in [&wq, &wk, &wv, &wo] {
+        tensors.add_fp8(operand);
+    }
+
+    let heads = multi_head_attention(
+        &normed, &wq, &wk, &wv, batch_size, seq_length, d_model, num_heads,
+    );
+    let attention = gemm_fp8(&heads, &wo, tokens, d_model, d_model);
+    intermediate_hashes.push(("attention".to_string(), hash_tensor(&attention)));
+
+    let residual = add_tensors(&input, &attention);
+    intermediate_hashes.push(("residual1".to_string(), hash_tensor(&residual)));
+
+    // Feed-forward sub-layer
+    let ln2_gamma = generate_random_tensor(&[d_model], seed.wrapping_add(7))?;
+    let ln2_beta = generate_random_tensor(&[d_model], seed.wrapping_add(8))?;
+    tensors.add_fp8(&ln2_gamma);
+    tensors.add_fp8(&ln2_beta);
+    let normed = layer_norm(
+        &residual,
+        &ln2_gamma,
+        &ln2_beta,
+        d_model,
+        LAYER_NORM_EPSILON,
+    );
+    intermediate_hashes.push(("ln2".to_string(), hash_tensor(&normed)));
+
+    let w_in = generate_random_tensor(&[d_model, d_ff], seed.wrapping_add(9))?;
+    let w_down = generate_random_tensor(&[d_ff, d_model], seed.wrapping_add(10))?;
+    tensors.add_fp8(&w_in);
+    tensors.add_fp8(&w_down);
+
+    let hidden = match feed_forward {
+        FeedForwardType::GELU => gemm_fp8_fused(
+            &normed,
+            &w_in,
+            tokens,
+            d_model,
+            d_ff,
+            &Epilogue {
+                activation: Some(ActivationType::GELU),
+                ..Default::default()
+            },
+        ),
+        FeedForwardType::SwiGLU => {
+            let w_up = generate_random_tensor(&[d_model, d_ff], seed.wrapping_add(11))?;
+            tensors.add_fp8(&w_up);
+            let gate = gemm_fp8_fused(
+                &normed,
+                &w_in,
+                tokens,
+                d_model,
+                d_ff,
+                &Epilogue {
+                    activation: Some(ActivationType::Swish),
+                    ..Default::default()
+                },
+            );
+            gemm_fp8_fused(
+                &normed,
+                &w_up,
+                tokens,
+                d_model,
+                d_ff,
+                &Epilogue {
+                    gate: Some(&gate),
+                    ..Default::default()
+                },
+            )
+        }
+    };
+    intermediate_hashes.push(("ffn_hidden".to_string(), hash_tensor(&hidden)));
+
+    let ffn_out = gemm_fp8(&hidden, &w_down, tokens, d_ff, d_model);
+    intermediate_hashes.push(("ffn_out".to_string(), hash_tensor(&ffn_out)));
+
+    let output = add_tensors(&residual, &ffn_out);
+
+    let flops = transformer_block_flops(
+        batch_size,
+        seq_length,
+        d_model,
+        num_heads,
+        d_ff,
+        feed_forward,
+    )?;
+
+    Ok((
+        OperationOutput {
+            result_hash: hash_tensor(&output),
+            flops,
+            intermediate_hashes,
+            metrics: Vec::new(),
+        },
+        tensors.with_fp8_output(&output),
+    ))
+}
+
+/// FLOP count of a transformer block
+pub fn transformer_block_flops(
+    batch_size: usize,
+    seq_length: usize,
+    d_model: usize,
+    num_heads: usize,
+    d_ff: usize,
+    feed_forward: FeedForwardType,
+) -> Result<u64> {
+    let (batch, seq) = (batch_size, seq_length);
+    let d_k = d_model / num_heads;
+
+    // Two layer norms: mean, variance, normalize, scale and shift per element
+    let norm_flops = flops_product(&[2 * 8, batch, seq, d_model])?;
+    // Q, K, V and output projections
+    let projection_flops = flops_product(&[4 * 2, batch, seq, d_model, d_model])?;
+    // Scores (Q * K^T) and weighted values
+    let attention_flops = flops_product(&[2 * 2, batch, num_heads, seq, seq, d_k])?;
+    // Two residual additions
+    let residual_flops = flops_product(&[2, batch, seq, d_model])?;
+    let ffn_flops = match feed_forward {
+        // Up and down projections, activation
+        FeedForwardType::GELU => flops_sum(&[
+            flops_product(&[2 * 2, batch, seq, d_model, d_ff])?,
+            flops_product(&[batch, seq, d_ff])?,
+        ])?,
+        // Gate, up and down projections, activation and gating product
+        FeedForwardType::SwiGLU => flops_sum(&[
+            flops_product(&[3 * 2, batch, seq, d_model, d_ff])?,
+            flops_product(&[2, batch, seq, d_ff])?,
+        ])?,
+    };
+
+    flops_sum(&[
+        norm_flops,
+        projection_flops,
+        attention_flops,
+        residual_flops,
+        ffn_flops,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transformer_block_execution() {
+        for feed_forward in [FeedForwardType::GELU, FeedForwardType::SwiGLU] {
+            let output = execute_transformer_block(2, 8, 32, 4, 64, feed_forward, 42).unwrap();
+
+            assert!(!output.result_hash.is_empty());
+            assert!(output.flops > 0);
+
+            let names: Vec<&str> = output
+                .intermediate_hashes
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect();
+            assert_eq!(
+                names,
+                [
+                    "ln1",
+                    "attention",
+                    "residual1",
+                    "ln2",
+                    "ffn_hidden",
+                    "ffn_out"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_transformer_block_deterministic() {
+        let result1 =
+            execute_transformer_block(1, 4, 16, 2, 32, FeedForwardType::SwiGLU, 7).unwrap();
+        let result2 =
+            execute_transformer_block(1, 4, 16, 2, 32, FeedForwardType::SwiGLU, 7).unwrap();
+
+        assert_eq!(result1.result_hash, result2.result_hash);
+        assert_eq!(result1.intermediate_hashes, result2.intermediate_hashes);
+        assert_eq!(result1.flops, result2.flops);
+
+        // The feed-forward variant changes the FFN but not the attention sub-layer
+        let gelu = execute_transformer_block(1, 4, 16, 2, 32, FeedForwardType::GELU, 7).unwrap();
+        assert_eq!(
+            gelu.intermediate_hashes[..4],
+            result1.intermediate_hashes[..4]
+        );
+        assert_ne!(gelu.result_hash, result1.result_hash);
+    }
+
+    #[test]
+    fn test_transformer_block_invalid_heads() {
+        let result = execute_transformer_block(1, 4, 30, 4, 32, FeedForwardType::GELU, 1);
+        assert!(result.is_err());
+        let result = execute_transformer_block(1, 4, 0, 4, 32, FeedForwardType::GELU, 1);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,461 @@
+use crate::cost::estimate_flops;
+use crate::graph::{execute_graph, OperationGraph};
+use crate::store::TensorStore;
+use demle_core::proof::parse_hash;
+use demle_core::{MLOperation, Result, WorkResult, WorkUnit};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Peak throughput above which a claimed execution time is implausible, in
+/// TFLOPS; dense FP8 on an H100 SXM peaks near 2000
+pub const DEFAULT_MAX_TERAFLOPS: f64 = 4000.0;
+
+/// Outcome of verifying a work result, with the process exit code it maps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationStatus {
+    /// Every hash and FLOP count matches and the timing is plausible
+    Verified,
+    /// An operation or node hash differs, the result doesn't belong to the
+    /// work unit, the work unit's seeds aren't derived from its chain state,
+    /// the result isn't signed by the work unit's miner, or its hash isn't a
+    /// header hash meeting the work unit's difficulty
+    HashMismatch,
+    /// Hashes match but claimed FLOP counts don't
+    FlopsDiscrepancy,
+    /// Hashes and FLOPs match but the claimed time implies an impossible
+    /// throughput
+    ImplausibleTiming,
+    /// The work unit has a malformed operation or dependency graph, so it
+    /// wasn't recomputed
+    InvalidWorkUnit,
+}
+
+impl VerificationStatus {
+    /// Exit code for scripts; 5 is left for unreadable input and 2 for usage errors
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Verified => 0,
+            Self::HashMismatch => 1,
+            Self::FlopsDiscrepancy => 3,
+            Self::ImplausibleTiming => 4,
+            Self::InvalidWorkUnit => 6,
+        }
+    }
+}
+
+/// Claimed and recomputed values of one operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationCheck {
+    pub index: usize,
+    /// Node name in the work unit's dependency graph
+    pub name: String,
+    pub operation: String,
+    pub claimed_hash: Option<String>,
+    pub recomputed_hash: String,
+    pub claimed_flops: Option<u64>,
+    pub recomputed_flops: u64,
+}
+
+impl OperationCheck {
+    pub fn hash_matches(&self) -> bool {
+        self.claimed_hash.as_deref() == Some(self.recomputed_hash.as_str())
+    }
+
+    pub fn flops_match(&self) -> bool {
+        self.claimed_flops == Some(self.recomputed_flops)
+    }
+}
+
+/// Per-operation comparison of a work result against a full recomputation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub work_id: String,
+    /// Why the work unit can't be executed, one entry per malformed operation
+    pub validation_errors: Vec<String>,
+    /// Whether the result names the work unit it is checked against
+    pub work_id_matches: bool,
+    /// Whether the operation seeds are derived from the previous hash, miner
+    /// address and sequence number, so the work wasn't precomputed
+    pub seeds_derived: bool,
+    /// Whether the proof is signed by the miner the seeds were derived for
+    pub signed_by_miner: bool,
+    /// Whether the result hash is the hash of the rebuilt block header and
+    /// meets the target for the work unit's difficulty
+    pub proof_of_work: bool,
+    pub checks: Vec<OperationCheck>,
+    /// Claimed results beyond the work unit's operations
+    pub extra_results: usize,
+    /// Node hashes that differ from the recomputed graph, by node name
+    pub node_hash_mismatches: Vec<String>,
+    pub claimed_flops: u64,
+    pub recomputed_flops: u64,
+    pub claimed_time_ms: u64,
+    pub recomputed_time_ms: u64,
+    /// Throughput implied by the claimed FLOPs and time, in TFLOPS
+    pub claimed_teraflops: f64,
+    pub max_teraflops: f64,
+}
+
+impl VerificationReport {
+    pub fn timing_plausible(&self) -> bool {
+        self.claimed_teraflops <= self.max_teraflops
+    }
+
+    pub fn status(&self) -> VerificationStatus {
+        if !self.validation_errors.is_empty() {
+            VerificationStatus::InvalidWorkUnit
+        } else if !self.work_id_matches
+            || !self.seeds_derived
+            || !self.signed_by_miner
+            || !self.proof_of_work
+            || self.extra_results > 0
+            || !self.node_hash_mismatches.is_empty()
+            || !self.checks.iter().all(OperationCheck::hash_matches)
+        {
+            VerificationStatus::HashMismatch
+        } else if self.claimed_flops != self.recomputed_flops
+            || !self.checks.iter().all(OperationCheck::flops_match)
+        {
+            VerificationStatus::FlopsDiscrepancy
+        } else if !self.timing_plausible() {
+            VerificationStatus::ImplausibleTiming
+        } else {
+            VerificationStatus::Verified
+        }
+    }
+}
+
+/// Reasons the operations of a work unit can't be executed: a malformed
+/// dependency graph or operations the kernels would reject
+pub fn validate_operations(operations: &[MLOperation]) -> Vec<String> {
+    let mut errors: Vec<String> = OperationGraph::build(operations)
+        .err()
+        .map(|e| e.to_string())
+        .into_iter()
+        .collect();
+    errors.extend(
+        operations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, operation)| {
+                estimate_flops(operation)
+                    .err()
+                    .map(|e| format!("[{}] {}: {}", index, operation, e))
+            }),
+    );
+    errors
+}
+
+/// Recompute every operation of the work unit on this build's kernels, one
+/// node at a time, and compare the results with the claimed ones. Stored
+/// input tensors are read from `store`. A work unit that fails validation
+/// isn't recomputed and reports `InvalidWorkUnit`.
+pub fn verify_work_result(
+    work_unit: &WorkUnit,
+    result: &WorkResult,
+    store: Option<&TensorStore>,
+    max_teraflops: f64,
+) -> Result<VerificationReport> {
+    let validation_errors = validate_operations(&work_unit.operations);
+    let start = Instant::now();
+    let (checks, node_hash_mismatches, recomputed_flops) = if validation_errors.is_empty() {
+        recompute(work_unit, result, store)?
+    } else {
+        (Vec::new(), Vec::new(), 0)
+    };
+    let recomputed_time_ms = start.elapsed().as_millis() as u64;
+
+    let claimed_teraflops = if result.execution_time_ms == 0 {
+        if result.total_flops == 0 {
+            0.0
+        } else {
+            f64::INFINITY
+        }
+    } else {
+        result.total_flops as f64 / 1e12 / (result.execution_time_ms as f64 / 1000.0)
+    };
+
+    Ok(VerificationReport {
+        work_id: result.work_id.clone(),
+        validation_errors,
+        work_id_matches: result.work_id == work_unit.id,
+        seeds_derived: work_unit.seeds_match().unwrap_or(false),
+        signed_by_miner: result.signed_by_miner(work_unit),
+        proof_of_work: proof_of_work(work_unit, result).unwrap_or(false),
+        checks,
+        extra_results: result
+            .operation_results
+            .len()
+            .saturating_sub(work_unit.operations.len()),
+        node_hash_mismatches,
+        claimed_flops: result.total_flops,
+        recomputed_flops,
+        claimed_time_ms: result.execution_time_ms,
+        recomputed_time_ms,
+        claimed_teraflops,
+        max_teraflops,
+    })
+}
+
+/// Whether the claimed hash is the header hash of the proof rebuilt from the
+/// result and meets the work unit's difficulty
+fn proof_of_work(work_unit: &WorkUnit, result: &WorkResult) -> Result<bool> {
+    let proof = result.proof(work_unit)?;
+    Ok(parse_hash(&result.hash)? == parse_hash(&proof.work_hash)?
+        && proof.verify(work_unit.difficulty)?)
+}
+
+/// Per-operation checks, mismatched node names and total recomputed FLOPs of
+/// a validated work unit
+fn recompute(
+    work_unit: &WorkUnit,
+    result: &WorkResult,
+    store: Option<&TensorStore>,
+) -> Result<(Vec<OperationCheck>, Vec<String>, u64)> {
+    let graph = OperationGraph::build(&work_unit.operations)?;
+    let recomputed = execute_graph(&graph, &work_unit.operations, store, false)?;
+
+    let checks: Vec<OperationCheck> = recomputed
+        .iter()
+        .enumerate()
+        .map(|(index, expected)| {
+            let claimed = result.operation_results.get(index);
+            OperationCheck {
+                index,
+                name: graph.names[index].clone(),
+                operation: work_unit.operations[index].to_string(),
+                claimed_hash: claimed.map(|c| c.result_hash.clone()),
+                recomputed_hash: expected.result_hash.clone(),
+                claimed_flops: claimed.map(|c| c.flops),
+                recomputed_flops: expected.flops,
+            }
+        })
+        .collect();
+
+    // Node hashes are optional, but any that are reported must match
+    let expected_nodes = graph.node_hashes(&recomputed);
+    let node_hash_mismatches = result
+        .node_hashes
+        .iter()
+        .filter(|node| !expected_nodes.contains(node))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    Ok((
+        checks,
+        node_hash_mismatches,
+        recomputed.iter().map(|r| r.flops).sum(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_work_unit;
+    use demle_core::signing::MinerKey;
+
+    fn miner_key() -> MinerKey {
+        MinerKey::from_hex(&"12".repeat(32)).unwrap()
+    }
+
+    fn work_unit() -> WorkUnit {
+        let mut unit = WorkUnit {
+            id: "unit-1".to_string(),
+            previous_hash: "0".repeat(64),
+            timestamp: 0,
+            difficulty: 1,
+            operations: vec![
+                MLOperation::MatrixMultiply {
+                    dimensions: (16, 16, 16),
+                    seed: 1,
+                },
+                MLOperation::Softmax {
+                    rows: 4,
+                    cols: 8,
+                    log: true,
+                    seed: 2,
+                },
+            ],
+            nonce_range: (0, 1),
+            miner_address: miner_key().address(),
+            sequence: 0,
+        };
+        unit.derive_seeds().unwrap();
+        unit
+    }
+
+    fn honest_result(unit: &WorkUnit) -> WorkResult {
+        let operation_results = execute_work_unit(&unit.operations).unwrap();
+        let graph = OperationGraph::build(&unit.operations).unwrap();
+        let mut result = WorkResult {
+            work_id: unit.id.clone(),
+            nonce: 0,
+            hash: String::new(),
+            execution_time_ms: 10,
+            total_flops: operation_results.iter().map(|r| r.flops).sum(),
+            node_hashes: graph.node_hashes(&operation_results),
+            operation_results,
+            beneficiary: String::new(),
+            signature: String::new(),
+        };
+        let mut proof = result.proof(unit).unwrap();
+        while !proof.verify(unit.difficulty).unwrap() {
+            let nonce = proof.header.nonce + 1;
+            proof = proof.with_nonce(nonce);
+        }
+        let proof = proof.sign(&miner_key()).unwrap();
+        result.nonce = proof.header.nonce;
+        result.hash = proof.work_hash;
+        result.beneficiary = proof.beneficiary;
+        result.signature = proof.signature;
+        result
+    }
+
+    #[test]
+    fn test_verify_honest_and_tampered_results() {
+        let unit = work_unit();
+        let honest = honest_result(&unit);
+        let report = verify_work_result(&unit, &honest, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::Verified);
+        assert_eq!(report.status().exit_code(), 0);
+        assert!(report
+            .checks
+            .iter()
+            .all(|c| c.hash_matches() && c.flops_match()));
+
+        let mut wrong_hash = honest.clone();
+        wrong_hash.operation_results[1].result_hash = "f".repeat(64);
+        let report = verify_work_result(&unit, &wrong_hash, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+        assert!(report.checks[0].hash_matches() && !report.checks[1].hash_matches());
+
+        let mut missing = honest.clone();
+        missing.operation_results.pop();
+        let report = verify_work_result(&unit, &missing, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+
+        let mut inflated = honest.clone();
+        inflated.operation_results[0].flops *= 2;
+        inflated.total_flops += honest.operation_results[0].flops;
+        inflated.signature = inflated
+            .proof(&unit)
+            .unwrap()
+            .sign(&miner_key())
+            .unwrap()
+            .signature;
+        let report = verify_work_result(&unit, &inflated, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::FlopsDiscrepancy);
+
+        let mut precomputed = unit.clone();
+        precomputed.sequence = 1;
+        let report =
+            verify_work_result(&precomputed, &honest, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(!report.seeds_derived);
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+
+        // Resubmitting the result for another beneficiary fails, whether the
+        // original signature is kept or the result is re-signed
+        let thief = MinerKey::from_hex(&"34".repeat(32)).unwrap();
+        let mut stolen = honest.clone();
+        stolen.beneficiary = thief.address();
+        let report = verify_work_result(&unit, &stolen, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(!report.signed_by_miner);
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+        let resigned = stolen.proof(&unit).unwrap().sign(&thief).unwrap();
+        assert!(resigned.verify_signature());
+        stolen.signature = resigned.signature;
+        let report = verify_work_result(&unit, &stolen, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+
+        let mut instant = honest.clone();
+        instant.execution_time_ms = 0;
+        let report = verify_work_result(&unit, &instant, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::ImplausibleTiming);
+        assert_eq!(report.status().exit_code(), 4);
+    }
+
+    #[test]
+    fn test_verify_proof_of_work() {
+        let mut unit = work_unit();
+        unit.difficulty = 16;
+        let honest = honest_result(&unit);
+        let report = verify_work_result(&unit, &honest, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(report.proof_of_work);
+        assert_eq!(report.status(), VerificationStatus::Verified);
+
+        let mut forged = honest.clone();
+        forged.hash = "deadbeef".to_string();
+        let report = verify_work_result(&unit, &forged, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(!report.proof_of_work);
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+
+        // A nonce whose header hash misses the target is rejected even when
+        // the claimed hash is that header hash
+        let mut missed = honest.clone();
+        let mut proof = missed.proof(&unit).unwrap();
+        while proof.verify(unit.difficulty).unwrap() {
+            let nonce = proof.header.nonce + 1;
+            proof = proof.with_nonce(nonce);
+        }
+        let proof = proof.sign(&miner_key()).unwrap();
+        missed.nonce = proof.header.nonce;
+        missed.hash = proof.work_hash;
+        missed.signature = proof.signature;
+        let report = verify_work_result(&unit, &missed, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(report.signed_by_miner && !report.proof_of_work);
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+
+        // Results mined for an easier difficulty don't meet a harder one
+        let mut harder = unit.clone();
+        harder.difficulty = u64::MAX;
+        let hard_result = WorkResult {
+            work_id: harder.id.clone(),
+            ..honest.clone()
+        };
+        let report =
+            verify_work_result(&harder, &hard_result, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert!(!report.proof_of_work);
+        assert_eq!(report.status(), VerificationStatus::HashMismatch);
+    }
+
+    #[test]
+    fn test_verify_malformed_work_unit() {
+        let unit = work_unit();
+        let honest = honest_result(&unit);
+
+        let mut malformed = unit.clone();
+        malformed.operations = vec![
+            MLOperation::Convolution2D {
+                input_shape: (1, 2, 4, 4),
+                kernel_shape: (4, 2, 3, 3),
+                stride: (0, 1),
+                padding: (0, 0),
+                seed: 0,
+            },
+            MLOperation::TransformerBlock {
+                batch_size: 1,
+                seq_length: 4,
+                d_model: 0,
+                num_heads: 2,
+                d_ff: 8,
+                feed_forward: demle_core::FeedForwardType::GELU,
+                seed: 0,
+            },
+            MLOperation::Softmax {
+                rows: 4,
+                cols: 8,
+                log: true,
+                seed: 0,
+            },
+        ];
+        malformed.derive_seeds().unwrap();
+
+        let report = verify_work_result(&malformed, &honest, None, DEFAULT_MAX_TERAFLOPS).unwrap();
+        assert_eq!(report.status(), VerificationStatus::InvalidWorkUnit);
+        assert_eq!(report.status().exit_code(), 6);
+        assert_eq!(report.validation_errors.len(), 2);
+        assert!(report.validation_errors[0].starts_with("[0] "));
+        assert!(report.validation_errors[1].starts_with("[1] "));
+        assert!(report.checks.is_empty());
+    }
+}
@@ -1,8 +1,23 @@
-use clap::Parser;
-use demle_core::{types::MiningStats, MLOperation, NetworkConfig, WorkUnit};
-use demle_fp8::{execute_ml_operation, flops_to_teraflops};
+use clap::{Parser, Subcommand};
+use demle_core::difficulty::difficulty_to_target;
+use demle_core::proof::{BlockHeader, NonceSearch, Proof};
+use demle_core::signing::MinerKey;
+use demle_core::{
+    types::MiningStats, MLOperation, NetworkConfig, NumericFormat, WorkResult, WorkUnit,
+};
+use demle_fp8::conformance::{self, ConformanceVectors, NativeBackend};
+use demle_fp8::graph::{execute_graph, OperationGraph};
+use demle_fp8::inference::{self, CnnModel, LabeledImages};
+use demle_fp8::quantize::TensorData;
+use demle_fp8::tensor_file::{load_tensors, save_tensors, NamedTensor};
+use demle_fp8::verify;
+use demle_fp8::{flops_to_teraflops, TensorStore};
 use demle_rpc::DemleRpcClient;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use rayon::prelude::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::{info, warn};
 
 #[cfg(feature = "cuda")]
@@ -16,7 +31,7 @@ struct Args {
     #[arg(short = 'j', long, default_value = "4")]
     threads: usize,
 
-    /// Mining target in teraflops
+    /// Mining target in teraflops, for reporting throughput; the difficulty comes from the chain
     #[arg(short, long, default_value = "150.0")]
     target_teraflops: f64,
 
@@ -28,9 +43,84 @@ struct Args {
     #[arg(long, default_value = "http://localhost:8545")]
     rpc_url: String,
     
-    /// Contract address for DEMLE token
+    /// Contract address for DEMLE token, required for mining
     #[arg(long)]
-    contract: String,
+    contract: Option<String>,
+
+    /// File holding the hex secp256k1 key that signs proofs; its address receives the
+    /// rewards and work units are derived for it. Defaults to demle-miner.key, which is
+    /// created with a new key if it doesn't exist
+    #[arg(long)]
+    miner_key_file: Option<PathBuf>,
+
+    /// File holding the next work-unit sequence number, which is mixed into the operation
+    /// seeds. It is advanced before each work unit, so seeds aren't reused across restarts.
+    /// Defaults to demle-miner.sequence
+    #[arg(long)]
+    sequence_file: Option<PathBuf>,
+
+    /// Directory of stored tensors that operations read inputs from and write outputs to
+    #[arg(long)]
+    tensor_store: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run batched FP8 inference of an image classifier and check its accuracy.
+    /// Without --model it runs a synthetic benchmark with hand-set weights.
+    Infer {
+        /// Trained model weights (.safetensors); defaults to the synthetic
+        /// stripe benchmark, whose weights are not trained
+        #[arg(long)]
+        model: Option<PathBuf>,
+
+        /// Images and labels (.safetensors); defaults to the synthetic stripe images
+        #[arg(long)]
+        dataset: Option<PathBuf>,
+
+        /// Images per forward pass
+        #[arg(long, default_value = "16")]
+        batch_size: usize,
+
+        /// Write logits and predictions to a .safetensors file
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Recompute a submitted work result and compare it operation by operation.
+    /// Exits 0 when verified, 1 on hash mismatches, 3 on FLOP discrepancies,
+    /// 4 on implausible timing, 5 on unreadable input or stored tensors and
+    /// 6 on a malformed work unit.
+    Verify {
+        /// Work unit as JSON
+        #[arg(long)]
+        work_unit: PathBuf,
+
+        /// Work result as JSON
+        #[arg(long)]
+        work_result: PathBuf,
+
+        /// Highest plausible throughput in TFLOPS
+        #[arg(long, default_value_t = verify::DEFAULT_MAX_TERAFLOPS)]
+        max_teraflops: f64,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check this build's kernels against golden conformance vectors. Exits 0 when
+    /// every result matches and 1 otherwise.
+    Conformance {
+        /// Vectors as JSON; defaults to the checked-in reference vectors
+        #[arg(long)]
+        vectors: Option<PathBuf>,
+
+        /// Write vectors generated by this build to a file instead of checking
+        #[arg(long)]
+        generate: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -43,6 +133,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_env_filter(format!("demle_miner={}", log_level))
         .init();
 
+    let tensor_store = args.tensor_store.map(TensorStore::open).transpose()?;
+    if let Some(store) = &tensor_store {
+        info!("Tensor store: {}", store.root().display());
+    }
+
+    match args.command {
+        Some(Command::Infer {
+            model,
+            dataset,
+            batch_size,
+            output,
+        }) => {
+            return run_inference(
+                model.as_deref(),
+                dataset.as_deref(),
+                batch_size,
+                output.as_deref(),
+                tensor_store.as_ref(),
+            );
+        }
+        Some(Command::Verify {
+            work_unit,
+            work_result,
+            max_teraflops,
+            json,
+        }) => {
+            let code = match run_verify(
+                &work_unit,
+                &work_result,
+                max_teraflops,
+                json,
+                tensor_store.as_ref(),
+            ) {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    VERIFY_INPUT_ERROR
+                }
+            };
+            std::process::exit(code);
+        }
+        Some(Command::Conformance { vectors, generate }) => {
+            let code = run_conformance(vectors.as_deref(), generate.as_deref())?;
+            std::process::exit(code);
+        }
+        None => {}
+    }
+    let contract = args.contract.ok_or("--contract is required for mining")?;
+
     info!("🚀 Starting DEMLE FP8 Miner");
     info!("Threads: {}", args.threads);
     info!("Target: {:.2} TeraFLOPS", args.target_teraflops);
@@ -50,40 +189,372 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let network_config = NetworkConfig {
         rpc_url: args.rpc_url,
-        contract_address: args.contract,
+        contract_address: contract,
         ..Default::default()
     };
 
-    let mut miner = Miner::new(network_config, args.threads, args.target_teraflops).await?;
+    let miner_key = load_miner_key(args.miner_key_file.as_deref())?;
+    let sequence_file = args
+        .sequence_file
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SEQUENCE_FILE));
+
+    let mut miner = Miner::new(
+        network_config,
+        args.threads,
+        args.target_teraflops,
+        tensor_store,
+        miner_key,
+        sequence_file,
+    )
+    .await?;
     miner.start_mining().await?;
 
     Ok(())
 }
 
+/// Sequence file used when --sequence-file is omitted
+const DEFAULT_SEQUENCE_FILE: &str = "demle-miner.sequence";
+
+/// Read the next work-unit sequence number, starting at 0 if the file doesn't exist yet
+fn load_sequence(path: &Path) -> Result<u64, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(contents
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid sequence number in {}: {}", path.display(), e))?)
+}
+
+/// Key file used when --miner-key-file is omitted
+const DEFAULT_KEY_FILE: &str = "demle-miner.key";
+
+/// Read the miner key, or without a path, read the default key file and create it with a
+/// new key, readable only by the owner, if it doesn't exist yet. The secret is never logged.
+fn load_miner_key(path: Option<&Path>) -> Result<MinerKey, Box<dyn std::error::Error>> {
+    let read = |path: &Path| -> Result<MinerKey, Box<dyn std::error::Error>> {
+        let secret = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Ok(MinerKey::from_hex(&secret)?)
+    };
+    if let Some(path) = path {
+        return read(path);
+    }
+
+    let path = Path::new(DEFAULT_KEY_FILE);
+    if path.exists() {
+        return read(path);
+    }
+    let key = MinerKey::generate();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    writeln!(file, "{}", key.to_hex())?;
+    warn!(
+        "🔑 No --miner-key-file given, created {} for address {}; back it up to keep the rewards",
+        path.display(),
+        key.address()
+    );
+    Ok(key)
+}
+
+/// Run the classifier over the labeled images and report predictions and accuracy
+fn run_inference(
+    model_path: Option<&Path>,
+    dataset_path: Option<&Path>,
+    batch_size: usize,
+    output: Option<&Path>,
+    tensor_store: Option<&TensorStore>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let model_tensors = match model_path {
+        Some(path) => load_tensors(path, NumericFormat::FP8E4M3)?,
+        None => {
+            info!("🧪 No model given: running the untrained synthetic stripe benchmark");
+            inference::benchmark_model()?
+        }
+    };
+    let dataset_tensors = match dataset_path {
+        Some(path) => load_tensors(path, NumericFormat::FP8E4M3)?,
+        None => inference::benchmark_dataset()?,
+    };
+    let model = CnnModel::from_tensors(&model_tensors)?;
+    let dataset = LabeledImages::from_tensors(&dataset_tensors)?;
+
+    info!(
+        "🧠 Running inference on {} images in batches of {}",
+        dataset.len(),
+        batch_size
+    );
+    let start = Instant::now();
+    let report = inference::run_inference(&model, &dataset, batch_size)?;
+    let elapsed = start.elapsed();
+
+    let correct = report
+        .predictions
+        .iter()
+        .zip(&dataset.labels)
+        .filter(|(p, l)| p == l)
+        .count();
+    info!(
+        "🎯 Accuracy: {:.2}% ({}/{})",
+        report.accuracy * 100.0,
+        correct,
+        dataset.len()
+    );
+    info!("🔢 Predictions: {:?}", report.predictions);
+    info!("🔑 Logits hash: {}", report.output.result_hash);
+    info!(
+        "⚡ {} FLOPs in {:.2} ms",
+        report.output.flops,
+        elapsed.as_secs_f64() * 1000.0
+    );
+
+    if let Some(store) = tensor_store {
+        let logits = store.put(&report.logits)?;
+        info!("💾 Stored logits as {}", logits.hash);
+    }
+    if let Some(path) = output {
+        let predictions: Vec<f32> = report.predictions.iter().map(|&p| p as f32).collect();
+        let tensors = [
+            NamedTensor::from_stored("logits", &report.logits),
+            NamedTensor::new(
+                "predictions",
+                vec![predictions.len()],
+                TensorData::F32(predictions),
+            )?,
+        ];
+        save_tensors(path, &tensors)?;
+        info!("💾 Wrote logits and predictions to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Check the native kernels against conformance vectors, or generate new ones, returning
+/// the exit code
+fn run_conformance(
+    vectors_path: Option<&Path>,
+    generate: Option<&Path>,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    if let Some(path) = generate {
+        let vectors = conformance::generate_vectors(&NativeBackend)?;
+        std::fs::write(path, serde_json::to_string_pretty(&vectors)? + "\n")?;
+        info!(
+            "💾 Wrote {} operation vectors to {}",
+            vectors.operations.len(),
+            path.display()
+        );
+        return Ok(0);
+    }
+
+    let vectors: ConformanceVectors = match vectors_path {
+        Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+        None => conformance::reference_vectors()?,
+    };
+    let report = conformance::run_conformance(&NativeBackend, &vectors)?;
+    for failure in &report.failures {
+        println!(
+            "  ✗ {}: expected {}, got {}",
+            failure.check, failure.expected, failure.actual
+        );
+    }
+    println!(
+        "{} backend: {}/{} checks passed",
+        report.backend,
+        report.checks - report.failures.len(),
+        report.checks
+    );
+    Ok(if report.passed() { 0 } else { 1 })
+}
+
+/// Nonces per work unit; the work commitment differs between units, so every range starts at 0
+const NONCE_RANGE: u64 = 1 << 32;
+
+/// Nonces each thread tries before checking whether another found a solution
+const NONCE_CHUNK: u64 = 1 << 16;
+
+/// Search a nonce range on all rayon threads, returning the lowest solution of the first
+/// chunk found to contain one
+fn search_nonce_parallel(
+    header: &BlockHeader,
+    nonce_range: (u64, u64),
+    target: &[u8; 32],
+) -> NonceSearch {
+    let (start, end) = nonce_range;
+    let hashes = AtomicU64::new(0);
+    let chunks = end.saturating_sub(start).div_ceil(NONCE_CHUNK);
+
+    let nonce = (0..chunks).into_par_iter().find_map_any(|chunk| {
+        let chunk_start = start + chunk * NONCE_CHUNK;
+        let search =
+            header.search_nonce((chunk_start, (chunk_start + NONCE_CHUNK).min(end)), target);
+        hashes.fetch_add(search.hashes, Ordering::Relaxed);
+        search.nonce
+    });
+
+    NonceSearch {
+        nonce,
+        hashes: hashes.into_inner(),
+    }
+}
+
+/// Exit code of `verify` when the work unit, result or stored tensors can't be read
+const VERIFY_INPUT_ERROR: i32 = 5;
+
+/// Verify a serialized work result against its work unit, returning the exit code
+fn run_verify(
+    work_unit_path: &Path,
+    work_result_path: &Path,
+    max_teraflops: f64,
+    json: bool,
+    tensor_store: Option<&TensorStore>,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+    let work_unit: WorkUnit = serde_json::from_str(&read(work_unit_path)?)?;
+    let work_result: WorkResult = serde_json::from_str(&read(work_result_path)?)?;
+
+    let report = verify::verify_work_result(&work_unit, &work_result, tensor_store, max_teraflops)?;
+    let status = report.status();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(status.exit_code());
+    }
+
+    println!(
+        "Work unit {} ({} operations)",
+        work_unit.id,
+        work_unit.operations.len()
+    );
+    for error in &report.validation_errors {
+        println!("  ✗ invalid: {}", error);
+    }
+    if !report.work_id_matches {
+        println!("  ✗ result is for work unit {}", report.work_id);
+    }
+    if !report.proof_of_work {
+        println!(
+            "  ✗ result hash is not a header hash meeting difficulty {}",
+            work_unit.difficulty
+        );
+    }
+    for check in &report.checks {
+        let mark = if check.hash_matches() && check.flops_match() {
+            "✓"
+        } else {
+            "✗"
+        };
+        println!(
+            "  {} [{}] {}: {}",
+            mark, check.index, check.name, check.operation
+        );
+        if !check.hash_matches() {
+            println!(
+                "      hash: claimed {}, recomputed {}",
+                check.claimed_hash.as_deref().unwrap_or("<missing>"),
+                check.recomputed_hash
+            );
+        }
+        if !check.flops_match() {
+            let claimed = check
+                .claimed_flops
+                .map_or("<missing>".to_string(), |f| f.to_string());
+            println!(
+                "      flops: claimed {}, recomputed {}",
+                claimed, check.recomputed_flops
+            );
+        }
+    }
+    if report.extra_results > 0 {
+        println!(
+            "  ✗ {} results beyond the work unit's operations",
+            report.extra_results
+        );
+    }
+    for name in &report.node_hash_mismatches {
+        println!("  ✗ node hash of {} does not match", name);
+    }
+    println!(
+        "FLOPs: claimed {}, recomputed {}",
+        report.claimed_flops, report.recomputed_flops
+    );
+    println!(
+        "Time: claimed {} ms ({:.2} TFLOPS, limit {:.0}), recomputed in {} ms",
+        report.claimed_time_ms,
+        report.claimed_teraflops,
+        report.max_teraflops,
+        report.recomputed_time_ms
+    );
+    println!("Status: {:?}", status);
+
+    Ok(status.exit_code())
+}
+
 struct Miner {
     config: NetworkConfig,
     rpc_client: DemleRpcClient,
     threads: usize,
     target_teraflops: f64,
+    tensor_store: Option<TensorStore>,
     stats: MiningStats,
     start_time: Instant,
+    /// Time spent searching nonces, for the hashrate
+    hashing_time: Duration,
+    /// Key that signs proofs; its address is the beneficiary and the one operation
+    /// seeds are derived for
+    miner_key: MinerKey,
+    miner_address: String,
+    /// Sequence number of the next work unit, persisted in `sequence_file`
+    sequence: u64,
+    sequence_file: PathBuf,
 }
 
 impl Miner {
-    async fn new(config: NetworkConfig, threads: usize, target_teraflops: f64) -> Result<Self, Box<dyn std::error::Error>> {
+    async fn new(
+        config: NetworkConfig,
+        threads: usize,
+        target_teraflops: f64,
+        tensor_store: Option<TensorStore>,
+        miner_key: MinerKey,
+        sequence_file: PathBuf,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let mut rpc_client = DemleRpcClient::new(config.clone());
         
         // Initialize the contract
         rpc_client.init_contract().await
             .map_err(|e| format!("Failed to initialize contract: {}", e))?;
-        
+
+        let miner_address = miner_key.address();
+        info!("👷 Miner address: {}", miner_address);
+        let sequence = load_sequence(&sequence_file)?;
+        info!(
+            "🔢 Next work unit sequence: {} ({})",
+            sequence,
+            sequence_file.display()
+        );
+
         Ok(Self {
             config,
             rpc_client,
             threads,
             target_teraflops,
+            tensor_store,
             stats: MiningStats::default(),
             start_time: Instant::now(),
+            hashing_time: Duration::ZERO,
+            miner_key,
+            miner_address,
+            sequence,
+            sequence_file,
         })
     }
 
@@ -113,13 +584,18 @@ impl Miner {
             info!("💻 Running on CPU (compile with --features cuda for GPU acceleration)");
         }
 
-        let mut nonce = 0u64;
-
         loop {
-            let work_unit = self.generate_work_unit(nonce).await?;
+            let work_unit = self.generate_work_unit().await?;
 
             match self.mine_work_unit(&work_unit).await {
-                Ok(result) => {
+                Ok(None) => {
+                    info!(
+                        "🔁 Nonce range of {} exhausted without a solution",
+                        work_unit.id
+                    );
+                    self.print_stats();
+                }
+                Ok(Some(result)) => {
                     self.update_stats(&result);
                     
                     // Submit work to blockchain
@@ -147,12 +623,13 @@ impl Miner {
                 }
             }
 
-            nonce = nonce.wrapping_add(1);
             // No delay for maximum H100 utilization - removed artificial bottleneck
         }
     }
 
-    async fn generate_work_unit(&self, nonce: u64) -> Result<WorkUnit, Box<dyn std::error::Error>> {
+    /// Build the next work unit and persist the advanced sequence number before it is mined,
+    /// so a restart never derives the same seeds again
+    async fn generate_work_unit(&mut self) -> Result<WorkUnit, Box<dyn std::error::Error>> {
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
         // H100 Tensor Core Optimized: Memory-balanced massive operations
@@ -160,7 +637,7 @@ impl Miner {
             // Massive GEMM for maximum tensor core utilization (proven to work - 105+ TFLOPS!)
             MLOperation::MatrixMultiply {
                 dimensions: (16384, 16384, 8192), // ~4.3 TB FLOPS single operation!
-                seed: nonce,
+                seed: 0,
             },
             // Memory-optimized attention (proven to work - adds ~16 TFLOPS)
             MLOperation::MultiHeadAttention {
@@ -168,126 +645,144 @@ impl Miner {
                 seq_length: 1024, 
                 d_model: 4096, 
                 num_heads: 64, 
-                seed: nonce.wrapping_add(1),
+                seed: 0,
             },
             // Fast completing GEMM operation (replaces slow convolution)
             MLOperation::MatrixMultiply {
                 dimensions: (8192, 8192, 4096), // Smaller but fast GEMM, ~1 TB FLOPS
-                seed: nonce.wrapping_add(2),
+                seed: 0,
             },
         ];
 
-        Ok(WorkUnit {
-            id: format!("work_{}", nonce),
-            previous_hash: "0x0000000000000000000000000000000000000000".to_string(),
+        // Seeds come from the latest block, so work can't be precomputed or taken from another
+        // miner. The difficulty is the chain's: it sets the header hash target,
+        // ⌊(2²⁵⁶ − 1) / difficulty⌋, and is independent of the FLOPs the operations cost.
+        let sequence = self.sequence;
+        let mut work_unit = WorkUnit {
+            id: format!("work_{}", sequence),
+            previous_hash: self.rpc_client.get_latest_block_hash().await?,
             timestamp,
-            difficulty: (self.target_teraflops * 1e6) as u64,
+            difficulty: self.rpc_client.get_difficulty().await?,
             operations,
-            nonce_range: (nonce, nonce + 1000),
-        })
+            nonce_range: (0, NONCE_RANGE),
+            miner_address: self.miner_address.clone(),
+            sequence,
+        };
+        work_unit.derive_seeds()?;
+
+        let next = sequence
+            .checked_add(1)
+            .ok_or("Work unit sequence exhausted")?;
+        std::fs::write(&self.sequence_file, format!("{}\n", next))
+            .map_err(|e| format!("Failed to write {}: {}", self.sequence_file.display(), e))?;
+        self.sequence = next;
+        Ok(work_unit)
     }
 
+    /// Execute the work unit's operations, bind their results into a block header and
+    /// search the nonce range for a header hash that meets the difficulty target
     async fn mine_work_unit(
-        &self,
+        &mut self,
         work_unit: &WorkUnit,
-    ) -> Result<demle_core::WorkResult, Box<dyn std::error::Error>> {
+    ) -> Result<Option<demle_core::WorkResult>, Box<dyn std::error::Error>> {
         let start = Instant::now();
 
         info!("⚡ Mining work unit: {}", work_unit.id);
         info!("📋 Operations: {}", work_unit.operations.len());
 
+        let graph = OperationGraph::build(&work_unit.operations)?;
+
         // H100 Optimized: Sequential execution of massive operations
         // Parallel threads for GPU are counterproductive due to CUDA context overhead
         #[cfg(feature = "cuda")]
-        {
-            let mut operation_results = Vec::new();
-            let mut total_flops = 0u64;
-
-            // Execute each massive operation sequentially for maximum GPU utilization
-            for (i, operation) in work_unit.operations.iter().enumerate() {
-                info!("🔄 Executing MASSIVE operation {} on H100: {}", i + 1, operation);
-                
-                let result = execute_ml_operation(operation)?;
-                total_flops += result.flops;
-                operation_results.push(result);
-                
-                // Log progress for massive operations
-                info!("✅ Completed operation {} - {:.2} TFLOPS accumulated", 
-                      i + 1, total_flops as f64 / 1e12);
-                
-                // Memory cleanup between operations to prevent OOM
-                if i < work_unit.operations.len() - 1 { // Don't cleanup after last operation
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                    // Force garbage collection in Rust
-                    info!("🧹 Memory cleanup between operations...");
-                }
-            }
-            
-            let execution_time_ms = start.elapsed().as_millis() as u64;
-
-            // Simple hash combining all operation hashes
-            let hash_strings: Vec<String> = operation_results
-                .iter()
-                .map(|r| r.result_hash.clone())
-                .collect();
-
-            let combined_hash = format!("{}:{}", work_unit.nonce_range.0, hash_strings.join(","));
-            let result_hash = format!("{:x}", md5::compute(combined_hash));
-
-            Ok(demle_core::WorkResult {
-                work_id: work_unit.id.clone(),
-                nonce: work_unit.nonce_range.0,
-                hash: result_hash,
-                execution_time_ms,
-                total_flops,
-                operation_results,
-            })
-        }
+        let operation_results = {
+            info!(
+                "🔄 Executing {} MASSIVE operations on H100 in dependency order",
+                work_unit.operations.len()
+            );
+            execute_graph(
+                &graph,
+                &work_unit.operations,
+                self.tensor_store.as_ref(),
+                false,
+            )?
+        };
         #[cfg(not(feature = "cuda"))]
-        {
-            // Fallback to tokio async for CPU
-            let handles: Vec<_> = work_unit.operations
-                .iter()
-                .enumerate()
-                .map(|(i, operation)| {
-                    let op = operation.clone();
-                    tokio::spawn(async move {
-                        info!("🔄 Executing operation {} in parallel: {}", i + 1, op);
-                        execute_ml_operation(&op)
-                    })
-                })
-                .collect();
-
-            let mut operation_results = Vec::new();
-            let mut total_flops = 0u64;
-
-            // Collect results from parallel execution
-            for handle in handles {
-                let result = handle.await??;
-                total_flops += result.flops;
-                operation_results.push(result);
-            }
-
-            let execution_time_ms = start.elapsed().as_millis() as u64;
+        let operation_results = {
+            // Independent branches of the dependency graph run in parallel on the CPU
+            info!(
+                "🔄 Executing {} operations in {} parallel waves",
+                work_unit.operations.len(),
+                graph.waves.len()
+            );
+            let operations = work_unit.operations.clone();
+            let store = self.tensor_store.clone();
+            let graph = graph.clone();
+            tokio::task::spawn_blocking(move || {
+                execute_graph(&graph, &operations, store.as_ref(), true)
+            })
+            .await??
+        };
 
-            // Simple hash combining all operation hashes
-            let hash_strings: Vec<String> = operation_results
-                .iter()
-                .map(|r| r.result_hash.clone())
-                .collect();
+        let total_flops: u64 = operation_results.iter().map(|r| r.flops).sum();
+        info!(
+            "✅ Completed operations - {:.2} TFLOPS",
+            total_flops as f64 / 1e12
+        );
 
-            let combined_hash = format!("{}:{}", work_unit.nonce_range.0, hash_strings.join(","));
-            let result_hash = format!("{:x}", md5::compute(combined_hash));
+        let execution_time_ms = start.elapsed().as_millis() as u64;
 
-            Ok(demle_core::WorkResult {
-                work_id: work_unit.id.clone(),
-                nonce: work_unit.nonce_range.0,
-                hash: result_hash,
-                execution_time_ms,
-                total_flops,
-                operation_results,
-            })
+        // Block header hash binding the previous hash, the work unit and every operation's result root
+        let hash_strings: Vec<String> = operation_results
+            .iter()
+            .map(|r| r.result_hash.clone())
+            .collect();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let proof = Proof::new(
+            &work_unit.previous_hash,
+            &work_unit.commitment()?,
+            work_unit.nonce_range.0,
+            hash_strings,
+            total_flops,
+            timestamp,
+        )?;
+
+        // With the ML work bound into the header, each nonce costs one header hash
+        let header = proof.header;
+        let nonce_range = work_unit.nonce_range;
+        let target = difficulty_to_target(work_unit.difficulty);
+        let search_start = Instant::now();
+        let search = tokio::task::spawn_blocking(move || {
+            search_nonce_parallel(&header, nonce_range, &target)
+        })
+        .await?;
+        self.hashing_time += search_start.elapsed();
+        self.stats.total_hashes += search.hashes;
+        if !self.hashing_time.is_zero() {
+            self.stats.hashrate = self.stats.total_hashes as f64 / self.hashing_time.as_secs_f64();
         }
+
+        let Some(nonce) = search.nonce else {
+            return Ok(None);
+        };
+        self.stats.blocks_found += 1;
+        let proof = proof.with_nonce(nonce).sign(&self.miner_key)?;
+        info!(
+            "🔓 Nonce {} solves {} after {} hashes",
+            nonce, work_unit.id, search.hashes
+        );
+
+        Ok(Some(demle_core::WorkResult {
+            work_id: work_unit.id.clone(),
+            nonce,
+            hash: proof.work_hash,
+            execution_time_ms,
+            total_flops,
+            node_hashes: graph.node_hashes(&operation_results),
+            operation_results,
+            beneficiary: proof.beneficiary,
+            signature: proof.signature,
+        }))
     }
 
     fn update_stats(&mut self, result: &demle_core::WorkResult) {
@@ -298,12 +793,6 @@ impl Miner {
         if result.execution_time_ms > 0 {
             self.stats.teraflops = (result.total_flops as f64) / 1e12 / (result.execution_time_ms as f64 / 1000.0);
         }
-        
-        // Calculate rolling average hashrate
-        let elapsed_secs = self.stats.uptime_seconds as f64;
-        if elapsed_secs > 0.0 {
-            self.stats.hashrate = self.stats.total_operations as f64 / elapsed_secs;
-        }
     }
 
     fn print_stats(&self) {
@@ -314,9 +803,13 @@ impl Miner {
             self.stats.teraflops
         );
         println!(
-            "│ 🔥 Hashrate:  {:>8.1} ops/s          │",
+            "│ 🔥 Hashrate:  {:>8.1} H/s            │",
             self.stats.hashrate
         );
+        println!(
+            "│ 🧱 Blocks:    {:>7}                │",
+            self.stats.blocks_found
+        );
         println!(
             "│ 🧮 Total Ops: {:>7}                │",
             self.stats.total_operations
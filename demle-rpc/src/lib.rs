@@ -3,7 +3,7 @@ pub mod contract;
 
 use demle_core::{DemleError, NetworkConfig, Result};
 use web3::contract::{Contract, Options};
-use web3::types::{Address, Bytes, H256, U256};
+use web3::types::{Address, BlockId, BlockNumber, Bytes, H256, U256};
 use web3::Web3;
 use sha3::{Digest, Sha3_256};
 use rand::Rng;
@@ -71,6 +71,15 @@ impl DemleRpcClient {
         Ok(block_number.as_u64())
     }
 
+    /// Get the hash of the latest block, which work-unit seeds are derived from
+    pub async fn get_latest_block_hash(&self) -> Result<String> {
+        let block = self.web3.eth().block(BlockId::Number(BlockNumber::Latest)).await
+            .map_err(|e| DemleError::NetworkError(format!("Failed to get latest block: {}", e)))?;
+        let hash = block.and_then(|block| block.hash)
+            .ok_or_else(|| DemleError::NetworkError("Latest block has no hash".to_string()))?;
+        Ok(format!("{:?}", hash))
+    }
+
     /// Get current difficulty
     pub async fn get_difficulty(&self) -> Result<u64> {
         // For now, return default difficulty
@@ -96,23 +105,8 @@ impl DemleRpcClient {
         let nonce_hash = Sha3_256::digest(nonce_input.as_bytes());
         let nonce = H256::from_slice(&nonce_hash);
         
-        // Create ML proof from work result - add some additional verification data
-        let proof_data = serde_json::json!({
-            "work_id": work_result.work_id,
-            "nonce": work_result.nonce,
-            "hash": work_result.hash,
-            "execution_time_ms": work_result.execution_time_ms,
-            "total_flops": work_result.total_flops,
-            "operation_results": work_result.operation_results,
-            "timestamp": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            "verification": "demle_fp8_mining_proof"
-        });
-        
-        let ml_proof = serde_json::to_vec(&proof_data)
-            .map_err(|e| DemleError::SerializationError(format!("Failed to serialize proof: {}", e)))?;
+        // Binary encoding of the signed work result as the contract's mlProof
+        let ml_proof = work_result.to_binary()?;
         
         tracing::debug!(
             "Submitting nonce: {:?}, proof size: {} bytes",
@@ -120,7 +114,8 @@ impl DemleRpcClient {
             ml_proof.len()
         );
         
-        // Get accounts (use first account as default)
+        // The proof carries its beneficiary and signature, but the contract doesn't check
+        // them yet and mints to the sending account
         let accounts = self.web3.eth().accounts().await
             .map_err(|e| DemleError::NetworkError(format!("Failed to get accounts: {}", e)))?;
             